@@ -0,0 +1,646 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// drt-verify/src/lib.rs
+
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use reqwest::blocking::Client;
+use reqwest::Certificate;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs::read;
+
+/// Program ID of the deployed `drt-manager` Anchor program
+pub const DRT_MANAGER_PROGRAM_ID: &str = "CME2Dg7UEW82Hf99rQetEi7Hc5Db9JQPx6Azmx1eWbEE";
+
+/// Default Solana JSON-RPC endpoint, as served by `solana-test-validator`
+pub const DEFAULT_RPC_URL: &str = "http://127.0.0.1:8899";
+
+/// Decoded `DrtRedeemed` event emitted by `drt_manager::redeem_drt`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrtRedeemed {
+    pub pool: String, // Base58-encoded pool PDA
+    pub drt_type: String,
+    pub execution_type: String,
+    pub redeemer: String, // Base58-encoded wallet of the redeemer
+    pub github_url: Option<String>,
+    pub code_hash: Option<String>,
+    pub timestamp: i64,
+}
+
+/// Values a `DrtRedeemed` event must carry to authorise a request
+pub struct ExpectedRedemption<'a> {
+    pub pool: &'a str,
+    pub drt_type: &'a str,
+    pub execution_type: &'a str,
+    pub github_url: Option<&'a str>,
+    pub code_hash: Option<&'a str>,
+}
+
+impl DrtRedeemed {
+    /// Checks that the redemption matches what the request is asking for
+    pub fn check(&self, expected: &ExpectedRedemption) -> Result<()> {
+        if self.pool != expected.pool {
+            return Err(anyhow!(
+                "Redemption is for pool {}, not {}",
+                self.pool,
+                expected.pool
+            ));
+        }
+        if self.drt_type != expected.drt_type {
+            return Err(anyhow!(
+                "Redemption is for DRT type '{}', not '{}'",
+                self.drt_type,
+                expected.drt_type
+            ));
+        }
+        if self.execution_type != expected.execution_type {
+            return Err(anyhow!(
+                "Redemption is for execution type '{}', not '{}'",
+                self.execution_type,
+                expected.execution_type
+            ));
+        }
+        if let Some(github_url) = expected.github_url {
+            if self.github_url.as_deref() != Some(github_url) {
+                return Err(anyhow!(
+                    "Redemption does not cover code at '{}'",
+                    github_url
+                ));
+            }
+        }
+        if let Some(code_hash) = expected.code_hash {
+            let matches = self
+                .code_hash
+                .as_deref()
+                .is_some_and(|hash| hash.eq_ignore_ascii_case(code_hash));
+            if !matches {
+                return Err(anyhow!(
+                    "Redemption does not cover code hash '{}'",
+                    code_hash
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Verifies DRT redemptions and pool ownership by looking up `drt-manager`
+/// transactions and accounts over Solana JSON-RPC
+pub struct RedemptionVerifier {
+    rpc_url: String,
+    program_id: String,
+    client: Client,
+}
+
+impl RedemptionVerifier {
+    /// Creates a verifier for the given RPC endpoint and `drt-manager` program ID
+    pub fn new(rpc_url: &str, program_id: &str) -> Result<Self> {
+        let mut builder = Client::builder().use_rustls_tls();
+
+        // Trust the system CA bundle for HTTPS endpoints, if one is available
+        if rpc_url.starts_with("https://") {
+            let ca_bundle = read("/etc/ssl/certs/ca-certificates.crt")
+                .map_err(|e| anyhow!("Failed to read CA certificates: {}", e))?;
+            let ca_cert = Certificate::from_pem(&ca_bundle)
+                .map_err(|e| anyhow!("Failed to create certificate from PEM: {}", e))?;
+            builder = builder.add_root_certificate(ca_cert);
+        }
+
+        let client = builder
+            .build()
+            .map_err(|e| anyhow!("Failed to build HTTP client: {}", e))?;
+
+        Ok(Self {
+            rpc_url: rpc_url.to_string(),
+            program_id: program_id.to_string(),
+            client,
+        })
+    }
+
+    /// Fetches the transaction with the given signature and checks its `DrtRedeemed` event
+    pub fn verify(&self, signature: &str, expected: &ExpectedRedemption) -> Result<DrtRedeemed> {
+        let redemption = self.fetch_redemption(signature)?;
+        redemption.check(expected)?;
+        Ok(redemption)
+    }
+
+    /// Fetches the transaction with the given signature and decodes its `DrtRedeemed` event
+    pub fn fetch_redemption(&self, signature: &str) -> Result<DrtRedeemed> {
+        let params = json!([
+            signature,
+            {
+                "encoding": "json",
+                "commitment": "finalized",
+                "maxSupportedTransactionVersion": 0
+            }
+        ]);
+
+        let result = self.call("getTransaction", params)?;
+        parse_redemption(signature, &result, &self.program_id)
+    }
+
+    /// Returns the Unix time of the latest finalized block, as recorded on-chain
+    pub fn fetch_chain_time(&self) -> Result<i64> {
+        let slot = self.call("getSlot", json!([{ "commitment": "finalized" }]))?;
+        let slot = slot
+            .as_u64()
+            .ok_or_else(|| anyhow!("Invalid slot: {}", slot))?;
+        let block_time = self.call("getBlockTime", json!([slot]))?;
+        block_time
+            .as_i64()
+            .ok_or_else(|| anyhow!("No block time for slot {}", slot))
+    }
+
+    /// Fetches the `Pool` account at the given PDA and returns its base58-encoded owner
+    pub fn fetch_pool_owner(&self, pool: &str) -> Result<String> {
+        let params = json!([
+            pool,
+            {
+                "encoding": "base64",
+                "commitment": "finalized"
+            }
+        ]);
+
+        let result = self.call("getAccountInfo", params)?;
+        let account = result
+            .get("value")
+            .filter(|value| !value.is_null())
+            .ok_or_else(|| anyhow!("Pool account {} not found", pool))?;
+
+        // Only the program may write its accounts, so anything else could be forged
+        if account.get("owner").and_then(Value::as_str) != Some(self.program_id.as_str()) {
+            return Err(anyhow!("Account {} is not owned by the DRT manager", pool));
+        }
+
+        let data = account
+            .get("data")
+            .and_then(|data| data.get(0))
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("Pool account {} has no data", pool))?;
+        let bytes = BASE64
+            .decode(data)
+            .map_err(|e| anyhow!("Invalid account data: {}", e))?;
+
+        if !bytes.starts_with(&discriminator("account:Pool")) {
+            return Err(anyhow!("Account {} is not a pool", pool));
+        }
+        decode_pool_owner(&bytes[8..])
+    }
+
+    /// Sends a JSON-RPC request and returns its result
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        let mut response: Value = self
+            .client
+            .post(&self.rpc_url)
+            .json(&request)
+            .send()
+            .map_err(|e| anyhow!("RPC request failed: {}", e))?
+            .json()
+            .map_err(|e| anyhow!("Failed to parse RPC response: {}", e))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!("RPC error: {}", error));
+        }
+
+        Ok(response
+            .get_mut("result")
+            .map(Value::take)
+            .unwrap_or_default())
+    }
+}
+
+/// Returns the 8-byte Anchor discriminator for the given preimage, such as
+/// `event:DrtRedeemed` or `account:Pool`
+fn discriminator(preimage: &str) -> [u8; 8] {
+    let hash = Sha256::digest(preimage.as_bytes());
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// Decodes the `DrtRedeemed` event of a `getTransaction` result, checking that
+/// the transaction succeeded and that `program_id` emitted the event
+pub fn parse_redemption(
+    signature: &str,
+    transaction: &Value,
+    program_id: &str,
+) -> Result<DrtRedeemed> {
+    if transaction.is_null() {
+        return Err(anyhow!(
+            "Transaction {} not found or not finalized",
+            signature
+        ));
+    }
+
+    let meta = transaction
+        .get("meta")
+        .ok_or_else(|| anyhow!("Transaction {} has no status metadata", signature))?;
+
+    if !meta.get("err").is_none_or(Value::is_null) {
+        return Err(anyhow!("Transaction {} failed on-chain", signature));
+    }
+
+    let logs: Vec<&str> = meta
+        .get("logMessages")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("Transaction {} has no log messages", signature))?
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+
+    find_drt_redeemed(&logs, program_id)?
+        .ok_or_else(|| anyhow!("Transaction {} did not redeem a DRT", signature))
+}
+
+/// Line the Solana runtime logs when a program starts, returns or fails
+#[derive(Debug, PartialEq, Eq)]
+enum ProgramLog<'a> {
+    Invoke(&'a str, usize), // Program ID and invocation depth, starting at 1
+    Success,
+    Failed,
+}
+
+impl<'a> ProgramLog<'a> {
+    /// Parses the exact formats of `Program <id> invoke [<depth>]`,
+    /// `Program <id> success` and `Program <id> failed: <error>`.
+    ///
+    /// Programs can only log lines starting with `Program log: `, `Program data: `
+    /// or `Program return: `, none of which hold a valid program ID in place of
+    /// `<id>`, so they can never be mistaken for one of these.
+    fn parse(line: &'a str) -> Option<Self> {
+        let (program_id, status) = line.strip_prefix("Program ")?.split_once(' ')?;
+        let is_program_id = bs58::decode(program_id)
+            .into_vec()
+            .is_ok_and(|bytes| bytes.len() == 32);
+        if !is_program_id {
+            return None;
+        }
+
+        if let Some(depth) = status
+            .strip_prefix("invoke [")
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            return depth
+                .parse()
+                .ok()
+                .map(|depth| ProgramLog::Invoke(program_id, depth));
+        }
+        match status {
+            "success" => Some(ProgramLog::Success),
+            _ if status.starts_with("failed: ") => Some(ProgramLog::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Scans transaction logs for a `DrtRedeemed` event emitted by the given program.
+///
+/// Anchor emits events as `Program data: <base64>` lines. Only lines logged while
+/// `program_id` is the innermost executing program are considered, so that other
+/// programs in the same transaction cannot forge a redemption. Logs whose
+/// invocations do not nest properly, e.g. because they were truncated, are
+/// rejected rather than guessed at.
+pub fn find_drt_redeemed(logs: &[&str], program_id: &str) -> Result<Option<DrtRedeemed>> {
+    let mut call_stack: Vec<bool> = Vec::new();
+
+    for line in logs {
+        match ProgramLog::parse(line) {
+            Some(ProgramLog::Invoke(invoked, depth)) => {
+                if depth != call_stack.len() + 1 {
+                    return Err(anyhow!(
+                        "Program invoked at depth {} inside {} calls",
+                        depth,
+                        call_stack.len()
+                    ));
+                }
+                call_stack.push(invoked == program_id);
+            }
+            Some(ProgramLog::Success | ProgramLog::Failed) => {
+                if call_stack.pop().is_none() {
+                    return Err(anyhow!("Program returned without being invoked"));
+                }
+            }
+            None => {
+                let Some(data) = line.strip_prefix("Program data: ") else {
+                    continue;
+                };
+                if call_stack.last() != Some(&true) {
+                    continue;
+                }
+                let bytes = BASE64
+                    .decode(data.trim())
+                    .map_err(|e| anyhow!("Invalid event data: {}", e))?;
+                if bytes.starts_with(&discriminator("event:DrtRedeemed")) {
+                    return decode_drt_redeemed(&bytes[8..]).map(Some);
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Decodes the Borsh-serialised body of a `DrtRedeemed` event (without discriminator)
+pub fn decode_drt_redeemed(data: &[u8]) -> Result<DrtRedeemed> {
+    let mut reader = BorshReader { data };
+    let event = DrtRedeemed {
+        pool: reader.read_pubkey()?,
+        drt_type: reader.read_string()?,
+        execution_type: reader.read_string()?,
+        redeemer: reader.read_pubkey()?,
+        github_url: reader.read_option_string()?,
+        code_hash: reader.read_option_string()?,
+        timestamp: reader.read_i64()?,
+    };
+    Ok(event)
+}
+
+/// Decodes the owner from the Borsh-serialised body of a `Pool` account (without discriminator)
+pub fn decode_pool_owner(data: &[u8]) -> Result<String> {
+    let mut reader = BorshReader { data };
+    reader.take(1)?; // PDA bump
+    reader.read_string()?; // Pool name
+    reader.read_pubkey()
+}
+
+/// Minimal reader for the Borsh types used by `drt-manager` events and accounts
+struct BorshReader<'a> {
+    data: &'a [u8],
+}
+
+impl BorshReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        if self.data.len() < len {
+            return Err(anyhow!("Unexpected end of Borsh data"));
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn read_pubkey(&mut self) -> Result<String> {
+        Ok(bs58::encode(self.take(32)?).into_string())
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_i64(&mut self) -> Result<i64> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(i64::from_le_bytes(bytes))
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        let len = self.read_u32()? as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|e| anyhow!("Invalid UTF-8 in Borsh string: {}", e))
+    }
+
+    fn read_option_string(&mut self) -> Result<Option<String>> {
+        match self.take(1)?[0] {
+            0 => Ok(None),
+            1 => self.read_string().map(Some),
+            tag => Err(anyhow!("Invalid option tag {}", tag)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POOL: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+    const REDEEMER: &str = "FAe4sisG95oZ42w7buUn5qEE4TAnfTTFPiguZUHmhiF";
+    const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+    const OTHER_PROGRAM_ID: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+    const SIGNATURE: &str =
+        "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW";
+
+    fn borsh_string(value: &str) -> Vec<u8> {
+        let mut bytes = (value.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(value.as_bytes());
+        bytes
+    }
+
+    /// `Program data:` line of a `DrtRedeemed` event for a WASM computation
+    fn event_line() -> String {
+        let mut event = discriminator("event:DrtRedeemed").to_vec();
+        event.extend(bs58::decode(POOL).into_vec().unwrap());
+        event.extend(borsh_string("compute"));
+        event.extend(borsh_string("wasm"));
+        event.extend(bs58::decode(REDEEMER).into_vec().unwrap());
+        event.push(1);
+        event.extend(borsh_string("https://github.com/ntls-io/wasm-mean"));
+        event.push(1);
+        event.extend(borsh_string("ab12"));
+        event.extend(1_760_000_000i64.to_le_bytes());
+        format!("Program data: {}", BASE64.encode(event))
+    }
+
+    /// `getTransaction` result, as returned with `"encoding": "json"`
+    fn transaction(err: Value, logs: &[String]) -> Value {
+        json!({
+            "slot": 342_118_204,
+            "blockTime": 1_760_000_001,
+            "meta": {
+                "err": err,
+                "fee": 5000,
+                "computeUnitsConsumed": 41_326,
+                "innerInstructions": [],
+                "logMessages": logs,
+                "postBalances": [],
+                "preBalances": [],
+                "status": if err.is_null() { json!({ "Ok": null }) } else { json!({ "Err": err }) }
+            },
+            "transaction": {
+                "message": { "accountKeys": [REDEEMER, POOL, DRT_MANAGER_PROGRAM_ID] },
+                "signatures": [SIGNATURE]
+            },
+            "version": 0
+        })
+    }
+
+    /// Logs of a successful `redeem_drt` instruction that burns the DRT
+    fn redeem_logs() -> Vec<String> {
+        vec![
+            format!("Program {} invoke [1]", DRT_MANAGER_PROGRAM_ID),
+            "Program log: Instruction: RedeemDrt".to_string(),
+            format!("Program {} invoke [2]", TOKEN_PROGRAM_ID),
+            "Program log: Instruction: Burn".to_string(),
+            format!(
+                "Program {} consumed 4753 of 183620 compute units",
+                TOKEN_PROGRAM_ID
+            ),
+            format!("Program {} success", TOKEN_PROGRAM_ID),
+            "Program log: Redeemed wasm compute operation".to_string(),
+            event_line(),
+            format!(
+                "Program {} consumed 41326 of 200000 compute units",
+                DRT_MANAGER_PROGRAM_ID
+            ),
+            format!("Program {} success", DRT_MANAGER_PROGRAM_ID),
+        ]
+    }
+
+    fn parse(transaction: &Value) -> Result<DrtRedeemed> {
+        parse_redemption(SIGNATURE, transaction, DRT_MANAGER_PROGRAM_ID)
+    }
+
+    #[test]
+    fn decodes_redemption() {
+        let redemption = parse(&transaction(Value::Null, &redeem_logs())).unwrap();
+        assert_eq!(
+            redemption,
+            DrtRedeemed {
+                pool: POOL.to_string(),
+                drt_type: "compute".to_string(),
+                execution_type: "wasm".to_string(),
+                redeemer: REDEEMER.to_string(),
+                github_url: Some("https://github.com/ntls-io/wasm-mean".to_string()),
+                code_hash: Some("ab12".to_string()),
+                timestamp: 1_760_000_000,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_missing_or_failed_transaction() {
+        assert!(parse(&Value::Null).is_err());
+
+        let err = json!({ "InstructionError": [0, { "Custom": 6000 }] });
+        assert!(parse(&transaction(err, &redeem_logs())).is_err());
+    }
+
+    #[test]
+    fn ignores_event_emitted_by_another_program() {
+        let logs = vec![
+            format!("Program {} invoke [1]", OTHER_PROGRAM_ID),
+            event_line(),
+            format!("Program {} success", OTHER_PROGRAM_ID),
+        ];
+        assert!(parse(&transaction(Value::Null, &logs)).is_err());
+    }
+
+    #[test]
+    fn ignores_event_emitted_by_program_called_from_drt_manager() {
+        let logs = vec![
+            format!("Program {} invoke [1]", DRT_MANAGER_PROGRAM_ID),
+            format!("Program {} invoke [2]", OTHER_PROGRAM_ID),
+            event_line(),
+            format!("Program {} success", OTHER_PROGRAM_ID),
+            format!("Program {} success", DRT_MANAGER_PROGRAM_ID),
+        ];
+        assert!(parse(&transaction(Value::Null, &logs)).is_err());
+    }
+
+    #[test]
+    fn logged_text_does_not_change_call_stack() {
+        // A program called by drt-manager logs a fake return, so that its forged
+        // event would look as if drt-manager emitted it
+        let logs = vec![
+            format!("Program {} invoke [1]", DRT_MANAGER_PROGRAM_ID),
+            format!("Program {} invoke [2]", OTHER_PROGRAM_ID),
+            format!("Program log: Program {} success", OTHER_PROGRAM_ID),
+            event_line(),
+            format!("Program {} success", OTHER_PROGRAM_ID),
+            format!("Program {} success", DRT_MANAGER_PROGRAM_ID),
+        ];
+        assert!(parse(&transaction(Value::Null, &logs)).is_err());
+    }
+
+    #[test]
+    fn rejects_logs_that_do_not_nest() {
+        let mut logs = redeem_logs();
+        logs[2] = format!("Program {} invoke [3]", TOKEN_PROGRAM_ID);
+        assert!(find_drt_redeemed(&to_strs(&logs), DRT_MANAGER_PROGRAM_ID).is_err());
+
+        let logs = vec![format!("Program {} success", DRT_MANAGER_PROGRAM_ID)];
+        assert!(find_drt_redeemed(&to_strs(&logs), DRT_MANAGER_PROGRAM_ID).is_err());
+    }
+
+    #[test]
+    fn parses_runtime_log_formats() {
+        let invoke = format!("Program {} invoke [2]", TOKEN_PROGRAM_ID);
+        let failed = format!(
+            "Program {} failed: custom program error: 0x1",
+            TOKEN_PROGRAM_ID
+        );
+        let success = format!("Program {} success", TOKEN_PROGRAM_ID);
+        assert_eq!(
+            ProgramLog::parse(&invoke),
+            Some(ProgramLog::Invoke(TOKEN_PROGRAM_ID, 2))
+        );
+        assert_eq!(ProgramLog::parse(&failed), Some(ProgramLog::Failed));
+        assert_eq!(ProgramLog::parse(&success), Some(ProgramLog::Success));
+
+        for line in [
+            "Program log: success",
+            "Program data: invoke [1]",
+            "Program return: TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA AQ==",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA consumed 4753 of 183620 compute units",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [x]",
+        ] {
+            assert_eq!(ProgramLog::parse(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn checks_expected_redemption() {
+        let redemption = parse(&transaction(Value::Null, &redeem_logs())).unwrap();
+        let expected = ExpectedRedemption {
+            pool: POOL,
+            drt_type: "compute",
+            execution_type: "wasm",
+            github_url: Some("https://github.com/ntls-io/wasm-mean"),
+            code_hash: Some("AB12"),
+        };
+        assert!(redemption.check(&expected).is_ok());
+
+        let other_pool = ExpectedRedemption {
+            pool: OTHER_PROGRAM_ID,
+            ..expected
+        };
+        assert!(redemption.check(&other_pool).is_err());
+        let other_code = ExpectedRedemption {
+            code_hash: Some("cd34"),
+            ..other_pool
+        };
+        assert!(redemption
+            .check(&ExpectedRedemption {
+                pool: POOL,
+                ..other_code
+            })
+            .is_err());
+    }
+
+    fn to_strs(logs: &[String]) -> Vec<&str> {
+        logs.iter().map(String::as_str).collect()
+    }
+}
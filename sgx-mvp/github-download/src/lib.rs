@@ -15,17 +15,34 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use anyhow::{anyhow, Result};
-use reqwest::blocking::Client;
+use reqwest::blocking::{Client, Response};
 use reqwest::Certificate;
 use sha2::{Digest, Sha256};
 use std::fs::{read, File};
-use std::io::Write;
+use std::io::{Read, Write};
+
+/// Reads a response body, failing if it is larger than `max_bytes`
+fn read_limited(response: Response, max_bytes: u64) -> Result<Vec<u8>> {
+    if response.content_length().is_some_and(|len| len > max_bytes) {
+        return Err(anyhow!("Download exceeds the limit of {} bytes", max_bytes));
+    }
+    let mut body = Vec::new();
+    response
+        .take(max_bytes + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| anyhow!("Failed to read response body: {}", e))?;
+    if body.len() as u64 > max_bytes {
+        return Err(anyhow!("Download exceeds the limit of {} bytes", max_bytes));
+    }
+    Ok(body)
+}
 
 /// Helper function to download a Python script from GitHub, calculate its hash, and verify integrity
 pub fn verify_and_download_python_github(
     github_url: &str,
     save_path: &str,
     expected_hash: &str,
+    max_bytes: u64,
 ) -> Result<()> {
 
     // Convert the GitHub URL to the raw URL
@@ -50,8 +67,8 @@ pub fn verify_and_download_python_github(
     let response = client
         .get(raw_url.clone())
         .send()
-        .map_err(|e| anyhow!("HTTP GET request failed: {}", e))?
-        .text()
+        .map_err(|e| anyhow!("HTTP GET request failed: {}", e))?;
+    let response = String::from_utf8(read_limited(response, max_bytes)?)
         .map_err(|e| anyhow!("Failed to read response text: {}", e))?;
 
     // Calculate SHA256 hash
@@ -84,6 +101,7 @@ pub fn verify_and_download_wasm(
     github_url: &str,
     save_path: &str,
     expected_hash: &str,
+    max_bytes: u64,
 ) -> Result<()> {
 
     // Convert the GitHub URL to the raw URL
@@ -106,11 +124,10 @@ pub fn verify_and_download_wasm(
 
     // Fetch the script
     let response = client
-        .get(&raw_url.clone())
+        .get(raw_url.clone())
         .send()
-        .map_err(|e| anyhow!("HTTP GET request failed: {}", e))?
-        .bytes()
-        .map_err(|e| anyhow!("Failed to read response bytes: {}", e))?;
+        .map_err(|e| anyhow!("HTTP GET request failed: {}", e))?;
+    let response = read_limited(response, max_bytes)?;
 
     // Calculate SHA256 hash
     let mut hasher = Sha256::new();
@@ -0,0 +1,219 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Compares data pools held as `serde_json::Value` trees with pools held as
+//! Arrow record batches, in memory and in time.
+//!
+//! Pools are decoded from the plaintext they are sealed as: JSON text for value
+//! trees, an Arrow IPC stream for record batches.
+//!
+//! Run with `cargo bench -p json-append`, optionally setting `BENCH_ROWS` to a
+//! comma-separated list of row counts.
+
+use arrow_array::RecordBatch;
+use arrow_ipc::reader::StreamReader;
+use arrow_ipc::writer::StreamWriter;
+use json_append::{
+    append_json, append_record_batch, columns_to_rows, to_record_batch, write_columns, write_rows,
+    ColumnPolicy,
+};
+use serde_json::{json, Value};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Number of runs of each timed operation, of which the fastest is reported
+const RUNS: usize = 5;
+
+/// Allocator that keeps track of the bytes allocated and their peak
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            let allocated = ALLOCATED.fetch_add(new_size, Ordering::Relaxed) + new_size;
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Runs `f`, returning its result with the bytes it left allocated and the
+/// most it had allocated at once
+fn measure<T>(f: impl FnOnce() -> T) -> (T, usize, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let result = f();
+    let retained = ALLOCATED.load(Ordering::Relaxed).saturating_sub(before);
+    let peak = PEAK.load(Ordering::Relaxed) - before;
+    (result, retained, peak)
+}
+
+/// Returns the fastest of several runs of `f`
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let started = Instant::now();
+            black_box(f());
+            started.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// Builds a columnar data pool of typical columns
+fn dataset(rows: usize) -> Value {
+    const CITIES: [&str; 4] = ["Cape Town", "Johannesburg", "Durban", "Pretoria"];
+    json!({
+        "id": (0..rows).collect::<Vec<_>>(),
+        "name": (0..rows).map(|row| format!("user-{:08}", row)).collect::<Vec<_>>(),
+        "city": (0..rows).map(|row| CITIES[row % CITIES.len()]).collect::<Vec<_>>(),
+        "amount": (0..rows).map(|row| row as f64 * 0.25 + 0.1).collect::<Vec<_>>(),
+        "active": (0..rows).map(|row| row % 3 == 0).collect::<Vec<_>>(),
+    })
+}
+
+/// Encodes a record batch as an Arrow IPC stream, as it is sealed
+fn encode_ipc(batch: &RecordBatch) -> Vec<u8> {
+    let mut ipc = Vec::new();
+    let mut writer = StreamWriter::try_new(&mut ipc, &batch.schema()).expect("writer starts");
+    writer.write(batch).expect("batch writes");
+    writer.finish().expect("stream finishes");
+    drop(writer);
+    ipc
+}
+
+/// Decodes a record batch from an Arrow IPC stream, as it is unsealed
+fn decode_ipc(ipc: &[u8]) -> RecordBatch {
+    let mut reader = StreamReader::try_new(ipc, None).expect("reader starts");
+    reader
+        .next()
+        .expect("stream holds a batch")
+        .expect("batch reads")
+}
+
+fn mib(bytes: usize) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn ms(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn bench(rows: usize) {
+    let text = serde_json::to_vec(&dataset(rows)).expect("dataset serializes");
+    let appended = dataset(rows / 100);
+    let policy = ColumnPolicy::Reject;
+
+    let ipc = {
+        let tree: Value = serde_json::from_slice(&text).expect("dataset parses");
+        encode_ipc(&to_record_batch(&tree, None).expect("dataset converts"))
+    };
+
+    // Memory held by the pool once unsealed, and the time unsealing takes
+    // beyond decryption
+    let (tree, tree_bytes, tree_peak) =
+        measure(|| serde_json::from_slice::<Value>(&text).expect("dataset parses"));
+    let (batch, batch_bytes, batch_peak) = measure(|| decode_ipc(&ipc));
+    let tree_decode = time(|| serde_json::from_slice::<Value>(&text));
+    let batch_decode = time(|| decode_ipc(&ipc));
+
+    // Time to serialize the pool for an executor
+    let tree_columns = time(|| serde_json::to_vec(&tree));
+    let batch_columns = time(|| {
+        let mut json_data = Vec::new();
+        write_columns(&batch, &mut json_data).map(|()| json_data)
+    });
+    let tree_rows = time(|| columns_to_rows(&tree).map(|rows| serde_json::to_vec(&rows)));
+    let batch_rows = time(|| {
+        let mut json_data = Vec::new();
+        write_rows(&batch, &mut json_data).map(|()| json_data)
+    });
+
+    // Time to append 1% more rows
+    let tree_append = time(|| append_json(&tree, &appended, &policy));
+    let batch_append = time(|| append_record_batch(&batch, &appended, None, &policy));
+
+    println!("\n{} rows\n", rows);
+    println!("| Measure | Value tree | Arrow record batch |");
+    println!("|---------|------------|--------------------|");
+    println!(
+        "| Sealed plaintext | {} | {} |",
+        mib(text.len()),
+        mib(ipc.len())
+    );
+    println!(
+        "| Memory held | {} | {} |",
+        mib(tree_bytes),
+        mib(batch_bytes)
+    );
+    println!(
+        "| Peak memory while decoding | {} | {} |",
+        mib(tree_peak),
+        mib(batch_peak)
+    );
+    println!(
+        "| Decode sealed plaintext | {} | {} |",
+        ms(tree_decode),
+        ms(batch_decode)
+    );
+    println!(
+        "| Write columns as JSON | {} | {} |",
+        ms(tree_columns),
+        ms(batch_columns)
+    );
+    println!(
+        "| Write rows as JSON | {} | {} |",
+        ms(tree_rows),
+        ms(batch_rows)
+    );
+    println!(
+        "| Append 1% rows | {} | {} |",
+        ms(tree_append),
+        ms(batch_append)
+    );
+}
+
+fn main() {
+    let rows = std::env::var("BENCH_ROWS").unwrap_or_else(|_| "10000,100000,1000000".to_string());
+    for rows in rows.split(',') {
+        let rows = rows.trim().parse().expect("BENCH_ROWS holds row counts");
+        bench(rows);
+    }
+}
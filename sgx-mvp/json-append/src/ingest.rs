@@ -0,0 +1,592 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Decoding of CSV and Parquet uploads into the columnar layout of data pools.
+
+use crate::record_batch_to_json;
+use crate::table::{schema_data_type, ByteCounter};
+use anyhow::{anyhow, Result};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float32Type, Float64Type, Int64Type, UInt64Type};
+use arrow_array::{Array, ArrayRef, RecordBatch, RecordBatchReader};
+use arrow_cast::cast;
+use arrow_cast::display::{ArrayFormatter, FormatOptions};
+use arrow_csv::reader::Format;
+use arrow_csv::ReaderBuilder;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use arrow_select::concat::concat_batches;
+use bytes::Bytes;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use serde::Deserialize;
+use serde_json::{Map, Number, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+/// How to read CSV data
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CsvOptions {
+    pub header: bool,                      // Whether the first line names the columns
+    pub delimiter: char,                   // Character between fields, which must be ASCII
+    pub column_names: Option<Vec<String>>, // Names of the columns in order, replacing the header
+    pub types: BTreeMap<String, CsvType>,  // Types of columns, overriding schema and inference
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            header: true,
+            delimiter: ',',
+            column_names: None,
+            types: BTreeMap::new(),
+        }
+    }
+}
+
+/// Type that a CSV column is read as, named as in JSON Schema
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvType {
+    Boolean,
+    Integer,
+    Number,
+    String,
+}
+
+impl CsvType {
+    fn data_type(self) -> DataType {
+        match self {
+            CsvType::Boolean => DataType::Boolean,
+            CsvType::Integer => DataType::Int64,
+            CsvType::Number => DataType::Float64,
+            CsvType::String => DataType::Utf8,
+        }
+    }
+}
+
+/// Decodes CSV text into the columnar layout of data pools, `{ "col": [..] }`.
+///
+/// Each column is read as the type that `options` gives it, else as the type
+/// that the pool's JSON Schema gives its values, else as the type its values
+/// suggest: booleans, integers or numbers, and strings exactly as written for
+/// anything else. Empty fields are read as null.
+pub fn csv_to_columns(text: &str, options: &CsvOptions, schema: Option<&Value>) -> Result<Value> {
+    let delimiter = u8::try_from(options.delimiter)
+        .ok()
+        .filter(u8::is_ascii)
+        .ok_or_else(|| anyhow!("CSV delimiter must be a single ASCII character"))?;
+    let format = Format::default()
+        .with_header(options.header)
+        .with_delimiter(delimiter);
+
+    // Infer types from all rows, then settle the name and type of each column
+    let (inferred, _) = format
+        .infer_schema(text.as_bytes(), None)
+        .map_err(|e| anyhow!("Invalid CSV: {}", e))?;
+    let names: Vec<String> = match &options.column_names {
+        Some(names) if names.len() != inferred.fields().len() => {
+            return Err(anyhow!(
+                "Expected {} CSV column names, got {}",
+                inferred.fields().len(),
+                names.len()
+            ));
+        }
+        Some(names) => names.clone(),
+        None => inferred
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect(),
+    };
+    let mut seen = HashSet::new();
+    if let Some(name) = names.iter().find(|name| !seen.insert(*name)) {
+        return Err(anyhow!("Column '{}' appears more than once", name));
+    }
+    if let Some(name) = options.types.keys().find(|name| !seen.contains(name)) {
+        return Err(anyhow!(
+            "Column '{}' of the CSV types is not in the data",
+            name
+        ));
+    }
+
+    let fields: Vec<Field> = inferred
+        .fields()
+        .iter()
+        .zip(&names)
+        .map(|(field, name)| {
+            let data_type = options
+                .types
+                .get(name)
+                .map(|csv_type| csv_type.data_type())
+                .or_else(|| schema_data_type(schema, name))
+                .unwrap_or_else(|| match field.data_type() {
+                    DataType::Boolean | DataType::Int64 | DataType::Float64 => {
+                        field.data_type().clone()
+                    }
+                    // Dates and times stay as written
+                    _ => DataType::Utf8,
+                });
+            Field::new(name, data_type, true)
+        })
+        .collect();
+
+    let schema = Arc::new(Schema::new(fields));
+    let batches = ReaderBuilder::new(schema.clone())
+        .with_format(format)
+        .build(text.as_bytes())
+        .and_then(|reader| reader.collect::<Result<Vec<RecordBatch>, _>>())
+        .map_err(|e| anyhow!("Invalid CSV: {}", e))?;
+    let batch = concat_batches(&schema, &batches)
+        .map_err(|e| anyhow!("Failed to combine CSV rows: {}", e))?;
+    record_batch_to_json(&batch)
+}
+
+/// Decodes a Parquet file into the columnar layout of data pools, `{ "col": [..] }`.
+///
+/// Booleans, integers, floating-point numbers and strings keep their values,
+/// lists and structs become arrays and objects, and other types, such as
+/// dates, times and decimals, become strings as Arrow displays them.
+///
+/// The file is decoded a batch of rows at a time, appending each to the
+/// columns, and decoding stops once the values take up more than `max_bytes`
+/// as JSON, so a small, highly compressed file cannot expand without bound.
+pub fn parquet_to_columns(file: Vec<u8>, max_bytes: usize) -> Result<Value> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(file))
+        .and_then(|builder| builder.build())
+        .map_err(|e| anyhow!("Invalid Parquet file: {}", e))?;
+    let schema: SchemaRef = reader.schema();
+
+    let mut seen = HashSet::new();
+    if let Some(field) = schema.fields().iter().find(|f| !seen.insert(f.name())) {
+        return Err(anyhow!("Column '{}' appears more than once", field.name()));
+    }
+
+    let mut columns: Vec<Vec<Value>> = vec![Vec::new(); schema.fields().len()];
+    let mut decoded_len = ByteCounter(0);
+    for batch in reader {
+        let batch = batch.map_err(|e| anyhow!("Invalid Parquet file: {}", e))?;
+        for (values, array) in columns.iter_mut().zip(batch.columns()) {
+            let batch_values = array_values(array)?;
+            for value in &batch_values {
+                serde_json::to_writer(&mut decoded_len, value)?;
+            }
+            if decoded_len.0 > max_bytes {
+                return Err(anyhow!(
+                    "Parquet data takes up more than {} bytes as JSON",
+                    max_bytes
+                ));
+            }
+            values.extend(batch_values);
+        }
+    }
+
+    let columns: Map<String, Value> = schema
+        .fields()
+        .iter()
+        .zip(columns)
+        .map(|(field, values)| (field.name().clone(), Value::Array(values)))
+        .collect();
+    Ok(Value::Object(columns))
+}
+
+/// Converts the values of an Arrow array to JSON, with nulls as `null`
+fn array_values(array: &ArrayRef) -> Result<Vec<Value>> {
+    let cast_to = |data_type: &DataType| {
+        cast(array, data_type).map_err(|e| anyhow!("Failed to convert Parquet column: {}", e))
+    };
+    let values = match array.data_type() {
+        DataType::Null => vec![Value::Null; array.len()],
+        DataType::Boolean => array
+            .as_boolean()
+            .iter()
+            .map(|value| value.map_or(Value::Null, Value::Bool))
+            .collect(),
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => {
+            cast_to(&DataType::Int64)?
+                .as_primitive::<Int64Type>()
+                .iter()
+                .map(|value| value.map_or(Value::Null, Value::from))
+                .collect()
+        }
+        DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => {
+            cast_to(&DataType::UInt64)?
+                .as_primitive::<UInt64Type>()
+                .iter()
+                .map(|value| value.map_or(Value::Null, Value::from))
+                .collect()
+        }
+        // Shortest decimal that reads back as the same single-precision value,
+        // rather than the exact value of its double-precision widening
+        DataType::Float16 | DataType::Float32 => cast_to(&DataType::Float32)?
+            .as_primitive::<Float32Type>()
+            .iter()
+            .map(|value| float_value(value.and_then(|value| value.to_string().parse().ok())))
+            .collect(),
+        DataType::Float64 => array
+            .as_primitive::<Float64Type>()
+            .iter()
+            .map(float_value)
+            .collect(),
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => cast_to(&DataType::Utf8)?
+            .as_string::<i32>()
+            .iter()
+            .map(|value| value.map_or(Value::Null, |value| Value::String(value.to_string())))
+            .collect(),
+        DataType::List(_) => list_values(array.as_list::<i32>().iter())?,
+        DataType::LargeList(_) => list_values(array.as_list::<i64>().iter())?,
+        DataType::FixedSizeList(..) => list_values(array.as_fixed_size_list().iter())?,
+        DataType::Struct(fields) => {
+            let children = array
+                .as_struct()
+                .columns()
+                .iter()
+                .map(array_values)
+                .collect::<Result<Vec<_>>>()?;
+            (0..array.len())
+                .map(|row| {
+                    if array.is_null(row) {
+                        return Value::Null;
+                    }
+                    let object = fields
+                        .iter()
+                        .zip(&children)
+                        .map(|(field, values)| (field.name().clone(), values[row].clone()))
+                        .collect();
+                    Value::Object(object)
+                })
+                .collect()
+        }
+        DataType::Dictionary(_, value_type) => array_values(&cast_to(value_type)?)?,
+        _ => {
+            let formatter = ArrayFormatter::try_new(array, &FormatOptions::default())
+                .map_err(|e| anyhow!("Failed to convert Parquet column: {}", e))?;
+            (0..array.len())
+                .map(|row| {
+                    if array.is_null(row) {
+                        return Value::Null;
+                    }
+                    Value::String(formatter.value(row).to_string())
+                })
+                .collect()
+        }
+    };
+    Ok(values)
+}
+
+/// Converts the lists of a list array to JSON arrays
+fn list_values(lists: impl Iterator<Item = Option<ArrayRef>>) -> Result<Vec<Value>> {
+    lists
+        .map(|list| match list {
+            Some(list) => array_values(&list).map(Value::Array),
+            None => Ok(Value::Null),
+        })
+        .collect()
+}
+
+/// Converts a float to JSON, with NaN and infinities as `null`
+fn float_value(value: Option<f64>) -> Value {
+    value
+        .and_then(Number::from_f64)
+        .map_or(Value::Null, Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::builder::{Int64Builder, ListBuilder};
+    use arrow_array::{
+        BooleanArray, Date32Array, Float32Array, Float64Array, Int32Array, Int64Array, StringArray,
+        StructArray, UInt64Array,
+    };
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::Compression;
+    use parquet::file::properties::WriterProperties;
+    use serde_json::json;
+
+    const MAX_BYTES: usize = 1024 * 1024;
+
+    fn csv(text: &str, options: &CsvOptions) -> Result<Value> {
+        csv_to_columns(text, options, None)
+    }
+
+    #[test]
+    fn csv_values_take_the_type_they_suggest() {
+        let text = "a,b,c,d,e\n1,2.5,true,x,2024-01-01\n2,,false,y,2024-01-02\n";
+        assert_eq!(
+            csv(text, &CsvOptions::default()).unwrap(),
+            json!({
+                "a": [1, 2],
+                "b": [2.5, null],
+                "c": [true, false],
+                "d": ["x", "y"],
+                "e": ["2024-01-01", "2024-01-02"],
+            })
+        );
+    }
+
+    #[test]
+    fn csv_delimiter_option() {
+        let options = CsvOptions {
+            delimiter: ';',
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            csv("a;b\n1,5;x\n", &options).unwrap(),
+            json!({ "a": ["1,5"], "b": ["x"] })
+        );
+
+        let options = CsvOptions {
+            delimiter: '\t',
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            csv("a\tb\n1\t2\n", &options).unwrap(),
+            json!({ "a": [1], "b": [2] })
+        );
+
+        let options = CsvOptions {
+            delimiter: '\u{e9}',
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            csv("a\n1\n", &options).unwrap_err().to_string(),
+            "CSV delimiter must be a single ASCII character"
+        );
+    }
+
+    #[test]
+    fn csv_column_names_option() {
+        let options = CsvOptions {
+            header: false,
+            column_names: Some(vec!["x".to_string(), "y".to_string()]),
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            csv("1,a\n2,b\n", &options).unwrap(),
+            json!({ "x": [1, 2], "y": ["a", "b"] })
+        );
+
+        // Names replace the header
+        let options = CsvOptions {
+            column_names: Some(vec!["x".to_string(), "y".to_string()]),
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            csv("a,b\n1,2\n", &options).unwrap(),
+            json!({ "x": [1], "y": [2] })
+        );
+
+        let options = CsvOptions {
+            column_names: Some(vec!["x".to_string()]),
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            csv("a,b\n1,2\n", &options).unwrap_err().to_string(),
+            "Expected 2 CSV column names, got 1"
+        );
+        assert_eq!(
+            csv("a,a\n1,2\n", &CsvOptions::default())
+                .unwrap_err()
+                .to_string(),
+            "Column 'a' appears more than once"
+        );
+    }
+
+    #[test]
+    fn csv_types_option() {
+        let options = CsvOptions {
+            types: BTreeMap::from([
+                ("code".to_string(), CsvType::String),
+                ("count".to_string(), CsvType::Number),
+                ("flag".to_string(), CsvType::Boolean),
+            ]),
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            csv("code,count,flag\n0012,3,true\n0034,4.5,\n", &options).unwrap(),
+            json!({
+                "code": ["0012", "0034"],
+                "count": [3.0, 4.5],
+                "flag": [true, null],
+            })
+        );
+
+        let options = CsvOptions {
+            types: BTreeMap::from([("n".to_string(), CsvType::Integer)]),
+            ..CsvOptions::default()
+        };
+        assert!(csv("n\n1.5\n", &options)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid CSV:"));
+
+        let options = CsvOptions {
+            types: BTreeMap::from([("missing".to_string(), CsvType::String)]),
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            csv("a\n1\n", &options).unwrap_err().to_string(),
+            "Column 'missing' of the CSV types is not in the data"
+        );
+    }
+
+    #[test]
+    fn csv_columns_follow_the_schema_unless_typed() {
+        let schema = json!({
+            "properties": {
+                "code": { "items": { "type": "string" } },
+                "n": { "items": { "type": "number" } },
+                "typed": { "items": { "type": "string" } },
+            }
+        });
+        let options = CsvOptions {
+            types: BTreeMap::from([("typed".to_string(), CsvType::Integer)]),
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            csv_to_columns("code,n,typed\n0012,1,7\n", &options, Some(&schema)).unwrap(),
+            json!({ "code": ["0012"], "n": [1], "typed": [7] })
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_csv() {
+        let error = csv("a,b\n1,2\n3\n", &CsvOptions::default()).unwrap_err();
+        assert!(error.to_string().starts_with("Invalid CSV:"));
+    }
+
+    /// Writes a record batch as a Parquet file with the given compression
+    fn parquet(batch: &RecordBatch, compression: Compression) -> Vec<u8> {
+        let properties = WriterProperties::builder()
+            .set_compression(compression)
+            .build();
+        let mut file = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut file, batch.schema(), Some(properties)).unwrap();
+        writer.write(batch).unwrap();
+        writer.close().unwrap();
+        file
+    }
+
+    #[test]
+    fn parquet_columns_keep_their_values() {
+        let mut lists = ListBuilder::new(Int64Builder::new());
+        lists.append_value([Some(1), Some(2)]);
+        lists.append_null();
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            (
+                "int",
+                Arc::new(Int64Array::from(vec![Some(9007199254740993), None])),
+            ),
+            ("float", Arc::new(Float64Array::from(vec![0.5, 2.0]))),
+            ("flag", Arc::new(BooleanArray::from(vec![true, false]))),
+            ("text", Arc::new(StringArray::from(vec![Some("x"), None]))),
+            ("date", Arc::new(Date32Array::from(vec![19723, 0]))),
+            ("list", Arc::new(lists.finish())),
+        ];
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+        let expected = json!({
+            "int": [9007199254740993_i64, null],
+            "float": [0.5, 2.0],
+            "flag": [true, false],
+            "text": ["x", null],
+            "date": ["2024-01-01", "1970-01-01"],
+            "list": [[1, 2], null],
+        });
+
+        for compression in [
+            Compression::UNCOMPRESSED,
+            Compression::SNAPPY,
+            Compression::LZ4_RAW,
+            Compression::ZSTD(Default::default()),
+            Compression::GZIP(Default::default()),
+            Compression::BROTLI(Default::default()),
+        ] {
+            assert_eq!(
+                parquet_to_columns(parquet(&batch, compression), MAX_BYTES).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn parquet_nested_and_narrow_values() {
+        let mut lists = ListBuilder::new(Int64Builder::new());
+        lists.append_value([Some(3)]);
+        lists.append_value([None]);
+        let tags: ArrayRef = Arc::new(lists.finish());
+        let point: ArrayRef = Arc::new(StructArray::from(vec![
+            (
+                Arc::new(Field::new("x", DataType::Int32, true)),
+                Arc::new(Int32Array::from(vec![1, 2])) as ArrayRef,
+            ),
+            (
+                Arc::new(Field::new("tags", tags.data_type().clone(), true)),
+                tags,
+            ),
+        ]));
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            ("point", point),
+            ("single", Arc::new(Float32Array::from(vec![0.1, f32::NAN]))),
+            ("unsigned", Arc::new(UInt64Array::from(vec![u64::MAX, 0]))),
+        ];
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+        assert_eq!(
+            parquet_to_columns(parquet(&batch, Compression::SNAPPY), MAX_BYTES).unwrap(),
+            json!({
+                "point": [{ "x": 1, "tags": [3] }, { "x": 2, "tags": [null] }],
+                "single": [0.1, null],
+                "unsigned": [u64::MAX, 0],
+            })
+        );
+    }
+
+    #[test]
+    fn parquet_decoding_stops_at_the_size_limit() {
+        // Repeated values compress to a tiny file that decodes to over 1 MiB
+        let rows = 20_000;
+        let text: ArrayRef = Arc::new(StringArray::from(vec!["x".repeat(100); rows]));
+        let batch = RecordBatch::try_from_iter(vec![("text", text)]).unwrap();
+        let file = parquet(&batch, Compression::ZSTD(Default::default()));
+        assert!(file.len() < MAX_BYTES / 10);
+
+        let error = parquet_to_columns(file.clone(), MAX_BYTES).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Parquet data takes up more than 1048576 bytes as JSON"
+        );
+        let columns = parquet_to_columns(file, 4 * MAX_BYTES).unwrap();
+        assert_eq!(columns["text"].as_array().unwrap().len(), rows);
+    }
+
+    #[test]
+    fn empty_parquet_file_keeps_its_columns() {
+        let batch = RecordBatch::try_from_iter(vec![(
+            "a",
+            Arc::new(Int64Array::from(Vec::<i64>::new())) as ArrayRef,
+        )])
+        .unwrap();
+        assert_eq!(
+            parquet_to_columns(parquet(&batch, Compression::SNAPPY), MAX_BYTES).unwrap(),
+            json!({ "a": [] })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_parquet_file() {
+        let error = parquet_to_columns(b"not a parquet file".to_vec(), MAX_BYTES).unwrap_err();
+        assert!(error.to_string().starts_with("Invalid Parquet file:"));
+    }
+}
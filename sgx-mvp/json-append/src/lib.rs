@@ -14,44 +14,446 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod ingest;
+mod table;
+
+pub use ingest::{csv_to_columns, parquet_to_columns, CsvOptions, CsvType};
+pub use table::{
+    append_record_batch, appended_json_len, columns_json_len, record_batch_to_json,
+    to_record_batch, write_columns, write_rows,
+};
+
 use anyhow::{anyhow, Result};
-use serde_json::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// What an append does with columns that the data pool has but the new data
+/// leaves out, or that the new data adds
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnPolicy {
+    /// Reject the append
+    #[default]
+    Reject,
+    /// Fill the rows that have no value for a column with null
+    BackfillNull,
+    /// Fill the rows that have no value for a column with the given value
+    BackfillDefault(Value),
+}
+
+impl ColumnPolicy {
+    /// Returns the value filling the rows of a column, or `None` if the policy
+    /// does not fill in columns
+    pub fn fill_value(&self) -> Option<&Value> {
+        static NULL: Value = Value::Null;
+        match self {
+            ColumnPolicy::Reject => None,
+            ColumnPolicy::BackfillNull => Some(&NULL),
+            ColumnPolicy::BackfillDefault(value) => Some(value),
+        }
+    }
 
-/// Function to append second JSON's columnar data to the first, expanding the columns
-pub fn append_json(json1: &Value, json2: &Value) -> Result<Value> {
-    // Ensure both inputs are objects with arrays as values
-    if !json1.is_object() || !json2.is_object() {
-        return Err(anyhow!("JSON data should be objects with arrays as values"));
+    /// Returns the values filling `count` rows of a column, or `None` if the
+    /// policy does not fill in columns
+    fn backfill(&self, count: usize) -> Option<Vec<Value>> {
+        self.fill_value().map(|value| vec![value.clone(); count])
     }
+}
 
+/// Function to append second JSON's data to the first's columns, expanding the columns.
+///
+/// The second JSON can be columnar, an array of records or NDJSON text (see
+/// `to_columnar`). Columns that only one of them has are handled as `policy`
+/// says, and all columns must hold the same number of values afterwards, so
+/// that every row stays aligned.
+pub fn append_json(json1: &Value, json2: &Value, policy: &ColumnPolicy) -> Result<Value> {
+    // Ensure the existing data is an object with arrays as values
     let obj1 = json1
         .as_object()
-        .ok_or_else(|| anyhow!("Expected an object in json1"))?;
+        .ok_or_else(|| anyhow!("JSON data should be objects with arrays as values"))?;
+    let existing_rows = count_rows(obj1).map_err(|e| anyhow!("Data pool is not aligned: {}", e))?;
+
+    // Bring the new data into the columnar layout of the existing data
+    let columns: Vec<String> = obj1.keys().cloned().collect();
+    let json2 = to_columnar(json2.clone(), Some(&columns), policy)?;
     let obj2 = json2
         .as_object()
-        .ok_or_else(|| anyhow!("Expected an object in json2"))?;
+        .ok_or_else(|| anyhow!("JSON data should be objects with arrays as values"))?;
+    let new_rows = count_rows(obj2).map_err(|e| anyhow!("New data is not aligned: {}", e))?;
 
-    let mut merged_data = json1.clone();
+    let mut merged_data = obj1.clone();
 
+    // Extend the existing columns, filling in those the new data leaves out
+    for (key, values) in merged_data.iter_mut() {
+        let new_values = match obj2.get(key).and_then(Value::as_array) {
+            Some(new_values) => new_values.clone(),
+            None => policy
+                .backfill(new_rows)
+                .ok_or_else(|| anyhow!("Column '{}' is missing from the new data", key))?,
+        };
+        if let Value::Array(values) = values {
+            values.extend(new_values);
+        }
+    }
+
+    // Add the columns the new data brings, filling in the existing rows
     for (key, value) in obj2 {
-        if let Some(arr1) = obj1.get(key).and_then(|v| v.as_array()) {
-            if let Some(arr2) = value.as_array() {
-                // Concatenate the two arrays and store them in the merged data
-                let mut combined_array = arr1.clone();
-                combined_array.extend(arr2.clone());
-
-                if let Some(merged_obj) = merged_data.as_object_mut() {
-                    merged_obj.insert(key.clone(), Value::Array(combined_array));
-                } else {
-                    return Err(anyhow!("Merged data is not an object"));
-                }
-            } else {
-                return Err(anyhow!("Expected an array for key '{}' in json2", key));
+        if obj1.contains_key(key) {
+            continue;
+        }
+        let mut values = policy
+            .backfill(existing_rows)
+            .ok_or_else(|| anyhow!("Column '{}' is not in the data pool", key))?;
+        values.extend(value.as_array().cloned().unwrap_or_default());
+        merged_data.insert(key.clone(), Value::Array(values));
+    }
+
+    count_rows(&merged_data)?;
+    Ok(Value::Object(merged_data))
+}
+
+/// Returns the number of rows in columnar data, checking that every column is
+/// an array and that all of them hold the same number of values
+pub fn row_count(columns: &Value) -> Result<usize> {
+    let columns = columns
+        .as_object()
+        .ok_or_else(|| anyhow!("JSON data should be objects with arrays as values"))?;
+    count_rows(columns)
+}
+
+fn count_rows(columns: &Map<String, Value>) -> Result<usize> {
+    let mut row_count: Option<(&String, usize)> = None;
+    for (key, value) in columns {
+        let values = value
+            .as_array()
+            .ok_or_else(|| anyhow!("Expected an array for column '{}'", key))?;
+        match row_count {
+            None => row_count = Some((key, values.len())),
+            Some((first, count)) if count != values.len() => {
+                return Err(anyhow!(
+                    "Column '{}' has {} values, but column '{}' has {}",
+                    key,
+                    values.len(),
+                    first,
+                    count
+                ));
             }
-        } else {
-            return Err(anyhow!("Expected an array for key '{}' in json1", key));
+            Some(_) => {}
+        }
+    }
+    Ok(row_count.map_or(0, |(_, count)| count))
+}
+
+/// Converts tabular data to the columnar layout of data pools, `{ "col": [..] }`.
+///
+/// The input can be:
+/// * a JSON object, which is already columnar and returned as is;
+/// * an array of records, `[{ "col": v, .. }, ..]`;
+/// * a string of NDJSON, one record per line, where blank lines are skipped.
+///
+/// Under `ColumnPolicy::Reject`, every record must have the same fields: those
+/// in `columns` if given, otherwise those of the first record. Under the other
+/// policies, records may have different fields, and the fields a record lacks
+/// are filled in.
+pub fn to_columnar(
+    input: Value,
+    columns: Option<&[String]>,
+    policy: &ColumnPolicy,
+) -> Result<Value> {
+    match input {
+        Value::Object(_) => Ok(input),
+        Value::Array(rows) => {
+            let records = rows
+                .iter()
+                .enumerate()
+                .map(|(index, record)| (format!("record {}", index), record));
+            records_to_columns(records, columns, policy)
         }
+        Value::String(text) => {
+            let records = parse_ndjson(&text)?;
+            let records = records
+                .iter()
+                .map(|(line, record)| (format!("line {}", line), record));
+            records_to_columns(records, columns, policy)
+        }
+        _ => Err(anyhow!(
+            "JSON data should be an object of columns, an array of records or NDJSON text"
+        )),
     }
+}
 
-    Ok(merged_data)
+/// Converts an array of records, `[{ "col": v, .. }, ..]`, to the columnar layout.
+///
+/// See `to_columnar` for how the columns are determined.
+pub fn rows_to_columns(
+    rows: &Value,
+    columns: Option<&[String]>,
+    policy: &ColumnPolicy,
+) -> Result<Value> {
+    let rows = rows
+        .as_array()
+        .ok_or_else(|| anyhow!("Expected an array of records"))?;
+    let records = rows
+        .iter()
+        .enumerate()
+        .map(|(index, record)| (format!("record {}", index), record));
+    records_to_columns(records, columns, policy)
+}
+
+/// Converts columnar data to an array of records, one per row.
+///
+/// All columns must hold the same number of values.
+pub fn columns_to_rows(columns: &Value) -> Result<Value> {
+    let row_count = row_count(columns)?;
+    let columns = columns.as_object().into_iter().flatten();
+    let rows = (0..row_count)
+        .map(|row| {
+            let record: Map<String, Value> = columns
+                .clone()
+                .map(|(key, values)| (key.clone(), values[row].clone()))
+                .collect();
+            Value::Object(record)
+        })
+        .collect();
+    Ok(Value::Array(rows))
+}
+
+/// Parses NDJSON text into its records, each with its 1-based line number
+fn parse_ndjson(text: &str) -> Result<Vec<(usize, Value)>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map(|record| (index + 1, record))
+                .map_err(|e| anyhow!("Invalid JSON on line {}: {}", index + 1, e))
+        })
+        .collect()
+}
+
+/// Collects records into columns, checking their fields against the policy.
+/// Each record comes with a description of where it was found, used in errors.
+fn records_to_columns<'a>(
+    records: impl Iterator<Item = (String, &'a Value)>,
+    columns: Option<&[String]>,
+    policy: &ColumnPolicy,
+) -> Result<Value> {
+    let mut merged: Map<String, Value> = columns
+        .into_iter()
+        .flatten()
+        .map(|key| (key.clone(), Value::Array(Vec::new())))
+        .collect();
+    let mut fixed = columns.is_some(); // Whether the columns are known yet
+
+    for (row, (location, record)) in records.enumerate() {
+        let record = record
+            .as_object()
+            .ok_or_else(|| anyhow!("Expected an object at {}", location))?;
+
+        // Without given columns, the first record defines them
+        if !fixed {
+            for key in record.keys() {
+                merged.insert(key.clone(), Value::Array(Vec::new()));
+            }
+            fixed = true;
+        }
+
+        // Add the fields this record brings, filling in the earlier rows
+        for key in record.keys() {
+            if !merged.contains_key(key) {
+                let values = policy
+                    .backfill(row)
+                    .ok_or_else(|| anyhow!("Unexpected field '{}' at {}", key, location))?;
+                merged.insert(key.clone(), Value::Array(values));
+            }
+        }
+
+        for (key, values) in merged.iter_mut() {
+            let value = match record.get(key) {
+                Some(value) => value.clone(),
+                None => policy
+                    .backfill(1)
+                    .and_then(|mut values| values.pop())
+                    .ok_or_else(|| anyhow!("Field '{}' is missing at {}", key, location))?,
+            };
+            if let Value::Array(values) = values {
+                values.push(value);
+            }
+        }
+    }
+
+    Ok(Value::Object(merged))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn error<T: std::fmt::Debug>(result: Result<T>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn records_become_columns() {
+        let records = json!([{ "a": 1, "b": "x" }, { "b": "y", "a": 2 }]);
+        assert_eq!(
+            to_columnar(records, None, &ColumnPolicy::Reject).unwrap(),
+            json!({ "a": [1, 2], "b": ["x", "y"] })
+        );
+
+        let columns = json!({ "a": [1] });
+        assert_eq!(
+            to_columnar(columns.clone(), None, &ColumnPolicy::Reject).unwrap(),
+            columns
+        );
+    }
+
+    #[test]
+    fn ndjson_skips_blank_lines() {
+        let text = json!("{\"a\": 1}\n\n  \n{\"a\": 2}\n");
+        assert_eq!(
+            to_columnar(text, None, &ColumnPolicy::Reject).unwrap(),
+            json!({ "a": [1, 2] })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_ndjson_naming_the_line() {
+        let text = json!("{\"a\": 1}\n\n{\"a\": 2,\n");
+        assert!(error(to_columnar(text, None, &ColumnPolicy::Reject))
+            .starts_with("Invalid JSON on line 3"));
+
+        let text = json!("{\"a\": 1}\n[1]\n");
+        assert_eq!(
+            error(to_columnar(text, None, &ColumnPolicy::Reject)),
+            "Expected an object at line 2"
+        );
+    }
+
+    #[test]
+    fn rejects_record_that_is_not_an_object() {
+        let records = json!([{ "a": 1 }, 2]);
+        assert_eq!(
+            error(to_columnar(records, None, &ColumnPolicy::Reject)),
+            "Expected an object at record 1"
+        );
+        assert!(to_columnar(json!(1), None, &ColumnPolicy::Reject).is_err());
+        assert!(rows_to_columns(&json!({ "a": [1] }), None, &ColumnPolicy::Reject).is_err());
+    }
+
+    #[test]
+    fn rejects_records_with_other_fields() {
+        let records = json!([{ "a": 1 }, { "a": 2, "b": 3 }]);
+        assert_eq!(
+            error(to_columnar(records, None, &ColumnPolicy::Reject)),
+            "Unexpected field 'b' at record 1"
+        );
+
+        let records = json!([{ "a": 1, "b": 2 }, { "a": 2 }]);
+        assert_eq!(
+            error(to_columnar(records, None, &ColumnPolicy::Reject)),
+            "Field 'b' is missing at record 1"
+        );
+
+        // Given columns take the place of the first record's fields
+        let columns = ["a".to_string(), "b".to_string()];
+        let text = json!("{\"a\": 1}");
+        assert_eq!(
+            error(to_columnar(text, Some(&columns), &ColumnPolicy::Reject)),
+            "Field 'b' is missing at line 1"
+        );
+    }
+
+    #[test]
+    fn backfills_fields_records_lack() {
+        let records = json!([{ "a": 1 }, { "b": 2 }]);
+        assert_eq!(
+            to_columnar(records.clone(), None, &ColumnPolicy::BackfillNull).unwrap(),
+            json!({ "a": [1, null], "b": [null, 2] })
+        );
+        assert_eq!(
+            to_columnar(records, None, &ColumnPolicy::BackfillDefault(json!(0))).unwrap(),
+            json!({ "a": [1, 0], "b": [0, 2] })
+        );
+    }
+
+    #[test]
+    fn rows_round_trip() {
+        let columns = json!({ "a": [1, 2], "b": ["x", null] });
+        let rows = columns_to_rows(&columns).unwrap();
+        assert_eq!(rows, json!([{ "a": 1, "b": "x" }, { "a": 2, "b": null }]));
+        assert_eq!(
+            rows_to_columns(&rows, None, &ColumnPolicy::Reject).unwrap(),
+            columns
+        );
+    }
+
+    #[test]
+    fn rejects_misaligned_columns() {
+        assert_eq!(
+            error(row_count(&json!({ "a": [1, 2], "b": [1] }))),
+            "Column 'b' has 1 values, but column 'a' has 2"
+        );
+        assert_eq!(
+            error(row_count(&json!({ "a": [1], "b": 1 }))),
+            "Expected an array for column 'b'"
+        );
+        assert_eq!(row_count(&json!({})).unwrap(), 0);
+
+        let pool = json!({ "a": [1], "b": [2] });
+        let misaligned = json!({ "a": [1, 2], "b": [3] });
+        for policy in [ColumnPolicy::Reject, ColumnPolicy::BackfillNull] {
+            assert!(error(append_json(&pool, &misaligned, &policy))
+                .starts_with("New data is not aligned"));
+            assert!(error(append_json(&misaligned, &pool, &policy))
+                .starts_with("Data pool is not aligned"));
+        }
+    }
+
+    #[test]
+    fn reject_policy_needs_the_columns_of_the_pool() {
+        let pool = json!({ "a": [1], "b": [2] });
+        assert_eq!(
+            error(append_json(
+                &pool,
+                &json!({ "a": [3] }),
+                &ColumnPolicy::Reject
+            )),
+            "Column 'b' is missing from the new data"
+        );
+        assert_eq!(
+            error(append_json(
+                &pool,
+                &json!({ "a": [3], "b": [4], "c": [5] }),
+                &ColumnPolicy::Reject
+            )),
+            "Column 'c' is not in the data pool"
+        );
+        assert_eq!(
+            append_json(&pool, &json!({ "a": [3], "b": [4] }), &ColumnPolicy::Reject).unwrap(),
+            json!({ "a": [1, 3], "b": [2, 4] })
+        );
+    }
+
+    #[test]
+    fn backfill_policies_keep_rows_aligned() {
+        let pool = json!({ "a": [1, 2], "b": [3, 4] });
+        let new_data = json!({ "a": [5], "c": [6] });
+        assert_eq!(
+            append_json(&pool, &new_data, &ColumnPolicy::BackfillNull).unwrap(),
+            json!({ "a": [1, 2, 5], "b": [3, 4, null], "c": [null, null, 6] })
+        );
+        assert_eq!(
+            append_json(&pool, &new_data, &ColumnPolicy::BackfillDefault(json!("-"))).unwrap(),
+            json!({ "a": [1, 2, 5], "b": [3, 4, "-"], "c": ["-", "-", 6] })
+        );
+
+        // Records are brought into the columns of the pool first
+        let records = json!([{ "a": 5 }, { "a": 6, "b": 7 }]);
+        assert_eq!(
+            append_json(&pool, &records, &ColumnPolicy::BackfillNull).unwrap(),
+            json!({ "a": [1, 2, 5, 6], "b": [3, 4, null, 7] })
+        );
+    }
 }
@@ -0,0 +1,763 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Data pools held as typed Arrow tables, converted to and from JSON at the edges.
+
+use crate::{count_rows, ColumnPolicy};
+use anyhow::{anyhow, Result};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, Int64Type};
+use arrow_array::{
+    new_null_array, Array, ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch,
+    RecordBatchOptions, StringArray, StructArray,
+};
+use arrow_buffer::NullBuffer;
+use arrow_schema::{DataType, Field, Fields, Schema};
+use arrow_select::concat::concat;
+use serde_json::{Map, Number, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Write};
+use std::iter::repeat_n;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Field metadata naming the extension type of a column
+const EXTENSION_NAME_KEY: &str = "ARROW:extension:name";
+
+/// Arrow's canonical extension type for UTF-8 columns holding JSON text
+const JSON_EXTENSION_NAME: &str = "arrow.json";
+
+/// Children of a column of integers mixed with other numbers, of which exactly
+/// one is set in each row that is not null
+const INTEGER_FIELD: &str = "integer";
+const FLOAT_FIELD: &str = "float";
+
+/// Type of a column of a data pool held as an Arrow table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    Null,    // No values yet, or only nulls
+    Boolean, // JSON booleans
+    Int64,   // JSON integers that fit 64 bits
+    Float64, // JSON numbers written with a fraction or exponent
+    Number,  // Integers mixed with other numbers, each in the child of its kind
+    Utf8,    // JSON strings
+    Json,    // Anything else, including mixed values, as JSON text
+}
+
+impl ColumnType {
+    /// Returns the type of a column from its Arrow field
+    fn of_field(field: &Field) -> Result<Self> {
+        let is_json = field.metadata().get(EXTENSION_NAME_KEY).map(String::as_str)
+            == Some(JSON_EXTENSION_NAME);
+        match field.data_type() {
+            DataType::Null => Ok(ColumnType::Null),
+            DataType::Boolean => Ok(ColumnType::Boolean),
+            DataType::Int64 => Ok(ColumnType::Int64),
+            DataType::Float64 => Ok(ColumnType::Float64),
+            DataType::Struct(fields) if *fields == number_fields() => Ok(ColumnType::Number),
+            DataType::Utf8 if is_json => Ok(ColumnType::Json),
+            DataType::Utf8 => Ok(ColumnType::Utf8),
+            other => Err(anyhow!(
+                "Column '{}' has unsupported type {}",
+                field.name(),
+                other
+            )),
+        }
+    }
+
+    /// Returns the narrowest type that holds a JSON value
+    fn of_value(value: &Value) -> Self {
+        match value {
+            Value::Null => ColumnType::Null,
+            Value::Bool(_) => ColumnType::Boolean,
+            Value::Number(number) if number.is_i64() => ColumnType::Int64,
+            Value::Number(number) if number.is_f64() => ColumnType::Float64,
+            Value::String(_) => ColumnType::Utf8,
+            // Objects, arrays and integers beyond `i64`
+            _ => ColumnType::Json,
+        }
+    }
+
+    /// Returns the type that a JSON Schema gives the values of a column, as in
+    /// `{ "properties": { "col": { "items": { "type": .. } } } }`, or `Null` if
+    /// the schema leaves it open. A `number` can be an integer or not, so it
+    /// leaves the type to the values.
+    fn of_schema(schema: Option<&Value>, column: &str) -> Self {
+        let types = schema
+            .and_then(|schema| schema.get("properties"))
+            .and_then(|properties| properties.get(column))
+            .and_then(|column| column.get("items"))
+            .and_then(|items| items.get("type"));
+        let types: Vec<&str> = match types {
+            Some(Value::String(name)) => vec![name],
+            Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+            _ => return ColumnType::Null,
+        };
+        types
+            .into_iter()
+            .map(|name| match name {
+                "null" => ColumnType::Null,
+                "boolean" => ColumnType::Boolean,
+                "integer" => ColumnType::Int64,
+                "number" => ColumnType::Null,
+                "string" => ColumnType::Utf8,
+                _ => ColumnType::Json,
+            })
+            .fold(ColumnType::Null, ColumnType::unify)
+    }
+
+    /// Returns the narrowest type that holds the values of both types.
+    ///
+    /// Integers and other numbers are kept together as `Number`, which holds
+    /// each in a child of its own type, since a `Float64` would turn integers
+    /// into decimals and round those beyond 2^53.
+    fn unify(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ColumnType::Null, other) | (other, ColumnType::Null) => other,
+            (ColumnType::Int64 | ColumnType::Float64 | ColumnType::Number, other)
+                if other.is_numeric() =>
+            {
+                ColumnType::Number
+            }
+            _ => ColumnType::Json,
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            ColumnType::Int64 | ColumnType::Float64 | ColumnType::Number
+        )
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            ColumnType::Null => DataType::Null,
+            ColumnType::Boolean => DataType::Boolean,
+            ColumnType::Int64 => DataType::Int64,
+            ColumnType::Float64 => DataType::Float64,
+            ColumnType::Number => DataType::Struct(number_fields()),
+            ColumnType::Utf8 | ColumnType::Json => DataType::Utf8,
+        }
+    }
+
+    /// Returns the Arrow field of a column of this type. Every column is
+    /// nullable, since appends can fill in missing values with null.
+    fn field(self, name: &str) -> Field {
+        let field = Field::new(name, self.data_type(), true);
+        if self != ColumnType::Json {
+            return field;
+        }
+        field.with_metadata(HashMap::from([(
+            EXTENSION_NAME_KEY.to_string(),
+            JSON_EXTENSION_NAME.to_string(),
+        )]))
+    }
+}
+
+/// Returns the children of a `Number` column
+fn number_fields() -> Fields {
+    Fields::from(vec![
+        Field::new(INTEGER_FIELD, DataType::Int64, true),
+        Field::new(FLOAT_FIELD, DataType::Float64, true),
+    ])
+}
+
+/// Returns the Arrow type that a JSON Schema gives the values of a column, if
+/// it gives them a single scalar type
+pub(crate) fn schema_data_type(schema: Option<&Value>, column: &str) -> Option<DataType> {
+    match ColumnType::of_schema(schema, column) {
+        ColumnType::Null | ColumnType::Number | ColumnType::Json => None,
+        column_type => Some(column_type.data_type()),
+    }
+}
+
+/// Returns the narrowest type that holds `initial` and all of the values
+fn values_type<'a>(values: impl Iterator<Item = &'a Value>, initial: ColumnType) -> ColumnType {
+    values
+        .map(ColumnType::of_value)
+        .fold(initial, ColumnType::unify)
+}
+
+/// Builds an array of the given type, which must hold all of the values
+fn build_array<'a>(values: impl Iterator<Item = &'a Value>, column_type: ColumnType) -> ArrayRef {
+    match column_type {
+        ColumnType::Null => new_null_array(&DataType::Null, values.count()),
+        ColumnType::Boolean => Arc::new(values.map(Value::as_bool).collect::<BooleanArray>()),
+        ColumnType::Int64 => Arc::new(values.map(Value::as_i64).collect::<Int64Array>()),
+        ColumnType::Float64 => Arc::new(values.map(Value::as_f64).collect::<Float64Array>()),
+        ColumnType::Number => {
+            let values: Vec<&Value> = values.collect();
+            let integers: Int64Array = values.iter().map(|value| value.as_i64()).collect();
+            let floats: Float64Array = values
+                .iter()
+                .map(|value| value.as_i64().is_none().then(|| value.as_f64()).flatten())
+                .collect();
+            let nulls: NullBuffer = values.iter().map(|value| !value.is_null()).collect();
+            Arc::new(StructArray::new(
+                number_fields(),
+                vec![Arc::new(integers), Arc::new(floats)],
+                Some(nulls),
+            ))
+        }
+        ColumnType::Utf8 => Arc::new(values.map(Value::as_str).collect::<StringArray>()),
+        ColumnType::Json => Arc::new(
+            values
+                .map(|value| (!value.is_null()).then(|| value.to_string()))
+                .collect::<StringArray>(),
+        ),
+    }
+}
+
+/// Converts an array to a wider type, as returned by `ColumnType::unify`
+fn widen_array(array: &ArrayRef, from: ColumnType, to: ColumnType) -> Result<ArrayRef> {
+    if from == to {
+        return Ok(array.clone());
+    }
+    if from == ColumnType::Null {
+        return Ok(new_null_array(&to.data_type(), array.len()));
+    }
+    let column = Column::new(array, from)?;
+    let values = (0..array.len())
+        .map(|row| column.value(row))
+        .collect::<Result<Vec<Value>>>()?;
+    Ok(build_array(values.iter(), to))
+}
+
+/// A column of a record batch, downcast to the array of its type
+enum Column<'a> {
+    Null,
+    Boolean(&'a BooleanArray),
+    Int64(&'a Int64Array),
+    Float64(&'a Float64Array),
+    Number(&'a StructArray, &'a Int64Array, &'a Float64Array),
+    Utf8(&'a StringArray),
+    Json(&'a StringArray),
+}
+
+impl<'a> Column<'a> {
+    fn new(array: &'a ArrayRef, column_type: ColumnType) -> Result<Self> {
+        let column = match column_type {
+            ColumnType::Null => Some(Column::Null),
+            ColumnType::Boolean => array.as_boolean_opt().map(Column::Boolean),
+            ColumnType::Int64 => array.as_primitive_opt::<Int64Type>().map(Column::Int64),
+            ColumnType::Float64 => array.as_primitive_opt::<Float64Type>().map(Column::Float64),
+            ColumnType::Number => array.as_struct_opt().and_then(|array| {
+                let integers = array.column(0).as_primitive_opt::<Int64Type>()?;
+                let floats = array.column(1).as_primitive_opt::<Float64Type>()?;
+                Some(Column::Number(array, integers, floats))
+            }),
+            ColumnType::Utf8 => array.as_string_opt::<i32>().map(Column::Utf8),
+            ColumnType::Json => array.as_string_opt::<i32>().map(Column::Json),
+        };
+        column.ok_or_else(|| anyhow!("Array does not have the type of its column"))
+    }
+
+    /// Returns the columns of a record batch with their names
+    fn of_batch(batch: &'a RecordBatch) -> Result<Vec<(&'a str, Self)>> {
+        batch
+            .schema_ref()
+            .fields()
+            .iter()
+            .zip(batch.columns())
+            .map(|(field, array)| {
+                let column = Column::new(array, ColumnType::of_field(field)?)?;
+                Ok((field.name().as_str(), column))
+            })
+            .collect()
+    }
+
+    fn is_null(&self, row: usize) -> bool {
+        match self {
+            Column::Null => true,
+            Column::Boolean(array) => array.is_null(row),
+            Column::Int64(array) => array.is_null(row),
+            Column::Float64(array) => array.is_null(row),
+            Column::Number(array, ..) => array.is_null(row),
+            Column::Utf8(array) | Column::Json(array) => array.is_null(row),
+        }
+    }
+
+    /// Returns the value in a row as JSON
+    fn value(&self, row: usize) -> Result<Value> {
+        if self.is_null(row) {
+            return Ok(Value::Null);
+        }
+        Ok(match self {
+            Column::Null => Value::Null,
+            Column::Boolean(array) => Value::Bool(array.value(row)),
+            Column::Int64(array) => Value::from(array.value(row)),
+            Column::Float64(array) => {
+                Number::from_f64(array.value(row)).map_or(Value::Null, Value::Number)
+            }
+            Column::Number(_, integers, _) if integers.is_valid(row) => {
+                Value::from(integers.value(row))
+            }
+            Column::Number(_, _, floats) => {
+                Number::from_f64(floats.value(row)).map_or(Value::Null, Value::Number)
+            }
+            Column::Utf8(array) => Value::String(array.value(row).to_string()),
+            Column::Json(array) => serde_json::from_str(array.value(row))
+                .map_err(|e| anyhow!("Invalid JSON in row {}: {}", row, e))?,
+        })
+    }
+
+    /// Writes the value in a row as JSON, exactly as `serde_json` would write
+    /// the value it stands for
+    fn write<W: Write>(&self, writer: &mut W, row: usize) -> io::Result<()> {
+        if self.is_null(row) {
+            return writer.write_all(b"null");
+        }
+        match self {
+            Column::Null => writer.write_all(b"null"),
+            Column::Boolean(array) if array.value(row) => writer.write_all(b"true"),
+            Column::Boolean(_) => writer.write_all(b"false"),
+            Column::Int64(array) => Ok(serde_json::to_writer(writer, &array.value(row))?),
+            Column::Float64(array) => Ok(serde_json::to_writer(writer, &array.value(row))?),
+            Column::Number(_, integers, _) if integers.is_valid(row) => {
+                Ok(serde_json::to_writer(writer, &integers.value(row))?)
+            }
+            Column::Number(_, _, floats) => Ok(serde_json::to_writer(writer, &floats.value(row))?),
+            Column::Utf8(array) => Ok(serde_json::to_writer(writer, array.value(row))?),
+            Column::Json(array) => writer.write_all(array.value(row).as_bytes()),
+        }
+    }
+}
+
+/// Builds a record batch from named columns, sorted by name as in JSON objects
+fn assemble(columns: BTreeMap<String, (ColumnType, ArrayRef)>, rows: usize) -> Result<RecordBatch> {
+    let (fields, arrays): (Vec<Field>, Vec<ArrayRef>) = columns
+        .into_iter()
+        .map(|(name, (column_type, array))| (column_type.field(&name), array))
+        .unzip();
+    let options = RecordBatchOptions::new().with_row_count(Some(rows));
+    RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), arrays, &options)
+        .map_err(|e| anyhow!("Failed to build record batch: {}", e))
+}
+
+/// Converts columnar JSON data, `{ "col": [..] }`, to an Arrow record batch.
+///
+/// Each column gets the type that the pool's JSON Schema gives its values, if
+/// any, widened to hold the values it actually has: integers mixed with other
+/// numbers keep each in a child of its own type, and columns of objects, arrays
+/// or other mixed values keep each value as JSON text, so integers always
+/// convert back as integers. All columns must hold the same number of values.
+pub fn to_record_batch(columns: &Value, schema: Option<&Value>) -> Result<RecordBatch> {
+    let columns = columns
+        .as_object()
+        .ok_or_else(|| anyhow!("JSON data should be objects with arrays as values"))?;
+    let rows = count_rows(columns)?;
+
+    let mut table = BTreeMap::new();
+    for (name, values) in columns {
+        let values = values.as_array().map(Vec::as_slice).unwrap_or_default();
+        let column_type = values_type(values.iter(), ColumnType::of_schema(schema, name));
+        table.insert(
+            name.clone(),
+            (column_type, build_array(values.iter(), column_type)),
+        );
+    }
+    assemble(table, rows)
+}
+
+/// Appends columnar JSON data to a record batch, returning the extended batch.
+///
+/// This is `append_json` for pools held as Arrow tables: columns that only one
+/// side has are handled as `policy` says, and columns are widened where the new
+/// values do not fit their type. New columns are typed as in `to_record_batch`.
+pub fn append_record_batch(
+    batch: &RecordBatch,
+    columns: &Value,
+    schema: Option<&Value>,
+    policy: &ColumnPolicy,
+) -> Result<RecordBatch> {
+    let new_columns = columns
+        .as_object()
+        .ok_or_else(|| anyhow!("JSON data should be objects with arrays as values"))?;
+    let new_rows =
+        count_rows(new_columns).map_err(|e| anyhow!("New data is not aligned: {}", e))?;
+    let existing_rows = batch.num_rows();
+    let fill_value = policy.fill_value();
+
+    // Extend the existing columns, filling in those the new data leaves out
+    let mut table = BTreeMap::new();
+    for (field, array) in batch.schema_ref().fields().iter().zip(batch.columns()) {
+        let existing_type = ColumnType::of_field(field)?;
+        let (column_type, new_array) = match new_columns.get(field.name()) {
+            Some(values) => {
+                let values = values.as_array().map(Vec::as_slice).unwrap_or_default();
+                let column_type = values_type(values.iter(), existing_type);
+                (column_type, build_array(values.iter(), column_type))
+            }
+            None => {
+                let value = fill_value.ok_or_else(|| {
+                    anyhow!("Column '{}' is missing from the new data", field.name())
+                })?;
+                let column_type = existing_type.unify(ColumnType::of_value(value));
+                (
+                    column_type,
+                    build_array(repeat_n(value, new_rows), column_type),
+                )
+            }
+        };
+        let existing_array = widen_array(array, existing_type, column_type)?;
+        let array = concat(&[existing_array.as_ref(), new_array.as_ref()])
+            .map_err(|e| anyhow!("Failed to extend column '{}': {}", field.name(), e))?;
+        table.insert(field.name().clone(), (column_type, array));
+    }
+
+    // Add the columns the new data brings, filling in the existing rows
+    for (name, values) in new_columns {
+        if table.contains_key(name) {
+            continue;
+        }
+        let value =
+            fill_value.ok_or_else(|| anyhow!("Column '{}' is not in the data pool", name))?;
+        let values = values.as_array().map(Vec::as_slice).unwrap_or_default();
+        let all_values = repeat_n(value, existing_rows).chain(values);
+        let column_type = values_type(all_values.clone(), ColumnType::of_schema(schema, name));
+        table.insert(
+            name.clone(),
+            (column_type, build_array(all_values, column_type)),
+        );
+    }
+
+    assemble(table, existing_rows + new_rows)
+}
+
+/// Converts a record batch back to columnar JSON data, `{ "col": [..] }`
+pub fn record_batch_to_json(batch: &RecordBatch) -> Result<Value> {
+    let mut columns = Map::new();
+    for (name, column) in Column::of_batch(batch)? {
+        let values = (0..batch.num_rows())
+            .map(|row| column.value(row))
+            .collect::<Result<Vec<Value>>>()?;
+        columns.insert(name.to_string(), Value::Array(values));
+    }
+    Ok(Value::Object(columns))
+}
+
+/// Writes a record batch as columnar JSON, `{ "col": [..] }`, byte for byte as
+/// `record_batch_to_json` followed by `serde_json::to_writer` would, but
+/// without building the JSON value
+pub fn write_columns<W: Write>(batch: &RecordBatch, writer: &mut W) -> Result<()> {
+    writer.write_all(b"{")?;
+    for (index, (name, column)) in Column::of_batch(batch)?.iter().enumerate() {
+        if index > 0 {
+            writer.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut *writer, name)?;
+        writer.write_all(b":[")?;
+        for row in 0..batch.num_rows() {
+            if row > 0 {
+                writer.write_all(b",")?;
+            }
+            column.write(writer, row)?;
+        }
+        writer.write_all(b"]")?;
+    }
+    writer.write_all(b"}")?;
+    Ok(())
+}
+
+/// Writes a record batch as an array of records, `[{ "col": v, .. }, ..]`,
+/// one per row, as `columns_to_rows` would lay out its columnar JSON
+pub fn write_rows<W: Write>(batch: &RecordBatch, writer: &mut W) -> Result<()> {
+    let columns = Column::of_batch(batch)?;
+    writer.write_all(b"[")?;
+    for row in 0..batch.num_rows() {
+        if row > 0 {
+            writer.write_all(b",")?;
+        }
+        writer.write_all(b"{")?;
+        for (index, (name, column)) in columns.iter().enumerate() {
+            if index > 0 {
+                writer.write_all(b",")?;
+            }
+            serde_json::to_writer(&mut *writer, name)?;
+            writer.write_all(b":")?;
+            column.write(writer, row)?;
+        }
+        writer.write_all(b"}")?;
+    }
+    writer.write_all(b"]")?;
+    Ok(())
+}
+
+/// Returns the length of a record batch written by `write_columns`
+pub fn columns_json_len(batch: &RecordBatch) -> Result<usize> {
+    let mut counter = ByteCounter(0);
+    write_columns(batch, &mut counter)?;
+    Ok(counter.0)
+}
+
+/// Returns the length of a record batch written by `write_columns`, given the
+/// batch `append_record_batch` extended and its length as written.
+///
+/// Only the new rows, and the existing rows of added columns, are written out
+/// to measure them. Widening a column does not change how its values are
+/// written, so the existing rows take up as much as they did before.
+pub fn appended_json_len(
+    batch: &RecordBatch,
+    batch_len: usize,
+    appended: &RecordBatch,
+) -> Result<usize> {
+    let existing_rows = batch.num_rows();
+    let rows = appended.num_rows();
+    let existing_values = batch_len
+        .checked_sub(framing_len(batch.schema_ref(), existing_rows)?)
+        .filter(|_| existing_rows <= rows)
+        .ok_or_else(|| anyhow!("Record batch was not extended from the given one"))?;
+    let existing_columns: HashSet<&str> = batch
+        .schema_ref()
+        .fields()
+        .iter()
+        .map(|field| field.name().as_str())
+        .collect();
+
+    let mut len = framing_len(appended.schema_ref(), rows)? + existing_values;
+    for (name, column) in Column::of_batch(appended)? {
+        let written = if existing_columns.contains(name) {
+            existing_rows..rows
+        } else {
+            0..rows
+        };
+        len += values_len(&column, written)?;
+    }
+    Ok(len)
+}
+
+/// Returns the length of everything `write_columns` writes for a batch but
+/// its values: braces, column names, brackets and separators
+fn framing_len(schema: &Schema, rows: usize) -> Result<usize> {
+    let columns = schema.fields().len();
+    let mut counter = ByteCounter(0);
+    for field in schema.fields() {
+        serde_json::to_writer(&mut counter, field.name())?;
+    }
+    Ok(counter.0 + 3 * columns + 2 + columns.saturating_sub(1) + columns * rows.saturating_sub(1))
+}
+
+/// Returns the length of the values of a column in the given rows, as written
+fn values_len(column: &Column, rows: Range<usize>) -> Result<usize> {
+    let mut counter = ByteCounter(0);
+    for row in rows {
+        column.write(&mut counter, row)?;
+    }
+    Ok(counter.0)
+}
+
+/// Writer that only counts the bytes written to it
+pub(crate) struct ByteCounter(pub(crate) usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn rejects_misaligned_columns() {
+        let error = to_record_batch(&json!({ "a": [1, 2], "b": [1] }), None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Column 'b' has 1 values, but column 'a' has 2"
+        );
+
+        let batch = to_record_batch(&json!({ "a": [1], "b": [2] }), None).unwrap();
+        let error = append_record_batch(
+            &batch,
+            &json!({ "a": [1, 2], "b": [3] }),
+            None,
+            &ColumnPolicy::BackfillNull,
+        )
+        .unwrap_err();
+        assert!(error.to_string().starts_with("New data is not aligned"));
+    }
+
+    #[test]
+    fn append_follows_the_column_policy() {
+        let batch = to_record_batch(&json!({ "a": [1], "b": [2] }), None).unwrap();
+        let new_data = json!({ "a": [3], "c": [4] });
+
+        let error =
+            append_record_batch(&batch, &new_data, None, &ColumnPolicy::Reject).unwrap_err();
+        assert_eq!(error.to_string(), "Column 'b' is missing from the new data");
+        let error = append_record_batch(
+            &batch,
+            &json!({ "a": [3], "b": [4], "c": [5] }),
+            None,
+            &ColumnPolicy::Reject,
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "Column 'c' is not in the data pool");
+
+        let appended =
+            append_record_batch(&batch, &new_data, None, &ColumnPolicy::BackfillNull).unwrap();
+        assert_eq!(appended.num_rows(), 2);
+        assert_eq!(
+            record_batch_to_json(&appended).unwrap(),
+            json!({ "a": [1, 3], "b": [2, null], "c": [null, 4] })
+        );
+    }
+
+    /// Converts columns to a record batch and back, as JSON and as written
+    fn round_trip(columns: &Value, schema: Option<&Value>) -> RecordBatch {
+        let batch = to_record_batch(columns, schema).unwrap();
+        assert_eq!(&record_batch_to_json(&batch).unwrap(), columns);
+
+        let mut written = Vec::new();
+        write_columns(&batch, &mut written).unwrap();
+        assert_eq!(written, serde_json::to_vec(columns).unwrap());
+        assert_eq!(columns_json_len(&batch).unwrap(), written.len());
+        batch
+    }
+
+    fn column_type(batch: &RecordBatch, name: &str) -> ColumnType {
+        ColumnType::of_field(batch.schema_ref().field_with_name(name).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn integers_round_trip() {
+        let columns = json!({ "a": [1, 2, 3], "b": [9007199254740993_i64, 0.5, 1] });
+        let schema = json!({
+            "properties": { "a": { "type": "array", "items": { "type": "number" } } }
+        });
+        let batch = round_trip(&columns, Some(&schema));
+        assert_eq!(column_type(&batch, "a"), ColumnType::Int64);
+        // Integers beyond 2^53 stay exact next to other numbers
+        assert_eq!(column_type(&batch, "b"), ColumnType::Number);
+
+        let columns = json!({ "big": [u64::MAX, 1], "min": [i64::MIN, i64::MAX] });
+        let batch = round_trip(&columns, None);
+        assert_eq!(column_type(&batch, "big"), ColumnType::Json);
+        assert_eq!(column_type(&batch, "min"), ColumnType::Int64);
+    }
+
+    #[test]
+    fn other_values_round_trip() {
+        let columns = json!({
+            "decimal": [0.5, 2.0, null, -1e-7],
+            "flag": [true, false, null, true],
+            "mixed": [1, "one", [1], { "n": 1 }],
+            "empty": [null, null, null, null],
+            "text": ["a", "", null, "\u{e9}\"\n"],
+        });
+
+        let batch = round_trip(&columns, None);
+        assert_eq!(column_type(&batch, "decimal"), ColumnType::Float64);
+        assert_eq!(column_type(&batch, "flag"), ColumnType::Boolean);
+        assert_eq!(column_type(&batch, "mixed"), ColumnType::Json);
+        assert_eq!(column_type(&batch, "empty"), ColumnType::Null);
+        assert_eq!(column_type(&batch, "text"), ColumnType::Utf8);
+    }
+
+    #[test]
+    fn append_keeps_values_that_do_not_fit() {
+        let batch = round_trip(&json!({ "a": [1, 2], "b": [0.5, 1.5] }), None);
+        let appended = append_record_batch(
+            &batch,
+            &json!({ "a": [0.5], "b": [3] }),
+            None,
+            &ColumnPolicy::Reject,
+        )
+        .unwrap();
+
+        let expected = json!({ "a": [1, 2, 0.5], "b": [0.5, 1.5, 3] });
+        assert_eq!(record_batch_to_json(&appended).unwrap(), expected);
+        assert_eq!(column_type(&appended, "a"), ColumnType::Number);
+        assert_eq!(column_type(&appended, "b"), ColumnType::Number);
+        round_trip(&expected, None);
+
+        let mut rows = Vec::new();
+        write_rows(&appended, &mut rows).unwrap();
+        let rows: Value = serde_json::from_slice(&rows).unwrap();
+        assert_eq!(rows[2], json!({ "a": 0.5, "b": 3 }));
+    }
+
+    #[test]
+    fn number_column_survives_an_ipc_round_trip() {
+        use arrow_ipc::reader::StreamReader;
+        use arrow_ipc::writer::StreamWriter;
+
+        let columns = json!({ "n": [9007199254740993_i64, null, -0.25, i64::MIN] });
+        let batch = round_trip(&columns, None);
+        let mut encoded = Vec::new();
+        let mut writer = StreamWriter::try_new(&mut encoded, &batch.schema()).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let decoded = StreamReader::try_new(encoded.as_slice(), None)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(column_type(&decoded, "n"), ColumnType::Number);
+        assert_eq!(record_batch_to_json(&decoded).unwrap(), columns);
+    }
+
+    #[test]
+    fn appended_length_matches_the_written_length() {
+        let pools = [
+            json!({ "a": [1, 2], "b": ["x", null] }),
+            json!({ "a": [], "b": [] }),
+            json!({}),
+        ];
+        let appends = [
+            (json!({ "a": [0.5], "b": ["y"] }), ColumnPolicy::Reject),
+            (
+                json!({ "a": [3, 4], "b": [[1], "z"] }),
+                ColumnPolicy::Reject,
+            ),
+            (json!({ "a": [] }), ColumnPolicy::BackfillNull),
+            (
+                json!({ "c": ["\u{e9}", "\"q\""] }),
+                ColumnPolicy::BackfillDefault(json!({ "k": 1 })),
+            ),
+        ];
+        let mut checked = 0;
+        for pool in &pools {
+            let batch = to_record_batch(pool, None).unwrap();
+            let batch_len = columns_json_len(&batch).unwrap();
+            for (data, policy) in &appends {
+                let Ok(appended) = append_record_batch(&batch, data, None, policy) else {
+                    continue;
+                };
+                assert_eq!(
+                    appended_json_len(&batch, batch_len, &appended).unwrap(),
+                    columns_json_len(&appended).unwrap(),
+                    "appending {} to {}",
+                    data,
+                    pool
+                );
+                checked += 1;
+            }
+        }
+        assert_eq!(checked, 10);
+    }
+}
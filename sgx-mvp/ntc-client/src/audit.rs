@@ -0,0 +1,153 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// ntc-client/src/audit.rs
+
+use crate::ratls::{ExpectedMeasurements, QuoteBody, QuoteVerifier};
+use crate::types::{AttestationEvidence, AuditEntry, ChainHead, CommittedResult};
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+/// Hash the first entry of the chain links to
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Returns the message the chain head signature is computed over
+pub fn head_message(length: u64, hash: &str, signed_at: u64) -> String {
+    format!("NTC-AUDIT-HEAD-V1\n{}\n{}\n{}", length, hash, signed_at)
+}
+
+/// Returns the hash of an entry, computed the same way as the enclave does
+fn entry_hash(entry: &AuditEntry) -> String {
+    let canonical = format!(
+        "NTC-AUDIT-V1\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+        entry.index,
+        entry.timestamp,
+        entry.operation,
+        entry.pool_id,
+        entry.redeemer.as_deref().unwrap_or_default(),
+        entry.code_hash.as_deref().unwrap_or_default(),
+        entry.data_version,
+        entry.outcome,
+        entry.result_hash.as_deref().unwrap_or_default(),
+        entry.prev_hash,
+    );
+    hex::encode(Sha256::digest(canonical))
+}
+
+/// Checks that `entries` are the complete audit log up to a signed head: they
+/// start at the genesis, each one links to the one before, and the last one is
+/// the head signed with `head.public_key`.
+///
+/// This proves the log is intact only if that key belongs to the enclave; check
+/// that with `verify_audit_key`.
+pub fn verify_audit_chain(entries: &[AuditEntry], head: &ChainHead) -> Result<()> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.index != index as u64 {
+            return Err(anyhow!(
+                "Entry {} is out of place, expected entry {}",
+                entry.index,
+                index
+            ));
+        }
+        if entry.prev_hash != prev_hash {
+            return Err(anyhow!("Entry {} does not link to the one before", index));
+        }
+        if entry.hash != entry_hash(entry) {
+            return Err(anyhow!("Entry {} does not match its hash", index));
+        }
+        prev_hash = entry.hash.clone();
+    }
+
+    if head.length != entries.len() as u64 || head.hash != prev_hash {
+        return Err(anyhow!(
+            "Log has {} entries but the signed head covers {}",
+            entries.len(),
+            head.length
+        ));
+    }
+
+    let public_key = decode_hex::<32>("audit public key", &head.public_key)?;
+    let public_key = VerifyingKey::from_bytes(&public_key)
+        .map_err(|e| anyhow!("Invalid audit public key: {}", e))?;
+    let signature = decode_hex::<64>("head signature", &head.signature)?;
+    public_key
+        .verify(
+            head_message(head.length, &head.hash, head.signed_at).as_bytes(),
+            &Signature::from_bytes(&signature),
+        )
+        .map_err(|_| anyhow!("Invalid head signature"))
+}
+
+/// Checks that the key signing the audit log head belongs to an enclave with
+/// the expected measurements: the evidence must carry a genuine quote whose
+/// report data ends with the hash of the key
+pub fn verify_audit_key(
+    head: &ChainHead,
+    evidence: &AttestationEvidence,
+    expected: &ExpectedMeasurements,
+    quote_verifier: &dyn QuoteVerifier,
+) -> Result<()> {
+    let quote = evidence
+        .quote
+        .as_deref()
+        .ok_or_else(|| anyhow!("Attestation evidence carries no quote"))?;
+    let quote = BASE64
+        .decode(quote)
+        .map_err(|e| anyhow!("Invalid quote encoding: {}", e))?;
+
+    let body = QuoteBody::parse(&quote)?;
+    let public_key = decode_hex::<32>("audit public key", &head.public_key)?;
+    if body.report_data[32..] != Sha256::digest(public_key)[..] {
+        return Err(anyhow!("Quote is not bound to the audit log key"));
+    }
+
+    quote_verifier.verify_quote(&quote)?;
+    expected.check(&body)
+}
+
+/// Checks that an audit log entry commits to a result returned by the enclave:
+/// its `result_hash` is the SHA-256 of the salt followed by the result
+pub fn verify_result_commitment(entry: &AuditEntry, result: &CommittedResult) -> Result<()> {
+    let salt = result
+        .salt
+        .as_deref()
+        .ok_or_else(|| anyhow!("Result was returned without a salt"))?;
+    let salt = decode_hex::<32>("result salt", salt)?;
+    let serialized = serde_json::to_vec(&result.value)
+        .map_err(|e| anyhow!("Failed to serialize result: {}", e))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(serialized);
+    if entry.result_hash.as_deref() != Some(hex::encode(hasher.finalize()).as_str()) {
+        return Err(anyhow!(
+            "Entry {} does not commit to the result",
+            entry.index
+        ));
+    }
+    Ok(())
+}
+
+fn decode_hex<const N: usize>(name: &str, value: &str) -> Result<[u8; N]> {
+    let bytes = hex::decode(value).map_err(|e| anyhow!("Invalid {}: {}", name, e))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("Invalid {}: expected {} bytes", name, N))
+}
@@ -0,0 +1,491 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// ntc-client/src/lib.rs
+
+mod audit;
+mod ratls;
+mod types;
+
+pub use audit::{
+    head_message, verify_audit_chain, verify_audit_key, verify_result_commitment, GENESIS_HASH,
+};
+pub use ratls::{
+    ExpectedMeasurements, MockQuoteVerifier, QuoteBody, QuoteVerifier, RaTlsVerifier, SGX_QUOTE_OID,
+};
+pub use types::*;
+
+use anyhow::{anyhow, Result};
+use ed25519_dalek::{Signer, SigningKey};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::{Method, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default address of the sgx-mvp server
+pub const DEFAULT_BASE_URL: &str = "https://127.0.0.1:8080";
+
+/// Entries requested per page by `full_audit_log`, the most the server returns
+const AUDIT_PAGE_SIZE: usize = 1000;
+
+/// Error returned by the server, with its stable machine-readable code.
+///
+/// Client methods return it inside `anyhow::Error`; use `downcast_ref` to
+/// react to specific codes.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: u16, // HTTP status code
+    pub code: String,
+    pub message: String,
+    pub request_id: String,
+    #[serde(default)]
+    pub violations: Vec<SchemaViolation>, // Rejected values, for `schema_violation` errors
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}, status {}, request {})",
+            self.message, self.code, self.status, self.request_id
+        )
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Client for the sgx-mvp API.
+///
+/// Every connection is verified with the given `RaTlsVerifier`, so requests
+/// only ever reach an enclave with the expected measurements. Endpoints that
+/// spend a DRT redemption or touch a job are signed with the wallet set with
+/// `with_wallet`.
+pub struct NtcClient {
+    base_url: Url,
+    http: Client,
+    wallet: Option<SigningKey>, // Solana wallet that signs requests
+}
+
+impl NtcClient {
+    /// Creates a client for the server at `base_url`, e.g. `DEFAULT_BASE_URL`
+    pub fn new(base_url: &str, verifier: RaTlsVerifier) -> Result<Self> {
+        let base_url =
+            Url::parse(base_url).map_err(|e| anyhow!("Invalid base URL '{}': {}", base_url, e))?;
+        let http = Client::builder()
+            .use_preconfigured_tls(verifier.client_config()?)
+            .build()
+            .map_err(|e| anyhow!("Failed to build HTTP client: {}", e))?;
+        Ok(Self {
+            base_url,
+            http,
+            wallet: None,
+        })
+    }
+
+    /// Signs requests with the given Solana wallet keypair
+    pub fn with_wallet(mut self, wallet: SigningKey) -> Self {
+        self.wallet = Some(wallet);
+        self
+    }
+
+    /// Base58-encoded public key of the wallet requests are signed with, if any
+    pub fn wallet(&self) -> Option<String> {
+        self.wallet
+            .as_ref()
+            .map(|wallet| bs58::encode(wallet.verifying_key().as_bytes()).into_string())
+    }
+
+    /// Calls the `health` API
+    pub fn health(&self) -> Result<String> {
+        let response = self.send(Method::GET, "/health", &[], None, false)?;
+        response
+            .text()
+            .map_err(|e| anyhow!("Failed to read response: {}", e))
+    }
+
+    /// Calls the `attestation` API
+    pub fn attestation(&self) -> Result<AttestationEvidence> {
+        self.call(Method::GET, "/attestation", &[], None, false)
+    }
+
+    /// Calls the `audit_log` API, returning up to `limit` entries from index `from`
+    pub fn audit_log(&self, from: u64, limit: usize) -> Result<AuditLogExport> {
+        let (from, limit) = (from.to_string(), limit.to_string());
+        let query = [("from", from.as_str()), ("limit", limit.as_str())];
+        self.call(Method::GET, "/audit_log", &query, None, false)
+    }
+
+    /// Fetches the whole audit log page by page, with the head signed when the
+    /// last page was read
+    pub fn full_audit_log(&self) -> Result<AuditLogExport> {
+        let mut export = self.audit_log(0, AUDIT_PAGE_SIZE)?;
+        loop {
+            let page = self.audit_log(export.entries.len() as u64, AUDIT_PAGE_SIZE)?;
+            let last_page = page.entries.is_empty();
+            export.entries.extend(page.entries);
+            export.head = page.head;
+            export.attestation = page.attestation;
+            if last_page {
+                return Ok(export);
+            }
+        }
+    }
+
+    /// Calls the `create_data_pool` API
+    pub fn create_data_pool(&self, request: &CreateDataPoolRequest) -> Result<SealedPool> {
+        self.call(
+            Method::POST,
+            "/create_data_pool",
+            &[],
+            Some(json_body(request)?),
+            true,
+        )
+    }
+
+    /// Calls the `append_data` API
+    pub fn append_data(&self, request: &AppendDataRequest) -> Result<SealedPool> {
+        self.call(
+            Method::POST,
+            "/append_data",
+            &[],
+            Some(json_body(request)?),
+            true,
+        )
+    }
+
+    /// Calls the `list_pools` API
+    pub fn list_pools(&self) -> Result<Vec<String>> {
+        self.call(Method::GET, "/list_pools", &[], None, false)
+    }
+
+    /// Calls the `delete_data_pool` API
+    pub fn delete_data_pool(&self, pool_id: &str) -> Result<()> {
+        let body = json!({ "pool_id": pool_id });
+        self.send(
+            Method::POST,
+            "/delete_data_pool",
+            &[],
+            Some(json_body(&body)?),
+            true,
+        )?;
+        Ok(())
+    }
+
+    /// Calls the `redemption_status` API
+    pub fn redemption_status(&self, signature: &str) -> Result<RedemptionStatus> {
+        let query = [("signature", signature)];
+        self.call(Method::GET, "/redemption_status", &query, None, false)
+    }
+
+    /// Calls the `execute_wasm` API, returning the result of the binary
+    pub fn execute_wasm(&self, request: &ExecuteWasmRequest) -> Result<CommittedResult> {
+        self.call_committed(
+            Method::POST,
+            "/execute_wasm",
+            &[],
+            Some(json_body(request)?),
+            true,
+        )
+    }
+
+    /// Calls the `execute_python` API, returning the output of the script
+    pub fn execute_python(&self, request: &ExecutePythonRequest) -> Result<CommittedResult> {
+        self.call_committed(
+            Method::POST,
+            "/execute_python",
+            &[],
+            Some(json_body(request)?),
+            true,
+        )
+    }
+
+    /// Calls the `submit_job` API
+    pub fn submit_job(&self, request: &JobRequest) -> Result<JobStatus> {
+        self.call(
+            Method::POST,
+            "/submit_job",
+            &[],
+            Some(json_body(request)?),
+            true,
+        )
+    }
+
+    /// Calls the `job_status` API
+    pub fn job_status(&self, job_id: &str) -> Result<JobStatus> {
+        self.call(
+            Method::GET,
+            "/job_status",
+            &[("job_id", job_id)],
+            None,
+            true,
+        )
+    }
+
+    /// Calls the `job_result` API
+    pub fn job_result(&self, job_id: &str) -> Result<CommittedResult> {
+        self.call_committed(
+            Method::GET,
+            "/job_result",
+            &[("job_id", job_id)],
+            None,
+            true,
+        )
+    }
+
+    /// Calls the `cancel_job` API
+    pub fn cancel_job(&self, job_id: &str) -> Result<JobStatus> {
+        let body = json!({ "job_id": job_id });
+        self.call(
+            Method::POST,
+            "/cancel_job",
+            &[],
+            Some(json_body(&body)?),
+            true,
+        )
+    }
+
+    /// Calls the `view_data` API, only served by builds with debug endpoints
+    pub fn view_data(&self, pool_id: &str) -> Result<Value> {
+        self.call(
+            Method::GET,
+            "/view_data",
+            &[("pool_id", pool_id)],
+            None,
+            true,
+        )
+    }
+
+    /// Calls the `migrate_data_pools` API, returning the IDs of the migrated pools
+    pub fn migrate_data_pools(&self, legacy_pool_id: Option<&str>) -> Result<Vec<String>> {
+        let body = json!({ "legacy_pool_id": legacy_pool_id });
+        let response: Value = self.call(
+            Method::POST,
+            "/migrate_data_pools",
+            &[],
+            Some(json_body(&body)?),
+            true,
+        )?;
+        field(response, "migrated")
+    }
+
+    /// Calls the `migration_key` API of the enclave that will take over the pools
+    pub fn migration_key(&self) -> Result<MigrationKey> {
+        self.call(Method::GET, "/migration_key", &[], None, false)
+    }
+
+    /// Calls the `export_migration_bundle` API, signed by an operator wallet
+    pub fn export_migration_bundle(&self, target_key: &MigrationKey) -> Result<MigrationBundle> {
+        let body = json!({ "target_key": target_key });
+        self.call(
+            Method::POST,
+            "/export_migration_bundle",
+            &[],
+            Some(json_body(&body)?),
+            true,
+        )
+    }
+
+    /// Calls the `import_migration_bundle` API, signed by an operator wallet,
+    /// returning the IDs of the imported pools
+    pub fn import_migration_bundle(&self, bundle: &MigrationBundle) -> Result<Vec<String>> {
+        let response: Value = self.call(
+            Method::POST,
+            "/import_migration_bundle",
+            &[],
+            Some(json_body(bundle)?),
+            true,
+        )?;
+        field(response, "imported")
+    }
+
+    /// Sends a request and parses the JSON response
+    fn call<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<Vec<u8>>,
+        signed: bool,
+    ) -> Result<T> {
+        self.send(method, path, query, body, signed)?
+            .json()
+            .map_err(|e| anyhow!("Failed to parse response: {}", e))
+    }
+
+    /// Sends a request for a computation result, keeping the salt the audit log
+    /// commits to it under
+    fn call_committed(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<Vec<u8>>,
+        signed: bool,
+    ) -> Result<CommittedResult> {
+        let response = self.send(method, path, query, body, signed)?;
+        let salt = response
+            .headers()
+            .get("X-NTC-Result-Salt")
+            .and_then(|salt| salt.to_str().ok())
+            .map(str::to_string);
+        let value = response
+            .json()
+            .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
+        Ok(CommittedResult { value, salt })
+    }
+
+    /// Sends a request, signing it if needed, and turns error responses into `ApiError`
+    fn send(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<Vec<u8>>,
+        signed: bool,
+    ) -> Result<Response> {
+        let mut url = self
+            .base_url
+            .join(path)
+            .map_err(|e| anyhow!("Invalid path '{}': {}", path, e))?;
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        let mut request = self.http.request(method.clone(), url.clone());
+        if signed {
+            let signed_body = body.as_deref().unwrap_or_default();
+            request = self.sign(request, &method, &url, signed_body)?;
+        }
+        if let Some(body) = body {
+            request = request
+                .header("Content-Type", "application/json")
+                .body(body);
+        }
+
+        let response = request
+            .send()
+            // Keep the source chain, which says why an enclave failed verification
+            .map_err(|e| anyhow!("Request to {} failed: {:#}", path, anyhow::Error::from(e)))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let text = response.text().unwrap_or_default();
+        match serde_json::from_str::<ApiError>(&text) {
+            Ok(mut error) => {
+                error.status = status.as_u16();
+                Err(error.into())
+            }
+            Err(_) => Err(anyhow!(
+                "Request to {} failed with {}: {}",
+                path,
+                status,
+                text
+            )),
+        }
+    }
+
+    /// Adds the wallet signature headers the server expects
+    fn sign(
+        &self,
+        request: RequestBuilder,
+        method: &Method,
+        url: &Url,
+        body: &[u8],
+    ) -> Result<RequestBuilder> {
+        let wallet_key = self
+            .wallet
+            .as_ref()
+            .ok_or_else(|| anyhow!("A wallet is required to sign this request"))?;
+        let wallet = bs58::encode(wallet_key.verifying_key().as_bytes()).into_string();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let nonce = hex::encode(rand::random::<[u8; 16]>());
+
+        let digest = request_digest(
+            method.as_str(),
+            url.path(),
+            url.query().unwrap_or_default(),
+            &wallet,
+            timestamp,
+            &nonce,
+            body,
+        );
+        let signature = wallet_key.sign(digest.as_bytes());
+
+        Ok(request
+            .header("X-NTC-Wallet", wallet)
+            .header("X-NTC-Timestamp", timestamp.to_string())
+            .header("X-NTC-Nonce", nonce)
+            .header(
+                "X-NTC-Signature",
+                bs58::encode(signature.to_bytes()).into_string(),
+            ))
+    }
+}
+
+/// Returns the hex-encoded SHA-256 digest of the canonical form of a request,
+/// which is what the wallet signs:
+///
+/// ```text
+/// NTC-REQUEST-V1
+/// <METHOD>
+/// <path>
+/// <query string, without '?'>
+/// <wallet>
+/// <timestamp>
+/// <nonce>
+/// <hex-encoded SHA-256 of the body>
+/// ```
+pub fn request_digest(
+    method: &str,
+    path: &str,
+    query: &str,
+    wallet: &str,
+    timestamp: u64,
+    nonce: &str,
+    body: &[u8],
+) -> String {
+    let canonical = format!(
+        "NTC-REQUEST-V1\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+        method.to_ascii_uppercase(),
+        path,
+        query,
+        wallet,
+        timestamp,
+        nonce,
+        hex::encode(Sha256::digest(body)),
+    );
+    hex::encode(Sha256::digest(canonical.as_bytes()))
+}
+
+/// Serializes a request body
+fn json_body(body: &impl Serialize) -> Result<Vec<u8>> {
+    serde_json::to_vec(body).map_err(|e| anyhow!("Failed to serialize request: {}", e))
+}
+
+/// Takes a field out of a JSON response object
+fn field<T: DeserializeOwned>(mut response: Value, name: &str) -> Result<T> {
+    serde_json::from_value(response[name].take())
+        .map_err(|e| anyhow!("Failed to parse '{}' in response: {}", name, e))
+}
@@ -0,0 +1,548 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// ntc-client/src/ratls.rs
+
+use anyhow::{anyhow, Result};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use sha2::{Digest, Sha256};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// OID of the X.509 extension gramine-ratls stores the raw SGX quote in
+pub const SGX_QUOTE_OID: &str = "1.2.840.113741.1.13.1";
+
+// Offsets of fields in an SGX ECDSA quote (v3), after the 48-byte header
+const REPORT_BODY: usize = 48;
+const MRENCLAVE: usize = REPORT_BODY + 64;
+const MRSIGNER: usize = REPORT_BODY + 128;
+const ISV_PROD_ID: usize = REPORT_BODY + 256;
+const ISV_SVN: usize = REPORT_BODY + 258;
+const REPORT_DATA: usize = REPORT_BODY + 320;
+const REPORT_BODY_END: usize = REPORT_BODY + 384;
+
+/// Measurements read from the report body of an SGX quote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteBody {
+    pub mrenclave: [u8; 32],
+    pub mrsigner: [u8; 32],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+    pub report_data: [u8; 64],
+}
+
+impl QuoteBody {
+    /// Reads the report body of a raw SGX quote
+    pub fn parse(quote: &[u8]) -> Result<Self> {
+        if quote.len() < REPORT_BODY_END {
+            return Err(anyhow!(
+                "Quote too short: {} bytes, expected at least {}",
+                quote.len(),
+                REPORT_BODY_END
+            ));
+        }
+        let bytes = |offset: usize, len: usize| &quote[offset..offset + len];
+        let u16_at = |offset: usize| u16::from_le_bytes([quote[offset], quote[offset + 1]]);
+
+        let mut body = Self {
+            mrenclave: [0u8; 32],
+            mrsigner: [0u8; 32],
+            isv_prod_id: u16_at(ISV_PROD_ID),
+            isv_svn: u16_at(ISV_SVN),
+            report_data: [0u8; 64],
+        };
+        body.mrenclave.copy_from_slice(bytes(MRENCLAVE, 32));
+        body.mrsigner.copy_from_slice(bytes(MRSIGNER, 32));
+        body.report_data.copy_from_slice(bytes(REPORT_DATA, 64));
+        Ok(body)
+    }
+}
+
+/// Checks that an SGX quote is genuine: signed by an SGX platform, with a TCB
+/// level the client is willing to accept.
+///
+/// Implementations typically hand the quote to the Intel DCAP quote
+/// verification library or an attestation service. Measurements are checked
+/// separately by `RaTlsVerifier`.
+pub trait QuoteVerifier: Debug + Send + Sync {
+    fn verify_quote(&self, quote: &[u8]) -> Result<()>;
+}
+
+/// Quote verifier that accepts every quote without checking its signature.
+///
+/// Only meant for offline tests, e.g. against a server with a self-made RA-TLS
+/// certificate. Measurements and the binding to the TLS key are still checked.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MockQuoteVerifier;
+
+impl QuoteVerifier for MockQuoteVerifier {
+    fn verify_quote(&self, _quote: &[u8]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Enclave identity the server must prove. Fields left as `None` are not checked.
+#[derive(Debug, Default, Clone)]
+pub struct ExpectedMeasurements {
+    pub mrenclave: Option<[u8; 32]>,
+    pub mrsigner: Option<[u8; 32]>,
+    pub isv_prod_id: Option<u16>,
+    pub min_isv_svn: Option<u16>, // Lowest security version accepted
+}
+
+impl ExpectedMeasurements {
+    /// Expects the given hex-encoded MRENCLAVE and/or MRSIGNER
+    pub fn from_hex(mrenclave: Option<&str>, mrsigner: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            mrenclave: mrenclave
+                .map(|m| parse_measurement("MRENCLAVE", m))
+                .transpose()?,
+            mrsigner: mrsigner
+                .map(|m| parse_measurement("MRSIGNER", m))
+                .transpose()?,
+            ..Self::default()
+        })
+    }
+
+    /// Checks the measurements of a quote against the expected ones
+    pub fn check(&self, body: &QuoteBody) -> Result<()> {
+        if let Some(mrenclave) = self.mrenclave {
+            if body.mrenclave != mrenclave {
+                return Err(anyhow!(
+                    "MRENCLAVE {} does not match the expected {}",
+                    hex::encode(body.mrenclave),
+                    hex::encode(mrenclave)
+                ));
+            }
+        }
+        if let Some(mrsigner) = self.mrsigner {
+            if body.mrsigner != mrsigner {
+                return Err(anyhow!(
+                    "MRSIGNER {} does not match the expected {}",
+                    hex::encode(body.mrsigner),
+                    hex::encode(mrsigner)
+                ));
+            }
+        }
+        if let Some(isv_prod_id) = self.isv_prod_id {
+            if body.isv_prod_id != isv_prod_id {
+                return Err(anyhow!(
+                    "ISV product ID {} does not match the expected {}",
+                    body.isv_prod_id,
+                    isv_prod_id
+                ));
+            }
+        }
+        if let Some(min_isv_svn) = self.min_isv_svn {
+            if body.isv_svn < min_isv_svn {
+                return Err(anyhow!(
+                    "ISV SVN {} is below the minimum {}",
+                    body.isv_svn,
+                    min_isv_svn
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// rustls certificate verifier for the RA-TLS certificate of an enclave.
+///
+/// Instead of a CA chain, the certificate is trusted because of the SGX quote
+/// embedded in it: the quote must be genuine according to the quote verifier,
+/// carry the expected measurements, and bind the certificate's public key
+/// through its report data. The server name is not checked, as the quote
+/// identifies the enclave regardless of the address it is reached at.
+#[derive(Debug)]
+pub struct RaTlsVerifier {
+    expected: ExpectedMeasurements,
+    quote_verifier: Arc<dyn QuoteVerifier>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl RaTlsVerifier {
+    /// Creates a verifier expecting the given measurements
+    pub fn new(expected: ExpectedMeasurements, quote_verifier: Arc<dyn QuoteVerifier>) -> Self {
+        Self {
+            expected,
+            quote_verifier,
+            provider: Arc::new(rustls::crypto::ring::default_provider()),
+        }
+    }
+
+    /// Verifies an RA-TLS certificate, returning the measurements of its quote
+    pub fn verify_certificate(&self, cert: &CertificateDer) -> Result<QuoteBody> {
+        let (_, cert) = x509_parser::parse_x509_certificate(cert.as_ref())
+            .map_err(|e| anyhow!("Failed to parse RA-TLS certificate: {}", e))?;
+        let quote = cert
+            .extensions()
+            .iter()
+            .find(|ext| ext.oid.to_id_string() == SGX_QUOTE_OID)
+            .map(|ext| ext.value)
+            .ok_or_else(|| anyhow!("Certificate does not carry an SGX quote"))?;
+
+        let body = QuoteBody::parse(quote)?;
+        let public_key_hash = Sha256::digest(cert.tbs_certificate.subject_pki.raw);
+        if body.report_data[..32] != public_key_hash[..] {
+            return Err(anyhow!(
+                "Quote is not bound to the certificate's public key"
+            ));
+        }
+
+        self.quote_verifier.verify_quote(quote)?;
+        self.expected.check(&body)?;
+        Ok(body)
+    }
+
+    /// Builds a TLS client configuration that only trusts enclaves passing this verifier
+    pub fn client_config(self) -> Result<ClientConfig> {
+        let provider = self.provider.clone();
+        let config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| anyhow!("Failed to create TLS config: {}", e))?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(self))
+            .with_no_client_auth();
+        Ok(config)
+    }
+}
+
+impl ServerCertVerifier for RaTlsVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.verify_certificate(end_entity)
+            .map(|_| ServerCertVerified::assertion())
+            .map_err(|e| rustls::Error::General(format!("RA-TLS verification failed: {}", e)))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+fn parse_measurement(name: &str, value: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(value.trim()).map_err(|e| anyhow!("Invalid {}: {}", name, e))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("Invalid {}: expected 32 bytes", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+    use rustls::{ClientConnection, ServerConfig, ServerConnection};
+
+    const ED25519_OID: &[u8] = &[0x06, 0x03, 0x2b, 0x65, 0x70];
+    const SGX_QUOTE_OID_DER: &[u8] = &[
+        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01,
+    ];
+
+    /// Quote verifier that rejects every quote
+    #[derive(Debug)]
+    struct RejectingQuoteVerifier;
+
+    impl QuoteVerifier for RejectingQuoteVerifier {
+        fn verify_quote(&self, _quote: &[u8]) -> Result<()> {
+            Err(anyhow!("Quote signature is invalid"))
+        }
+    }
+
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        match content.len() {
+            len @ 0..=0x7f => out.push(len as u8),
+            len @ 0x80..=0xff => out.extend_from_slice(&[0x81, len as u8]),
+            len => out.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]),
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    fn subject_public_key_info(public_key: &[u8]) -> Vec<u8> {
+        let key = der(0x03, &[&[0u8][..], public_key].concat());
+        der(0x30, &[der(0x30, ED25519_OID), key].concat())
+    }
+
+    /// Builds a synthetic quote with the given measurements and report data
+    fn quote(mrenclave: [u8; 32], mrsigner: [u8; 32], isv_svn: u16, report_data: &[u8]) -> Vec<u8> {
+        let mut quote = vec![0u8; REPORT_BODY_END];
+        quote[MRENCLAVE..MRENCLAVE + 32].copy_from_slice(&mrenclave);
+        quote[MRSIGNER..MRSIGNER + 32].copy_from_slice(&mrsigner);
+        quote[ISV_PROD_ID..ISV_PROD_ID + 2].copy_from_slice(&7u16.to_le_bytes());
+        quote[ISV_SVN..ISV_SVN + 2].copy_from_slice(&isv_svn.to_le_bytes());
+        quote[REPORT_DATA..REPORT_DATA + report_data.len()].copy_from_slice(report_data);
+        quote
+    }
+
+    /// Builds a self-signed Ed25519 certificate carrying the quote, the way
+    /// gramine-ratls does
+    fn certificate(key: &Ed25519KeyPair, quote: Option<&[u8]>) -> CertificateDer<'static> {
+        let name = der(
+            0x30,
+            &der(
+                0x31,
+                &der(
+                    0x30,
+                    &[&[0x06, 0x03, 0x55, 0x04, 0x03][..], &der(0x0c, b"ntc-test")].concat(),
+                ),
+            ),
+        );
+        let validity = der(
+            0x30,
+            &[der(0x17, b"250101000000Z"), der(0x17, b"491231235959Z")].concat(),
+        );
+        let mut tbs = [
+            der(0xa0, &der(0x02, &[2])), // v3
+            der(0x02, &[1]),
+            der(0x30, ED25519_OID),
+            name.clone(),
+            validity,
+            name,
+            subject_public_key_info(key.public_key().as_ref()),
+        ]
+        .concat();
+        if let Some(quote) = quote {
+            let extension = der(0x30, &[SGX_QUOTE_OID_DER, &der(0x04, quote)].concat());
+            tbs.extend(der(0xa3, &der(0x30, &extension)));
+        }
+        let tbs = der(0x30, &tbs);
+
+        let signature = key.sign(&tbs);
+        let signature = der(0x03, &[&[0u8][..], signature.as_ref()].concat());
+        let cert = der(0x30, &[tbs, der(0x30, ED25519_OID), signature].concat());
+        CertificateDer::from(cert)
+    }
+
+    fn key_pair() -> (Ed25519KeyPair, Vec<u8>) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        (key, pkcs8.as_ref().to_vec())
+    }
+
+    /// Certificate of an enclave with the given measurements, bound to its key
+    fn enclave_certificate(
+        key: &Ed25519KeyPair,
+        mrenclave: [u8; 32],
+        isv_svn: u16,
+    ) -> CertificateDer<'static> {
+        let report_data = Sha256::digest(subject_public_key_info(key.public_key().as_ref()));
+        certificate(
+            key,
+            Some(&quote(mrenclave, [2u8; 32], isv_svn, &report_data)),
+        )
+    }
+
+    fn verifier(expected: ExpectedMeasurements) -> RaTlsVerifier {
+        RaTlsVerifier::new(expected, Arc::new(MockQuoteVerifier))
+    }
+
+    fn expect_mrenclave(mrenclave: [u8; 32]) -> ExpectedMeasurements {
+        ExpectedMeasurements {
+            mrenclave: Some(mrenclave),
+            ..ExpectedMeasurements::default()
+        }
+    }
+
+    #[test]
+    fn accepts_expected_enclave() {
+        let (key, _) = key_pair();
+        let cert = enclave_certificate(&key, [1u8; 32], 3);
+        let expected = ExpectedMeasurements {
+            mrenclave: Some([1u8; 32]),
+            mrsigner: Some([2u8; 32]),
+            isv_prod_id: Some(7),
+            min_isv_svn: Some(3),
+        };
+
+        let body = verifier(expected).verify_certificate(&cert).unwrap();
+        assert_eq!(body.mrenclave, [1u8; 32]);
+        assert_eq!(body.mrsigner, [2u8; 32]);
+        assert_eq!(body.isv_prod_id, 7);
+        assert_eq!(body.isv_svn, 3);
+    }
+
+    #[test]
+    fn rejects_other_measurements() {
+        let (key, _) = key_pair();
+        let cert = enclave_certificate(&key, [1u8; 32], 3);
+
+        let rejected = |expected: ExpectedMeasurements| {
+            verifier(expected)
+                .verify_certificate(&cert)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(rejected(expect_mrenclave([9u8; 32])).starts_with("MRENCLAVE"));
+        assert!(rejected(ExpectedMeasurements {
+            mrsigner: Some([9u8; 32]),
+            ..ExpectedMeasurements::default()
+        })
+        .starts_with("MRSIGNER"));
+        assert!(rejected(ExpectedMeasurements {
+            isv_prod_id: Some(8),
+            ..ExpectedMeasurements::default()
+        })
+        .starts_with("ISV product ID"));
+        assert!(rejected(ExpectedMeasurements {
+            min_isv_svn: Some(4),
+            ..ExpectedMeasurements::default()
+        })
+        .starts_with("ISV SVN"));
+    }
+
+    #[test]
+    fn rejects_quote_of_another_key() {
+        let (key, _) = key_pair();
+        let (other, _) = key_pair();
+        let report_data = Sha256::digest(subject_public_key_info(other.public_key().as_ref()));
+        let cert = certificate(&key, Some(&quote([1u8; 32], [2u8; 32], 3, &report_data)));
+
+        let error = verifier(expect_mrenclave([1u8; 32]))
+            .verify_certificate(&cert)
+            .unwrap_err();
+        assert!(error.to_string().contains("not bound"));
+    }
+
+    #[test]
+    fn rejects_certificate_without_quote() {
+        let (key, _) = key_pair();
+        let error = verifier(ExpectedMeasurements::default())
+            .verify_certificate(&certificate(&key, None))
+            .unwrap_err();
+        assert!(error.to_string().contains("does not carry an SGX quote"));
+
+        let short = certificate(&key, Some(&[0u8; 100]));
+        assert!(verifier(ExpectedMeasurements::default())
+            .verify_certificate(&short)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_quote_the_verifier_rejects() {
+        let (key, _) = key_pair();
+        let cert = enclave_certificate(&key, [1u8; 32], 3);
+        let verifier = RaTlsVerifier::new(
+            expect_mrenclave([1u8; 32]),
+            Arc::new(RejectingQuoteVerifier),
+        );
+        assert!(verifier.verify_certificate(&cert).is_err());
+    }
+
+    /// Runs a TLS handshake in memory between a client trusting `verifier`
+    /// and a server presenting `cert`
+    fn handshake(
+        verifier: RaTlsVerifier,
+        cert: CertificateDer<'static>,
+        pkcs8: Vec<u8>,
+    ) -> Result<(), rustls::Error> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let server_config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![cert],
+                PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(pkcs8)),
+            )?;
+        let mut server = ServerConnection::new(Arc::new(server_config))?;
+        let mut client = ClientConnection::new(
+            Arc::new(verifier.client_config().unwrap()),
+            ServerName::try_from("enclave.example").unwrap(),
+        )?;
+
+        let mut buffer = Vec::new();
+        while client.is_handshaking() || server.is_handshaking() {
+            buffer.clear();
+            client.write_tls(&mut buffer).unwrap();
+            server.read_tls(&mut buffer.as_slice()).unwrap();
+            server.process_new_packets()?;
+
+            buffer.clear();
+            server.write_tls(&mut buffer).unwrap();
+            client.read_tls(&mut buffer.as_slice()).unwrap();
+            client.process_new_packets()?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn client_config_trusts_only_expected_enclave() {
+        let (key, pkcs8) = key_pair();
+        let cert = enclave_certificate(&key, [1u8; 32], 3);
+
+        handshake(
+            verifier(expect_mrenclave([1u8; 32])),
+            cert.clone(),
+            pkcs8.clone(),
+        )
+        .unwrap();
+
+        let error = handshake(verifier(expect_mrenclave([9u8; 32])), cert, pkcs8).unwrap_err();
+        assert!(error.to_string().contains("RA-TLS verification failed"));
+    }
+
+    #[test]
+    fn parses_hex_measurements() {
+        let mrenclave = "01".repeat(32);
+        let expected = ExpectedMeasurements::from_hex(Some(&mrenclave), None).unwrap();
+        assert_eq!(expected.mrenclave, Some([1u8; 32]));
+        assert_eq!(expected.mrsigner, None);
+
+        assert!(ExpectedMeasurements::from_hex(Some("zz"), None).is_err());
+        assert!(ExpectedMeasurements::from_hex(None, Some(&"01".repeat(31))).is_err());
+    }
+}
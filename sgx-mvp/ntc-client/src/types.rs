@@ -0,0 +1,290 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// ntc-client/src/types.rs
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// What an append does with columns that the data pool has but the new data
+/// leaves out, or that the new data adds
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnPolicy {
+    /// Reject the append
+    #[default]
+    Reject,
+    /// Fill the rows that have no value for a column with null
+    BackfillNull,
+    /// Fill the rows that have no value for a column with the given value
+    BackfillDefault(Value),
+}
+
+/// Format in which data is uploaded to a data pool
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DataFormat {
+    /// Columns, records or NDJSON
+    #[default]
+    Json,
+    /// CSV text, read with the request's CSV options
+    Csv,
+    /// Base64-encoded Parquet file
+    Parquet,
+}
+
+/// How the enclave reads CSV data
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    pub header: bool,                      // Whether the first line names the columns
+    pub delimiter: char,                   // Character between fields, which must be ASCII
+    pub column_names: Option<Vec<String>>, // Names of the columns in order, replacing the header
+    pub types: BTreeMap<String, CsvType>,  // Types of columns, overriding schema and inference
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            header: true,
+            delimiter: ',',
+            column_names: None,
+            types: BTreeMap::new(),
+        }
+    }
+}
+
+/// Type that a CSV column is read as, named as in JSON Schema
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvType {
+    Boolean,
+    Integer,
+    Number,
+    String,
+}
+
+/// Body of the `create_data_pool` API
+#[derive(Serialize, Debug, Clone)]
+pub struct CreateDataPoolRequest {
+    pub pool_id: String,             // Pool PDA of the new data pool
+    pub data: Value,                 // Data to be sealed, in the given format
+    pub schema: Option<Value>,       // JSON Schema that the data and all appends must satisfy
+    pub column_policy: ColumnPolicy, // What appends do with new or missing columns
+    pub format: DataFormat,          // Format of the data
+    pub csv: CsvOptions,             // How to read CSV data
+}
+
+/// Body of the `append_data` API
+#[derive(Serialize, Debug, Clone)]
+pub struct AppendDataRequest {
+    pub pool_id: String,              // Pool PDA of the data pool to append to
+    pub drt_type: String,             // Type of the redeemed DRT
+    pub redemption_signature: String, // Signature of the `redeem_drt` transaction
+    pub data: Value,                  // Data to append, in the given format
+    pub format: DataFormat,           // Format of the data
+    pub csv: CsvOptions,              // How to read CSV data
+}
+
+/// Layout in which executed code receives the data of a pool
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DataLayout {
+    /// `{ "col": [..] }`, as the pool is stored
+    #[default]
+    Columns,
+    /// `[{ "col": v, .. }]`, one record per row
+    Rows,
+}
+
+/// Body of the `execute_wasm` API
+#[derive(Serialize, Debug, Clone)]
+pub struct ExecuteWasmRequest {
+    pub pool_id: String,              // Pool PDA of the data pool to compute on
+    pub drt_type: String,             // Type of the redeemed DRT
+    pub redemption_signature: String, // Signature of the `redeem_drt` transaction
+    pub github_url: String,           // GitHub URL to the WASM binary
+    pub expected_hash: String,        // Expected SHA256 hash of the WASM binary
+    pub json_schema: Value,           // JSON schema for the input data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypt_result_to: Option<ResultRecipient>, // Key to encrypt the result to, if any
+    pub data_layout: DataLayout,      // Layout of the data passed to the binary
+}
+
+/// Body of the `execute_python` API
+#[derive(Serialize, Debug, Clone)]
+pub struct ExecutePythonRequest {
+    pub pool_id: String,              // Pool PDA of the data pool to compute on
+    pub drt_type: String,             // Type of the redeemed DRT
+    pub redemption_signature: String, // Signature of the `redeem_drt` transaction
+    pub github_url: String,           // GitHub URL to the script
+    pub expected_hash: String,        // Expected SHA256 hash of the script
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypt_result_to: Option<ResultRecipient>, // Key to encrypt the result to, if any
+    pub data_layout: DataLayout,      // Layout of the data passed to the script
+}
+
+/// Body of the `submit_job` API
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "execution_type", rename_all = "lowercase")]
+pub enum JobRequest {
+    Wasm(ExecuteWasmRequest),
+    Python(ExecutePythonRequest),
+}
+
+/// Public key a computation result is encrypted to
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", content = "key", rename_all = "lowercase")]
+pub enum ResultRecipient {
+    X25519(String), // Base64-encoded X25519 public key
+    Solana(String), // Base58-encoded Solana wallet
+}
+
+/// Response of the `create_data_pool` and `append_data` APIs
+#[derive(Deserialize, Debug, Clone)]
+pub struct SealedPool {
+    pub message: String,
+    pub content_id: String, // ID of the sealed pool in the store
+}
+
+/// Value rejected by the schema of a data pool
+#[derive(Deserialize, Debug, Clone)]
+pub struct SchemaViolation {
+    pub path: String,        // JSON pointer to the offending value, e.g. `/age/3`
+    pub schema_path: String, // JSON pointer to the schema keyword it fails
+    pub message: String,     // What is wrong with the value
+}
+
+/// Response of the `redemption_status` API
+#[derive(Deserialize, Debug, Clone)]
+pub struct RedemptionStatus {
+    pub signature: String,
+    pub consumed: bool,
+    // Only set once the redemption has been used
+    pub pool: Option<String>,
+    pub drt_type: Option<String>,
+    pub execution_type: Option<String>,
+    pub redeemer: Option<String>,
+    pub consumed_at: Option<u64>,
+}
+
+/// Lifecycle of an execution job
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// Response of the `submit_job`, `job_status` and `cancel_job` APIs
+#[derive(Deserialize, Debug, Clone)]
+pub struct JobStatus {
+    pub job_id: String,
+    pub status: JobState,
+    // Not returned by `submit_job`
+    pub execution_type: Option<String>,
+    pub pool_id: Option<String>,
+    pub submitted_at: Option<u64>, // Unix timestamps (seconds)
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    pub error: Option<JobError>, // Set when the job failed
+}
+
+/// Error a failed job ended with
+#[derive(Deserialize, Debug, Clone)]
+pub struct JobError {
+    pub code: String,
+    pub message: String,
+    pub status: u16, // HTTP status the error would have had
+}
+
+/// Response of the `attestation` API
+#[derive(Deserialize, Debug, Clone)]
+pub struct AttestationEvidence {
+    pub quote: Option<String>, // Base64-encoded SGX quote, `None` without SGX
+    pub mrenclave: String,
+    pub mrsigner: Option<String>,
+    pub isv_prod_id: Option<u16>,
+    pub isv_svn: Option<u16>,
+    pub report_data: String,
+    pub tls_public_key_hash: String,
+    pub audit_public_key: String, // Hex Ed25519 key that signs the audit log head
+    pub key_provider: String,
+    pub version: String,
+    pub commit: Option<String>,
+}
+
+/// Result of a computation, with the salt its audit log entry commits to it under
+#[derive(Debug, Clone)]
+pub struct CommittedResult {
+    pub value: Value,
+    pub salt: Option<String>, // Hex salt from the `x-ntc-result-salt` header
+}
+
+/// Entry of the audit log, linked to the previous one by `prev_hash`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditEntry {
+    pub index: u64,
+    pub timestamp: u64,    // Unix timestamp (seconds) of the operation
+    pub operation: String, // create_data_pool, append_data, execute_wasm, execute_python or delete_data_pool
+    pub pool_id: String,
+    pub redeemer: Option<String>, // Wallet that redeemed the DRT or, without one, signed the request
+    pub code_hash: Option<String>, // SHA-256 of the WASM binary or script that ran
+    pub data_version: String,     // SHA-256 of the sealed pool written or read
+    pub outcome: String,          // `succeeded` or the error code returned
+    pub result_hash: Option<String>, // SHA-256 of the salt returned with the result, followed by the result
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+/// Last entry of the audit log, signed by the enclave
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChainHead {
+    pub length: u64, // Number of entries in the log
+    pub hash: String,
+    pub signed_at: u64,
+    pub public_key: String, // Hex Ed25519 key, bound to the enclave by `AttestationEvidence`
+    pub signature: String,  // Hex Ed25519 signature
+}
+
+/// Response of the `audit_log` API
+#[derive(Deserialize, Debug, Clone)]
+pub struct AuditLogExport {
+    pub entries: Vec<AuditEntry>,
+    pub head: ChainHead,
+    pub attestation: AttestationEvidence,
+}
+
+/// Key an enclave takes migration bundles under, returned by the `migration_key` API
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MigrationKey {
+    pub mrenclave: String,     // Hex MRENCLAVE of the enclave holding the key
+    pub public_key: String,    // Base64 X25519 public key bundles are encrypted to
+    pub quote: Option<String>, // Base64 quote binding the key, none without SGX
+}
+
+/// Encrypted data pools handed from one enclave build to another
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MigrationBundle {
+    pub source_mrenclave: String, // Hex MRENCLAVE of the enclave that exported the bundle
+    pub target_mrenclave: String, // Hex MRENCLAVE of the enclave allowed to import it
+    pub source_quote: Option<String>, // Base64 quote of the source over `enc` and `ciphertext`
+    pub enc: String,              // Base64 HPKE encapsulated key
+    pub ciphertext: String,       // Base64 HPKE ciphertext of the pools and redemptions
+}
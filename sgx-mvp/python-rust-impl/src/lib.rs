@@ -18,8 +18,43 @@ use anyhow::Result;
 use pyo3::prelude::*;
 use pyo3::types::PyDict;
 use serde_json::Value;
+use std::fmt;
 
-pub fn run_python(json_data: &Value, py_file_path: &'static str) -> Result<Value> {
+/// Exception raised by a Python script
+#[derive(Debug)]
+pub struct PythonException {
+    pub exception_type: String, // Name of the exception class, e.g. `ValueError`
+    pub message: String,        // Message the exception was raised with
+}
+
+impl PythonException {
+    fn from_err(py: Python, err: PyErr) -> Self {
+        let exception_type = err
+            .ptype(py)
+            .name()
+            .map(str::to_string)
+            .unwrap_or_else(|_| "Exception".to_string());
+        let message = err
+            .pvalue(py)
+            .str()
+            .map(|message| message.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self {
+            exception_type,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for PythonException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.exception_type, self.message)
+    }
+}
+
+impl std::error::Error for PythonException {}
+
+pub fn run_python(json_data: &str, py_file_path: &str) -> Result<Value> {
     Python::with_gil(|py| {
         // Open and read the Python file contents
         let code = std::fs::read_to_string(py_file_path)
@@ -28,16 +63,18 @@ pub fn run_python(json_data: &Value, py_file_path: &'static str) -> Result<Value
         // Create a Python dictionary to hold the data
         let locals = PyDict::new(py);
 
-        // Convert the JSON data to a string and set it in the Python locals
-        locals.set_item("data", serde_json::to_string(json_data)?)?;
+        // Set the JSON data, already serialized, in the Python locals
+        locals.set_item("data", json_data)?;
 
         // Run the Python code from the file in the current Python context
-        py.run(&code, None, None)?;
+        py.run(&code, None, None)
+            .map_err(|e| PythonException::from_err(py, e))?;
 
         // Execute the mean calculation in the current context
         let result: String = py
-            .eval("exec(json.loads(data))", None, Some(locals))?
-            .extract()?;
+            .eval("exec(json.loads(data))", None, Some(locals))
+            .and_then(|result| result.extract())
+            .map_err(|e| PythonException::from_err(py, e))?;
 
         // Parse the result back into a Rust serde_json::Value
         let python_result: Value = serde_json::from_str(&result)?;
@@ -0,0 +1,157 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sealed-store/src/ipfs.rs
+
+use crate::{http_client, SealedStore};
+use anyhow::{anyhow, Result};
+use reqwest::blocking::multipart::{Form, Part};
+use reqwest::blocking::{Client, Response};
+use serde_json::Value;
+
+/// Default kubo RPC API address, as served by a local `ipfs daemon`
+pub const DEFAULT_IPFS_API_URL: &str = "http://127.0.0.1:5001";
+
+/// MFS directory holding one file per stored blob
+const MFS_ROOT: &str = "/ntc-pools";
+
+/// Stores sealed blobs in IPFS through the kubo RPC API.
+///
+/// Blobs are written to the kubo node's mutable file system (MFS), which keeps
+/// them pinned and maps each key to the CID of its latest version.
+pub struct IpfsStore {
+    api_url: String,
+    client: Client,
+}
+
+impl IpfsStore {
+    /// Creates a store backed by the kubo node at `api_url`
+    pub fn new(api_url: &str) -> Result<Self> {
+        Ok(Self {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            client: http_client(api_url)?,
+        })
+    }
+
+    /// Calls a kubo RPC command, returning the response if it succeeded
+    fn call(&self, command: &str, args: &[(&str, &str)], form: Option<Form>) -> Result<Response> {
+        let mut request = self
+            .client
+            .post(format!("{}/api/v0/{}", self.api_url, command))
+            .query(args);
+        if let Some(form) = form {
+            request = request.multipart(form);
+        }
+
+        let response = request
+            .send()
+            .map_err(|e| anyhow!("IPFS request failed: {}", e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body: Value = response.json().unwrap_or_default();
+            let message = body
+                .get("Message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error");
+            return Err(anyhow!("IPFS {} failed ({}): {}", command, status, message));
+        }
+        Ok(response)
+    }
+
+    /// Returns the CID of the MFS file for `key`, or `None` if it does not exist
+    fn stat(&self, key: &str) -> Result<Option<String>> {
+        let path = format!("{}/{}", MFS_ROOT, key);
+        match self.call("files/stat", &[("arg", &path)], None) {
+            Ok(response) => {
+                let stat: Value = response
+                    .json()
+                    .map_err(|e| anyhow!("Failed to parse IPFS response: {}", e))?;
+                let cid = stat
+                    .get("Hash")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| anyhow!("IPFS response has no CID"))?;
+                Ok(Some(cid.to_string()))
+            }
+            Err(e) if e.to_string().contains("does not exist") => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl SealedStore for IpfsStore {
+    fn put(&self, key: &str, sealed_data: &[u8]) -> Result<String> {
+        let path = format!("{}/{}", MFS_ROOT, key);
+        let form = Form::new().part(
+            "file",
+            Part::bytes(sealed_data.to_vec()).file_name(key.to_string()),
+        );
+        self.call(
+            "files/write",
+            &[
+                ("arg", &path),
+                ("create", "true"),
+                ("truncate", "true"),
+                ("parents", "true"),
+            ],
+            Some(form),
+        )?;
+        self.stat(key)?
+            .ok_or_else(|| anyhow!("Sealed data was not written to IPFS"))
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let path = format!("{}/{}", MFS_ROOT, key);
+        let response = self.call("files/read", &[("arg", &path)], None)?;
+        let bytes = response
+            .bytes()
+            .map_err(|e| anyhow!("Failed to read sealed data from IPFS: {}", e))?;
+        Ok(bytes.to_vec())
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.stat(key)?.is_some())
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let listing: Value = match self.call("files/ls", &[("arg", MFS_ROOT)], None) {
+            Ok(response) => response
+                .json()
+                .map_err(|e| anyhow!("Failed to parse IPFS response: {}", e))?,
+            // Nothing has been stored yet
+            Err(e) if e.to_string().contains("does not exist") => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let keys = listing
+            .get("Entries")
+            .and_then(Value::as_array)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|entry| entry.get("Name").and_then(Value::as_str))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(keys)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let path = format!("{}/{}", MFS_ROOT, key);
+        self.call("files/rm", &[("arg", &path)], None)?;
+        Ok(())
+    }
+}
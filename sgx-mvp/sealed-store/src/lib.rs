@@ -0,0 +1,112 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sealed-store/src/lib.rs
+
+mod ipfs;
+mod local;
+mod s3;
+
+pub use ipfs::{IpfsStore, DEFAULT_IPFS_API_URL};
+pub use local::LocalStore;
+pub use s3::S3Store;
+
+use anyhow::{anyhow, Result};
+use reqwest::blocking::Client;
+use reqwest::Certificate;
+use sha2::{Digest, Sha256};
+use std::fs::{self, read, File};
+use std::io::Write;
+use std::path::Path;
+
+/// Storage backend for sealed blobs.
+///
+/// Blobs are already encrypted and authenticated by the enclave, so backends
+/// only need to store them durably. Keys are validated by the caller and are
+/// safe to use as file names and URL path segments.
+pub trait SealedStore: Send + Sync {
+    /// Stores a sealed blob under `key`, replacing any previous one, and returns its content ID
+    fn put(&self, key: &str, sealed_data: &[u8]) -> Result<String>;
+
+    /// Reads the sealed blob stored under `key`
+    fn get(&self, key: &str) -> Result<Vec<u8>>;
+
+    /// Returns whether a sealed blob is stored under `key`
+    fn exists(&self, key: &str) -> Result<bool>;
+
+    /// Lists the keys of all stored blobs
+    fn list(&self) -> Result<Vec<String>>;
+
+    /// Removes the sealed blob stored under `key`
+    fn delete(&self, key: &str) -> Result<()>;
+}
+
+/// Atomically replaces the file at `path` with `data`.
+///
+/// The data is written to a temporary file in the same directory, flushed to
+/// disk and then renamed over the target, so a crash leaves either the old or
+/// the new contents in place but never a partially written file.
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("Invalid path: {}", path.display()))?;
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("Invalid path: {}", path.display()))?;
+    let temp_path = dir.join(format!(".{}.tmp", file_name));
+
+    let mut file =
+        File::create(&temp_path).map_err(|e| anyhow!("Failed to create temporary file: {}", e))?;
+    file.write_all(data)
+        .map_err(|e| anyhow!("Failed to write to temporary file: {}", e))?;
+    file.sync_all()
+        .map_err(|e| anyhow!("Failed to flush temporary file: {}", e))?;
+    drop(file);
+
+    if let Err(e) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(anyhow!("Failed to replace {}: {}", path.display(), e));
+    }
+
+    // Persist the rename itself
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .map_err(|e| anyhow!("Failed to flush directory {}: {}", dir.display(), e))
+}
+
+/// Content ID of a blob in stores that are not content-addressed
+fn sha256_content_id(data: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(data)))
+}
+
+/// Builds an HTTP client for a storage endpoint
+fn http_client(url: &str) -> Result<Client> {
+    let mut builder = Client::builder().use_rustls_tls();
+
+    // Trust the system CA bundle for HTTPS endpoints
+    if url.starts_with("https://") {
+        let ca_bundle = read("/etc/ssl/certs/ca-certificates.crt")
+            .map_err(|e| anyhow!("Failed to read CA certificates: {}", e))?;
+        let ca_cert = Certificate::from_pem(&ca_bundle)
+            .map_err(|e| anyhow!("Failed to create certificate from PEM: {}", e))?;
+        builder = builder.add_root_certificate(ca_cert);
+    }
+
+    builder
+        .build()
+        .map_err(|e| anyhow!("Failed to build HTTP client: {}", e))
+}
@@ -0,0 +1,74 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sealed-store/src/local.rs
+
+use crate::{sha256_content_id, write_atomic, SealedStore};
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Stores sealed blobs as files in a local directory
+pub struct LocalStore {
+    dir: PathBuf,
+}
+
+impl LocalStore {
+    /// Opens the store rooted at `dir`, creating the directory if needed
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .map_err(|e| anyhow!("Failed to create directory {}: {}", dir.display(), e))?;
+        Ok(Self { dir })
+    }
+}
+
+impl SealedStore for LocalStore {
+    fn put(&self, key: &str, sealed_data: &[u8]) -> Result<String> {
+        write_atomic(&self.dir.join(key), sealed_data)?;
+        Ok(sha256_content_id(sealed_data))
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>> {
+        fs::read(self.dir.join(key)).map_err(|e| anyhow!("Failed to read sealed data: {}", e))
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.dir.join(key).is_file())
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let entries =
+            fs::read_dir(&self.dir).map_err(|e| anyhow!("Failed to read directory: {}", e))?;
+
+        let mut keys = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| anyhow!("Failed to read directory: {}", e))?;
+            if let Some(name) = entry.file_name().to_str() {
+                // Skip temporary files left behind by an interrupted write
+                if entry.path().is_file() && !name.starts_with('.') {
+                    keys.push(name.to_string());
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        fs::remove_file(self.dir.join(key))
+            .map_err(|e| anyhow!("Failed to delete sealed data: {}", e))
+    }
+}
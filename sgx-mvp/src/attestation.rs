@@ -0,0 +1,273 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/attestation.rs
+
+use crate::keys::{key_provider, KeyProviderKind};
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rustls::pki_types::CertificateDer;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::ffi::{c_void, CStr};
+use std::ptr;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Version of this server build
+pub const BUILD_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Git commit this server was built from, if the build recorded it
+pub const BUILD_COMMIT: Option<&str> = option_env!("NTC_GIT_COMMIT");
+
+// Offsets of fields in an SGX ECDSA quote (v3), after the 48-byte header
+const REPORT_BODY: usize = 48;
+const MRENCLAVE: usize = REPORT_BODY + 64;
+const MRSIGNER: usize = REPORT_BODY + 128;
+const ISV_PROD_ID: usize = REPORT_BODY + 256;
+const ISV_SVN: usize = REPORT_BODY + 258;
+const REPORT_DATA: usize = REPORT_BODY + 320;
+const REPORT_BODY_END: usize = REPORT_BODY + 384;
+
+/// Intel DCAP quote verification library, loaded on first use
+const QVL_LIBRARY: &CStr = c"libsgx_dcap_quoteverify.so.1";
+const QVL_VERIFY_QUOTE: &CStr = c"sgx_qv_verify_quote";
+const SGX_QL_SUCCESS: u32 = 0x0000;
+const SGX_QL_QV_RESULT_OK: u32 = 0x0000;
+
+/// `sgx_qv_verify_quote` of the DCAP quote verification library
+type VerifyQuoteFn = unsafe extern "C" fn(
+    quote: *const u8,
+    quote_size: u32,
+    quote_collateral: *const c_void,
+    expiration_check_date: libc::time_t,
+    collateral_expiration_status: *mut u32,
+    quote_verification_result: *mut u32,
+    qve_report_info: *mut c_void,
+    supplemental_data_size: u32,
+    supplemental_data: *mut u8,
+) -> u32;
+
+/// Measurements read from the report body of an SGX quote
+pub struct QuoteBody {
+    pub mrenclave: [u8; 32],
+    pub mrsigner: [u8; 32],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+    pub report_data: [u8; 64],
+}
+
+impl QuoteBody {
+    /// Reads the report body of a raw SGX quote
+    pub fn parse(quote: &[u8]) -> Result<Self> {
+        if quote.len() < REPORT_BODY_END {
+            return Err(anyhow!(
+                "Quote too short: {} bytes, expected at least {}",
+                quote.len(),
+                REPORT_BODY_END
+            ));
+        }
+        let bytes = |offset: usize, len: usize| &quote[offset..offset + len];
+        let u16_at = |offset: usize| u16::from_le_bytes([quote[offset], quote[offset + 1]]);
+
+        let mut body = Self {
+            mrenclave: [0u8; 32],
+            mrsigner: [0u8; 32],
+            isv_prod_id: u16_at(ISV_PROD_ID),
+            isv_svn: u16_at(ISV_SVN),
+            report_data: [0u8; 64],
+        };
+        body.mrenclave.copy_from_slice(bytes(MRENCLAVE, 32));
+        body.mrsigner.copy_from_slice(bytes(MRSIGNER, 32));
+        body.report_data.copy_from_slice(bytes(REPORT_DATA, 64));
+        Ok(body)
+    }
+}
+
+/// Checks that an SGX quote is genuine, returning its report body.
+///
+/// Measurements and report data are checked separately by the caller.
+pub trait QuoteVerifier: Send + Sync {
+    fn verify_quote(&self, quote: &[u8]) -> Result<QuoteBody>;
+}
+
+/// Verifies quotes of other enclaves with the Intel DCAP quote verification
+/// library. Collateral is fetched through the quote provider library set up in
+/// `/etc/sgx_default_qcnl.conf`, and only quotes from a platform whose TCB is
+/// up to date are accepted.
+pub struct DcapQuoteVerifier;
+
+impl QuoteVerifier for DcapQuoteVerifier {
+    fn verify_quote(&self, quote: &[u8]) -> Result<QuoteBody> {
+        let body = QuoteBody::parse(quote)?;
+        let verify_quote = dcap_verify_quote()?;
+        let quote_size = u32::try_from(quote.len())
+            .map_err(|_| anyhow!("Quote too long: {} bytes", quote.len()))?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| anyhow!("System clock is before the Unix epoch: {}", e))?
+            .as_secs() as libc::time_t;
+
+        let mut expiration_status = 1u32;
+        let mut result = u32::MAX;
+        // SAFETY: the quote pointer and size describe a live slice, the output
+        // parameters point at locals, and the optional collateral, QvE report
+        // and supplemental data are left out with null pointers
+        let status = unsafe {
+            verify_quote(
+                quote.as_ptr(),
+                quote_size,
+                ptr::null(),
+                now,
+                &mut expiration_status,
+                &mut result,
+                ptr::null_mut(),
+                0,
+                ptr::null_mut(),
+            )
+        };
+        if status != SGX_QL_SUCCESS {
+            return Err(anyhow!(
+                "Quote verification failed with error {:#06x}",
+                status
+            ));
+        }
+        if expiration_status != 0 {
+            return Err(anyhow!("Quote verification collateral has expired"));
+        }
+        if result != SGX_QL_QV_RESULT_OK {
+            return Err(anyhow!(
+                "Quote is not trusted, verification result {:#06x}",
+                result
+            ));
+        }
+        Ok(body)
+    }
+}
+
+/// Loads `sgx_qv_verify_quote` from the DCAP quote verification library
+fn dcap_verify_quote() -> Result<VerifyQuoteFn> {
+    static VERIFY_QUOTE: OnceLock<Result<VerifyQuoteFn, String>> = OnceLock::new();
+    VERIFY_QUOTE
+        .get_or_init(|| {
+            // SAFETY: both names are NUL-terminated, and the symbol is the
+            // library's documented entry point with the signature of `VerifyQuoteFn`.
+            // The library is never unloaded, so the pointer stays valid.
+            unsafe {
+                let library = libc::dlopen(QVL_LIBRARY.as_ptr(), libc::RTLD_NOW);
+                if library.is_null() {
+                    return Err(format!("Failed to load {}", QVL_LIBRARY.to_string_lossy()));
+                }
+                let symbol = libc::dlsym(library, QVL_VERIFY_QUOTE.as_ptr());
+                if symbol.is_null() {
+                    return Err(format!(
+                        "{} has no {}",
+                        QVL_LIBRARY.to_string_lossy(),
+                        QVL_VERIFY_QUOTE.to_string_lossy()
+                    ));
+                }
+                Ok(std::mem::transmute::<*mut c_void, VerifyQuoteFn>(symbol))
+            }
+        })
+        .clone()
+        .map_err(|e| anyhow!(e))
+}
+
+/// Returns the SHA-256 hash of the DER-encoded public key of a certificate.
+///
+/// This is the value gramine-ratls binds into the report data of its quote, so
+/// a client can tie the TLS session to the attested enclave.
+pub fn public_key_hash(cert: &CertificateDer) -> Result<[u8; 32]> {
+    let (_, cert) = x509_parser::parse_x509_certificate(cert.as_ref())
+        .map_err(|e| anyhow!("Failed to parse RA-TLS certificate: {}", e))?;
+    Ok(Sha256::digest(cert.tbs_certificate.subject_pki.raw).into())
+}
+
+/// Attestation evidence for the running enclave, served by `/attestation`.
+///
+/// The quote is generated on first use, then reused, as the measurements cannot
+/// change while the enclave runs. Its report data holds the hash of the RA-TLS
+/// public key followed by the hash of the key that signs the audit log head.
+pub struct Attestation {
+    key_provider: KeyProviderKind,
+    tls_public_key_hash: [u8; 32],
+    audit_public_key: [u8; 32],
+    evidence: Mutex<Option<Value>>,
+}
+
+impl Attestation {
+    /// Creates the evidence source for an enclave serving the given RA-TLS
+    /// certificate and signing its audit log with the given key
+    pub fn new(
+        cert: &CertificateDer,
+        key_provider: KeyProviderKind,
+        audit_public_key: [u8; 32],
+    ) -> Result<Self> {
+        Ok(Self {
+            key_provider,
+            tls_public_key_hash: public_key_hash(cert)?,
+            audit_public_key,
+            evidence: Mutex::new(None),
+        })
+    }
+
+    /// Returns the attestation evidence as JSON, generating the quote if needed
+    pub fn evidence(&self) -> Result<Value> {
+        let mut evidence = self.evidence.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(evidence) = evidence.as_ref() {
+            return Ok(evidence.clone());
+        }
+
+        let mut report_data = [0u8; 64];
+        report_data[..32].copy_from_slice(&self.tls_public_key_hash);
+        report_data[32..].copy_from_slice(&Sha256::digest(self.audit_public_key));
+        let mut generated = match key_provider().quote(&report_data)? {
+            Some(quote) => {
+                let body = QuoteBody::parse(&quote)?;
+                if body.report_data != report_data {
+                    return Err(anyhow!("Quote does not carry the requested report data"));
+                }
+                json!({
+                    "quote": BASE64.encode(&quote),
+                    "mrenclave": hex::encode(body.mrenclave),
+                    "mrsigner": hex::encode(body.mrsigner),
+                    "isv_prod_id": body.isv_prod_id,
+                    "isv_svn": body.isv_svn,
+                    "report_data": hex::encode(body.report_data),
+                })
+            }
+            // Without SGX there is no quote, only the measurement the provider reports
+            None => json!({
+                "quote": null,
+                "mrenclave": hex::encode(key_provider().mrenclave()?),
+                "mrsigner": null,
+                "isv_prod_id": null,
+                "isv_svn": null,
+                "report_data": hex::encode(report_data),
+            }),
+        };
+
+        generated["tls_public_key_hash"] = json!(hex::encode(self.tls_public_key_hash));
+        generated["audit_public_key"] = json!(hex::encode(self.audit_public_key));
+        generated["key_provider"] = json!(format!("{:?}", self.key_provider).to_lowercase());
+        generated["version"] = json!(BUILD_VERSION);
+        generated["commit"] = json!(BUILD_COMMIT);
+
+        *evidence = Some(generated.clone());
+        Ok(generated)
+    }
+}
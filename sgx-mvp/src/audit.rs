@@ -0,0 +1,750 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/audit.rs
+
+use crate::keys::key_provider;
+use crate::sealing::{migrate_sealed_data, seal_data, unseal_data, SealingPolicy};
+use anyhow::{anyhow, Result};
+use ed25519_dalek::{Signer, SigningKey};
+use hkdf::Hkdf;
+use rand::RngCore;
+use sealed_store::write_atomic;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Pool ID the entries of the audit log are sealed under
+const SEALING_LABEL: &str = "audit-log";
+
+/// Pool ID the recorded chain head is sealed under
+const HEAD_SEALING_LABEL: &str = "audit-log-head";
+
+/// HKDF label of the key that signs the chain head
+const SIGNING_KEY_LABEL: &str = "ntc-audit-signing-key";
+
+/// Format of entries written before the chain head was recorded
+const PRE_HEAD_FORMAT: u32 = 1;
+
+/// Format of entries written since, which are never accepted without a head
+const ENTRY_FORMAT: u32 = 2;
+
+/// Response header carrying the salt the audit log commits to a result under
+pub const RESULT_SALT_HEADER: &str = "x-ntc-result-salt";
+
+/// Hash the first entry of the chain links to
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Most entries returned by a single export
+pub const MAX_EXPORT_ENTRIES: usize = 1000;
+
+/// Operations on data pools that are recorded in the audit log
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOperation {
+    CreateDataPool,
+    AppendData,
+    ExecuteWasm,
+    ExecutePython,
+    DeleteDataPool,
+}
+
+impl AuditOperation {
+    /// Name of the operation, as used in the chain hash
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditOperation::CreateDataPool => "create_data_pool",
+            AuditOperation::AppendData => "append_data",
+            AuditOperation::ExecuteWasm => "execute_wasm",
+            AuditOperation::ExecutePython => "execute_python",
+            AuditOperation::DeleteDataPool => "delete_data_pool",
+        }
+    }
+}
+
+/// Operation to record in the audit log
+pub struct AuditEvent<'a> {
+    pub operation: AuditOperation,
+    pub pool_id: &'a str,
+    pub redeemer: Option<&'a str>, // Wallet that redeemed the DRT or, without one, signed the request
+    pub code_hash: Option<&'a str>, // SHA-256 of the WASM binary or script that ran
+    pub data_version: &'a str,     // Hash of the sealed pool written or read
+    pub outcome: &'a str,          // `succeeded` or the error code returned
+    pub result: Option<&'a Value>, // Result returned, if any, which the entry commits to
+}
+
+/// Result of a computation, with the salt its audit log entry commits to it under
+#[derive(Clone, Debug)]
+pub struct CommittedResult {
+    pub value: Value,
+    pub salt: Option<String>, // Hex salt, absent if no entry commits to the result
+}
+
+/// Entry of the audit log, linked to the previous one by `prev_hash`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub index: u64,
+    pub timestamp: u64, // Unix timestamp (seconds) of the operation
+    pub operation: AuditOperation,
+    pub pool_id: String,
+    pub redeemer: Option<String>,
+    pub code_hash: Option<String>,
+    pub data_version: String,
+    pub outcome: String,
+    pub result_hash: Option<String>, // Hex SHA-256 of the salt followed by the result
+    pub prev_hash: String,           // `hash` of the previous entry, `GENESIS_HASH` for the first
+    pub hash: String,                // Hex SHA-256 of `canonical_form`
+}
+
+impl AuditEntry {
+    /// Returns the text the entry's hash is computed over: a version tag and
+    /// every field in order, one per line, with absent values left empty
+    pub fn canonical_form(&self) -> String {
+        format!(
+            "NTC-AUDIT-V1\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+            self.index,
+            self.timestamp,
+            self.operation.name(),
+            self.pool_id,
+            self.redeemer.as_deref().unwrap_or_default(),
+            self.code_hash.as_deref().unwrap_or_default(),
+            self.data_version,
+            self.outcome,
+            self.result_hash.as_deref().unwrap_or_default(),
+            self.prev_hash,
+        )
+    }
+
+    /// Computes the hash of the entry
+    pub fn compute_hash(&self) -> String {
+        hex::encode(Sha256::digest(self.canonical_form()))
+    }
+}
+
+/// Entry as sealed in the log file, with the format it was written in. The
+/// format is not part of the hash, so older entries still verify.
+#[derive(Serialize, Deserialize)]
+struct SealedEntry {
+    #[serde(default = "pre_head_format")]
+    format: u32, // Absent in entries written before heads were recorded
+    #[serde(flatten)]
+    entry: AuditEntry,
+}
+
+fn pre_head_format() -> u32 {
+    PRE_HEAD_FORMAT
+}
+
+/// Last entry of the chain, signed by the enclave
+#[derive(Clone, Serialize)]
+pub struct ChainHead {
+    pub length: u64,        // Number of entries in the log
+    pub hash: String,       // Hash of the last entry, `GENESIS_HASH` if the log is empty
+    pub signed_at: u64,     // Unix timestamp (seconds) of the signature
+    pub public_key: String, // Hex Ed25519 key bound to the enclave through its quote
+    pub signature: String,  // Hex Ed25519 signature over `head_message`
+}
+
+/// Returns the message the chain head signature is computed over
+pub fn head_message(length: u64, hash: &str, signed_at: u64) -> String {
+    format!("NTC-AUDIT-HEAD-V1\n{}\n{}\n{}", length, hash, signed_at)
+}
+
+/// Returns the hex SHA-256 hash of a byte string, such as a sealed pool
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// Returns the hex SHA-256 of a salt followed by a JSON value, as it is
+/// serialized in responses.
+///
+/// The salt is random and only handed to the caller the value is returned to,
+/// so anyone who holds the exported log but not the salt cannot test guesses of
+/// the result.
+pub fn commit_value(salt: &[u8], value: &Value) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(serde_json::to_vec(value).unwrap_or_default());
+    hex::encode(hasher.finalize())
+}
+
+/// Derives a key of the audit log from the MRENCLAVE sealing key, so it stays
+/// the same across restarts of the same enclave build
+fn derive_audit_key(label: &str) -> Result<[u8; 32]> {
+    let base_key = key_provider().base_key(SealingPolicy::MrEnclave)?;
+    let hkdf = Hkdf::<Sha256>::new(None, &base_key);
+    let mut key = [0u8; 32];
+    hkdf.expand(label.as_bytes(), &mut key)
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// Derives the key that signs the chain head
+pub fn audit_signing_key() -> Result<SigningKey> {
+    Ok(SigningKey::from_bytes(&derive_audit_key(
+        SIGNING_KEY_LABEL,
+    )?))
+}
+
+/// Length and hash of the chain as of the last acknowledged entry, sealed
+/// next to the log
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
+struct RecordedHead {
+    length: u64,
+    hash: String,
+}
+
+/// Entries held in memory, and the length of the file they were read from
+struct Chain {
+    entries: Vec<AuditEntry>,
+    file_len: u64,
+}
+
+/// Append-only, hash-chained log of every operation on a data pool.
+///
+/// Each entry is sealed on its own and appended to the log file as a 4-byte
+/// little-endian length followed by the sealed entry, so recording an operation
+/// never rewrites earlier entries. Entries are chained by hash, and the chain
+/// head is signed with a key bound to the enclave, so an auditor holding an
+/// export can check that no entry was altered, dropped or reordered. The host
+/// could still replace the whole file with an older copy; comparing the length
+/// of signed heads exported over time reveals this.
+///
+/// The length and hash of the chain are also sealed to a separate head file
+/// after every entry, so a log cut back to a record boundary is rejected when
+/// it is opened. A log without a head file is only accepted if every entry
+/// predates recorded heads; once a head has been recorded, all entries are
+/// rewritten in the current format. Only replacing both files with older copies
+/// together goes unnoticed until heads are compared.
+pub struct AuditLog {
+    path: PathBuf,
+    head_path: PathBuf,
+    signing_key: SigningKey,
+    chain: Mutex<Chain>,
+}
+
+impl AuditLog {
+    /// Opens the audit log at `path`, unsealing and verifying every entry, and
+    /// checking that no acknowledged entry is missing
+    pub fn open<P: AsRef<Path>>(path: P, signing_key: SigningKey) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let head_path = head_path(&path);
+        let mut chain = Chain {
+            entries: Vec::new(),
+            file_len: 0,
+        };
+        let mut pre_head_entries = 0;
+        let mut rewritten = None;
+
+        if path.is_file() {
+            let contents =
+                fs::read(&path).map_err(|e| anyhow!("Failed to read audit log: {}", e))?;
+            let (records, complete_len) = split_records(&contents);
+
+            let mut rewrite = complete_len < contents.len();
+            if rewrite {
+                // An entry whose write was interrupted was never acknowledged
+                warn!(
+                    bytes = contents.len() - complete_len,
+                    "Dropping incomplete entry at the end of the audit log"
+                );
+            }
+
+            let mut sealed_records = Vec::with_capacity(records.len());
+            for record in records {
+                // Bring entries sealed in an older format or under another policy up to date
+                let record = match migrate_sealed_data(record, SEALING_LABEL)? {
+                    Some(migrated) => {
+                        rewrite = true;
+                        migrated
+                    }
+                    None => record.to_vec(),
+                };
+                let sealed: SealedEntry =
+                    serde_json::from_value(unseal_data(&record, SEALING_LABEL)?)
+                        .map_err(|e| anyhow!("Failed to parse audit log entry: {}", e))?;
+                check_link(&chain.entries, &sealed.entry)?;
+
+                // Entries of the old format are brought up to date below, once
+                // the head has been recorded
+                let record = if sealed.format == PRE_HEAD_FORMAT {
+                    pre_head_entries += 1;
+                    rewrite = true;
+                    seal_entry(&sealed.entry)?
+                } else {
+                    record
+                };
+                chain.entries.push(sealed.entry);
+                sealed_records.push(record);
+            }
+
+            chain.file_len = contents.len() as u64;
+            if rewrite {
+                rewritten = Some(
+                    sealed_records
+                        .iter()
+                        .flat_map(|record| frame_record(record))
+                        .collect::<Vec<u8>>(),
+                );
+            }
+        }
+
+        let recorded = read_head(&head_path)?;
+        match &recorded {
+            Some(head) => check_head(&chain.entries, head)?,
+            None if pre_head_entries < chain.entries.len() => {
+                // A head has been recorded for these entries before, so it was removed
+                return Err(anyhow!(
+                    "Audit log has {} entries but no recorded head; it may have been truncated",
+                    chain.entries.len()
+                ));
+            }
+            None if !chain.entries.is_empty() => {
+                // Logs written before heads were recorded
+                warn!(
+                    entries = chain.entries.len(),
+                    "Audit log has no recorded head, recording one"
+                );
+            }
+            None => {}
+        }
+
+        let log = Self {
+            path,
+            head_path,
+            signing_key,
+            chain: Mutex::new(chain),
+        };
+        let mut chain = log.chain.lock().expect("lock not shared yet");
+        let current = current_head(&chain.entries);
+        if recorded.as_ref() != Some(&current) {
+            log.write_head(&current)?;
+        }
+
+        // Only rewritten after the head, so entries of the current format are
+        // never on disk without one
+        if let Some(contents) = rewritten {
+            write_atomic(&log.path, &contents)
+                .map_err(|e| anyhow!("Failed to write audit log: {}", e))?;
+            chain.file_len = contents.len() as u64;
+        }
+        drop(chain);
+        Ok(log)
+    }
+
+    /// Returns the public key that signs the chain head
+    pub fn public_key(&self) -> [u8; 32] {
+        self.signing_key.verifying_key().to_bytes()
+    }
+
+    /// Appends an entry for an operation, returning once it is on disk.
+    ///
+    /// Returns the hex salt the entry commits to the result under, if there is
+    /// a result, which is to be returned to the caller along with it.
+    pub fn record(&self, event: &AuditEvent) -> Result<Option<String>> {
+        let mut chain = self
+            .chain
+            .lock()
+            .map_err(|_| anyhow!("Audit log lock poisoned"))?;
+
+        let mut entry = AuditEntry {
+            index: chain.entries.len() as u64,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            operation: event.operation,
+            pool_id: event.pool_id.to_string(),
+            redeemer: event.redeemer.map(str::to_string),
+            code_hash: event.code_hash.map(|hash| hash.trim().to_ascii_lowercase()),
+            data_version: event.data_version.to_string(),
+            outcome: event.outcome.to_string(),
+            result_hash: None,
+            prev_hash: chain
+                .entries
+                .last()
+                .map_or_else(|| GENESIS_HASH.to_string(), |last| last.hash.clone()),
+            hash: String::new(),
+        };
+        let salt = event.result.map(|result| {
+            let mut salt = [0u8; 32];
+            rand::rngs::OsRng.fill_bytes(&mut salt);
+            entry.result_hash = Some(commit_value(&salt, result));
+            hex::encode(salt)
+        });
+        entry.hash = entry.compute_hash();
+
+        let record = frame_record(&seal_entry(&entry)?);
+        self.append(&record, chain.file_len)?;
+
+        chain.file_len += record.len() as u64;
+        chain.entries.push(entry);
+
+        // The entry is kept even if the head cannot be written, as it is on
+        // disk; a log longer than its head is accepted when it is opened
+        self.write_head(&current_head(&chain.entries))?;
+        Ok(salt)
+    }
+
+    /// Returns up to `limit` entries starting at index `from`, with the signed
+    /// head of the whole chain
+    pub fn export(&self, from: u64, limit: usize) -> Result<(Vec<AuditEntry>, ChainHead)> {
+        let chain = self
+            .chain
+            .lock()
+            .map_err(|_| anyhow!("Audit log lock poisoned"))?;
+
+        let entries = chain
+            .entries
+            .iter()
+            .skip(usize::try_from(from).unwrap_or(usize::MAX))
+            .take(limit.min(MAX_EXPORT_ENTRIES))
+            .cloned()
+            .collect();
+
+        let RecordedHead { length, hash } = current_head(&chain.entries);
+        let signed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let signature = self
+            .signing_key
+            .sign(head_message(length, &hash, signed_at).as_bytes());
+
+        let head = ChainHead {
+            length,
+            hash,
+            signed_at,
+            public_key: hex::encode(self.public_key()),
+            signature: hex::encode(signature.to_bytes()),
+        };
+        Ok((entries, head))
+    }
+
+    /// Seals the length and hash of the chain to the head file
+    fn write_head(&self, head: &RecordedHead) -> Result<()> {
+        let value = serde_json::to_value(head)
+            .map_err(|e| anyhow!("Failed to serialize audit log head: {}", e))?;
+        write_atomic(&self.head_path, &seal_data(&value, HEAD_SEALING_LABEL)?)
+            .map_err(|e| anyhow!("Failed to write audit log head: {}", e))
+    }
+
+    /// Appends a framed record to the log file and flushes it to disk
+    fn append(&self, record: &[u8], file_len: u64) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| anyhow!("Failed to open audit log: {}", e))?;
+
+        let written = file.write_all(record).and_then(|_| file.sync_data());
+        if let Err(e) = written {
+            // Cut off a partial record, so the next entry starts at a record boundary
+            let _ = file.set_len(file_len);
+            return Err(anyhow!("Failed to write audit log: {}", e));
+        }
+        Ok(())
+    }
+}
+
+/// Checks that an entry read back from disk follows on from the previous ones
+fn check_link(entries: &[AuditEntry], entry: &AuditEntry) -> Result<()> {
+    let expected_prev = entries
+        .last()
+        .map_or(GENESIS_HASH, |last| last.hash.as_str());
+    if entry.index != entries.len() as u64
+        || entry.prev_hash != expected_prev
+        || entry.hash != entry.compute_hash()
+    {
+        return Err(anyhow!("Audit log is broken at entry {}", entries.len()));
+    }
+    Ok(())
+}
+
+/// Seals an entry in the current format
+fn seal_entry(entry: &AuditEntry) -> Result<Vec<u8>> {
+    let value = serde_json::to_value(SealedEntry {
+        format: ENTRY_FORMAT,
+        entry: entry.clone(),
+    })
+    .map_err(|e| anyhow!("Failed to serialize audit log entry: {}", e))?;
+    seal_data(&value, SEALING_LABEL)
+}
+
+/// Returns the path of the head file recorded next to the log at `path`
+fn head_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".head");
+    path.with_file_name(name)
+}
+
+/// Reads the recorded head, if there is one
+fn read_head(head_path: &Path) -> Result<Option<RecordedHead>> {
+    if !head_path.is_file() {
+        return Ok(None);
+    }
+    let sealed =
+        fs::read(head_path).map_err(|e| anyhow!("Failed to read audit log head: {}", e))?;
+    let head = serde_json::from_value(unseal_data(&sealed, HEAD_SEALING_LABEL)?)
+        .map_err(|e| anyhow!("Failed to parse audit log head: {}", e))?;
+    Ok(Some(head))
+}
+
+/// Returns the length and hash of the chain
+fn current_head(entries: &[AuditEntry]) -> RecordedHead {
+    RecordedHead {
+        length: entries.len() as u64,
+        hash: entries
+            .last()
+            .map_or_else(|| GENESIS_HASH.to_string(), |last| last.hash.clone()),
+    }
+}
+
+/// Checks that the entries read back include every entry up to the recorded
+/// head. They may go past it if the head was not written after the last entry.
+fn check_head(entries: &[AuditEntry], head: &RecordedHead) -> Result<()> {
+    let hash_at_head = match usize::try_from(head.length) {
+        Ok(0) => Some(GENESIS_HASH),
+        Ok(length) => entries.get(length - 1).map(|entry| entry.hash.as_str()),
+        Err(_) => None,
+    };
+    match hash_at_head {
+        Some(hash) if hash == head.hash => Ok(()),
+        Some(_) => Err(anyhow!(
+            "Audit log does not match its recorded head at entry {}",
+            head.length
+        )),
+        None => Err(anyhow!(
+            "Audit log has {} entries but its recorded head covers {}; it has been truncated",
+            entries.len(),
+            head.length
+        )),
+    }
+}
+
+/// Prefixes a sealed entry with its length
+fn frame_record(sealed_entry: &[u8]) -> Vec<u8> {
+    let mut record = Vec::with_capacity(4 + sealed_entry.len());
+    record.extend_from_slice(&(sealed_entry.len() as u32).to_le_bytes());
+    record.extend_from_slice(sealed_entry);
+    record
+}
+
+/// Splits the log file into sealed entries, also returning the length of the
+/// complete records, which is less than the file length if the last one is torn
+fn split_records(contents: &[u8]) -> (Vec<&[u8]>, usize) {
+    let mut records = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = contents.get(offset..offset + 4) {
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        match contents.get(offset + 4..offset + 4 + len) {
+            Some(record) => records.push(record),
+            None => break,
+        }
+        offset += 4 + len;
+    }
+    (records, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::use_dev_keys;
+    use crate::testing::ScratchDir;
+    use serde_json::json;
+
+    fn open_log(path: &Path) -> Result<AuditLog> {
+        use_dev_keys();
+        AuditLog::open(path, audit_signing_key()?)
+    }
+
+    fn record(log: &AuditLog, result: Option<&Value>) -> Option<String> {
+        log.record(&AuditEvent {
+            operation: AuditOperation::ExecuteWasm,
+            pool_id: "pool",
+            redeemer: Some("wallet"),
+            code_hash: Some("ABCD"),
+            data_version: "version",
+            outcome: "succeeded",
+            result,
+        })
+        .unwrap()
+    }
+
+    /// Rewrites the log as if every entry had been written before heads were
+    /// recorded, and removes the head
+    fn make_pre_head(path: &Path) {
+        let contents = fs::read(path).unwrap();
+        let (records, _) = split_records(&contents);
+        let mut pre_head = Vec::new();
+        for record in records {
+            let mut value = unseal_data(record, SEALING_LABEL).unwrap();
+            value.as_object_mut().unwrap().remove("format");
+            pre_head.extend(frame_record(&seal_data(&value, SEALING_LABEL).unwrap()));
+        }
+        fs::write(path, pre_head).unwrap();
+        fs::remove_file(head_path(path)).unwrap();
+    }
+
+    #[test]
+    fn reopened_log_keeps_its_entries() {
+        let dir = ScratchDir::new();
+        let path = dir.0.join("audit_log");
+        let log = open_log(&path).unwrap();
+        record(&log, Some(&json!(1)));
+        record(&log, None);
+        drop(log);
+
+        let log = open_log(&path).unwrap();
+        let (entries, head) = log.export(0, 10).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(head.length, 2);
+        assert_eq!(head.hash, entries[1].hash);
+        assert_eq!(entries[0].code_hash.as_deref(), Some("abcd"));
+        assert_eq!(entries[1].prev_hash, entries[0].hash);
+    }
+
+    #[test]
+    fn result_is_committed_under_the_returned_salt() {
+        let dir = ScratchDir::new();
+        let log = open_log(&dir.0.join("audit_log")).unwrap();
+        let salt = record(&log, Some(&json!(true))).unwrap();
+        let other_salt = record(&log, Some(&json!(true))).unwrap();
+        assert_eq!(record(&log, None), None);
+
+        let (entries, _) = log.export(0, 10).unwrap();
+        let committed = entries[0].result_hash.as_deref().unwrap();
+        assert_eq!(
+            committed,
+            commit_value(&hex::decode(&salt).unwrap(), &json!(true))
+        );
+        // A guess hashed without the salt does not match
+        assert_ne!(committed, hash_bytes(b"true"));
+        assert_ne!(committed, commit_value(&[0u8; 32], &json!(true)));
+        // Nor do two commitments to the same result
+        assert_ne!(salt, other_salt);
+        assert_ne!(entries[1].result_hash.as_deref(), Some(committed));
+        assert_eq!(entries[2].result_hash, None);
+    }
+
+    #[test]
+    fn rejects_log_cut_back_to_a_record_boundary() {
+        let dir = ScratchDir::new();
+        let path = dir.0.join("audit_log");
+        let log = open_log(&path).unwrap();
+        record(&log, None);
+        let first_len = fs::metadata(&path).unwrap().len();
+        record(&log, None);
+        drop(log);
+
+        let contents = fs::read(&path).unwrap();
+        fs::write(&path, &contents[..first_len as usize]).unwrap();
+        let error = open_log(&path).err().unwrap();
+        assert!(error.to_string().contains("truncated"));
+
+        // Emptying the log is caught the same way
+        fs::write(&path, b"").unwrap();
+        assert!(open_log(&path).is_err());
+    }
+
+    #[test]
+    fn rejects_log_cut_back_with_its_head_removed() {
+        let dir = ScratchDir::new();
+        let path = dir.0.join("audit_log");
+        let log = open_log(&path).unwrap();
+        record(&log, None);
+        let first_len = fs::metadata(&path).unwrap().len();
+        record(&log, None);
+        drop(log);
+
+        let contents = fs::read(&path).unwrap();
+        fs::write(&path, &contents[..first_len as usize]).unwrap();
+        fs::remove_file(head_path(&path)).unwrap();
+        let error = open_log(&path).err().unwrap();
+        assert!(error.to_string().contains("no recorded head"));
+    }
+
+    #[test]
+    fn records_a_head_for_a_pre_head_log() {
+        let dir = ScratchDir::new();
+        let path = dir.0.join("audit_log");
+        let log = open_log(&path).unwrap();
+        record(&log, Some(&json!(1)));
+        record(&log, None);
+        drop(log);
+        make_pre_head(&path);
+
+        // Entries written before heads were recorded are accepted without one
+        let log = open_log(&path).unwrap();
+        let (entries, head) = log.export(0, 10).unwrap();
+        assert_eq!(head.length, 2);
+        assert_eq!(head.hash, entries[1].hash);
+        drop(log);
+
+        // Opening it recorded a head and brought the entries up to date, so
+        // removing the head again is caught
+        fs::remove_file(head_path(&path)).unwrap();
+        assert!(open_log(&path).is_err());
+    }
+
+    #[test]
+    fn accepts_log_ahead_of_its_head() {
+        let dir = ScratchDir::new();
+        let path = dir.0.join("audit_log");
+        let log = open_log(&path).unwrap();
+        record(&log, None);
+        let head = fs::read(head_path(&path)).unwrap();
+        record(&log, None);
+        drop(log);
+
+        // The head was not written after the last entry
+        fs::write(head_path(&path), head).unwrap();
+        let log = open_log(&path).unwrap();
+        assert_eq!(log.export(0, 10).unwrap().1.length, 2);
+        drop(log);
+
+        // Opening it recorded the new head
+        let contents = fs::read(&path).unwrap();
+        let (records, _) = split_records(&contents);
+        fs::write(&path, frame_record(records[0])).unwrap();
+        assert!(open_log(&path).is_err());
+    }
+
+    #[test]
+    fn drops_torn_entry() {
+        let dir = ScratchDir::new();
+        let path = dir.0.join("audit_log");
+        let log = open_log(&path).unwrap();
+        record(&log, None);
+        drop(log);
+
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[200, 0, 0, 0, 1, 2, 3]).unwrap();
+        drop(file);
+
+        let log = open_log(&path).unwrap();
+        record(&log, None);
+        assert_eq!(log.export(0, 10).unwrap().0.len(), 2);
+        drop(log);
+        assert_eq!(open_log(&path).unwrap().export(0, 10).unwrap().1.length, 2);
+    }
+}
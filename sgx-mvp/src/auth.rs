@@ -0,0 +1,458 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/auth.rs
+
+use crate::errors::ApiError;
+use actix_web::dev::Payload;
+use actix_web::error::PayloadError;
+use actix_web::web::{self, Bytes};
+use actix_web::{FromRequest, HttpRequest};
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Request headers carrying the wallet signature
+pub const WALLET_HEADER: &str = "x-ntc-wallet"; // Base58-encoded Solana wallet
+pub const SIGNATURE_HEADER: &str = "x-ntc-signature"; // Base58-encoded Ed25519 signature
+pub const TIMESTAMP_HEADER: &str = "x-ntc-timestamp"; // Unix timestamp (seconds)
+pub const NONCE_HEADER: &str = "x-ntc-nonce"; // Random value, never reused by a wallet
+
+/// Verifies requests signed with the caller's Solana wallet.
+///
+/// The wallet signs the hex-encoded SHA-256 digest of the canonical request
+/// (see `request_digest`), which binds the signature to the method, path,
+/// query, body, timestamp and nonce. Requests outside the clock skew window
+/// are rejected, and nonces are remembered for as long as their timestamp is
+/// accepted, so a captured request cannot be replayed.
+pub struct RequestAuthenticator {
+    max_clock_skew_secs: u64, // How far the request timestamp may be from the enclave clock
+    seen_nonces: Mutex<HashMap<String, u64>>, // "<wallet>:<nonce>" to the time it can be forgotten
+}
+
+impl RequestAuthenticator {
+    /// Creates an authenticator accepting timestamps up to `max_clock_skew_secs`
+    /// seconds away from the enclave clock
+    pub fn new(max_clock_skew_secs: u64) -> Self {
+        Self {
+            max_clock_skew_secs,
+            seen_nonces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Verifies the wallet signature of a request and returns the base58-encoded
+    /// wallet that signed it
+    pub fn authenticate(&self, req: &HttpRequest, body: &[u8]) -> Result<String, ApiError> {
+        self.verify(req, body).map_err(|e| {
+            warn!(error = %e, "Request authentication failed");
+            ApiError::Unauthorized
+        })
+    }
+
+    fn verify(&self, req: &HttpRequest, body: &[u8]) -> Result<String, String> {
+        let wallet = header(req, WALLET_HEADER)?;
+        let signature = header(req, SIGNATURE_HEADER)?;
+        let timestamp = header(req, TIMESTAMP_HEADER)?;
+        let nonce = header(req, NONCE_HEADER)?;
+
+        let timestamp: u64 = timestamp
+            .parse()
+            .map_err(|_| format!("Invalid timestamp '{}'", timestamp))?;
+        let now = now();
+        if timestamp.abs_diff(now) > self.max_clock_skew_secs {
+            return Err(format!(
+                "Timestamp {} is outside the accepted window",
+                timestamp
+            ));
+        }
+
+        let nonce_valid = (16..=128).contains(&nonce.len())
+            && nonce
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if !nonce_valid {
+            return Err("Nonce must be 16 to 128 characters of [A-Za-z0-9_-]".to_string());
+        }
+
+        let key = decode_base58::<32>(wallet).ok_or("Invalid wallet public key")?;
+        let key = VerifyingKey::from_bytes(&key).map_err(|_| "Invalid wallet public key")?;
+        let signature = decode_base58::<64>(signature).ok_or("Invalid signature encoding")?;
+        let signature = Signature::from_bytes(&signature);
+
+        let digest = request_digest(
+            req.method().as_str(),
+            req.path(),
+            req.query_string(),
+            wallet,
+            timestamp,
+            nonce,
+            body,
+        );
+        key.verify_strict(digest.as_bytes(), &signature)
+            .map_err(|_| "Signature does not match the request")?;
+
+        // Only remember nonces of genuine requests, so nobody can use up another wallet's nonces
+        let mut seen_nonces = self
+            .seen_nonces
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        seen_nonces.retain(|_, forget_at| *forget_at > now);
+        let forget_at = timestamp + self.max_clock_skew_secs + 1;
+        if seen_nonces
+            .insert(format!("{}:{}", wallet, nonce), forget_at)
+            .is_some()
+        {
+            return Err(format!("Nonce '{}' has already been used", nonce));
+        }
+
+        Ok(wallet.to_string())
+    }
+}
+
+/// JSON request body of a signed request, together with the wallet that signed it.
+///
+/// The body is checked against the signature byte for byte before it is
+/// parsed, so it must be sent exactly as it was signed.
+pub struct SignedJson<T> {
+    pub wallet: String, // Base58-encoded wallet that signed the request
+    pub body: T,
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for SignedJson<T> {
+    type Error = ApiError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, ApiError>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let bytes = Bytes::from_request(&req, payload);
+
+        Box::pin(async move {
+            let bytes = bytes
+                .await
+                .map_err(|e| match e.as_error::<PayloadError>() {
+                    Some(PayloadError::Overflow) => ApiError::RequestTooLarge,
+                    _ => ApiError::InvalidRequest(e.to_string()),
+                })?;
+
+            let authenticator =
+                req.app_data::<web::Data<RequestAuthenticator>>()
+                    .ok_or(ApiError::Internal(
+                        "Request authentication is not configured",
+                    ))?;
+            let wallet = authenticator.authenticate(&req, &bytes)?;
+
+            let body = serde_json::from_slice(&bytes)
+                .map_err(|e| ApiError::InvalidRequest(format!("Json deserialize error: {}", e)))?;
+            Ok(Self { wallet, body })
+        })
+    }
+}
+
+/// Returns the hex-encoded SHA-256 digest of the canonical form of a request:
+///
+/// ```text
+/// NTC-REQUEST-V1
+/// <METHOD>
+/// <path>
+/// <query string, without '?'>
+/// <wallet>
+/// <timestamp>
+/// <nonce>
+/// <hex-encoded SHA-256 of the body>
+/// ```
+pub fn request_digest(
+    method: &str,
+    path: &str,
+    query: &str,
+    wallet: &str,
+    timestamp: u64,
+    nonce: &str,
+    body: &[u8],
+) -> String {
+    let canonical = format!(
+        "NTC-REQUEST-V1\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+        method.to_ascii_uppercase(),
+        path,
+        query,
+        wallet,
+        timestamp,
+        nonce,
+        hex::encode(Sha256::digest(body)),
+    );
+    hex::encode(Sha256::digest(canonical.as_bytes()))
+}
+
+fn header<'a>(req: &'a HttpRequest, name: &str) -> Result<&'a str, String> {
+    req.headers()
+        .get(name)
+        .ok_or_else(|| format!("Missing {} header", name))?
+        .to_str()
+        .map_err(|_| format!("Invalid {} header", name))
+}
+
+fn decode_base58<const N: usize>(value: &str) -> Option<[u8; N]> {
+    let bytes = bs58::decode(value).into_vec().ok()?;
+    bytes.try_into().ok()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const SKEW: u64 = 300;
+    const NONCE: &str = "0123456789abcdef";
+    const BODY: &[u8] = br#"{"pool_id":"x"}"#;
+
+    type Headers = Vec<(&'static str, String)>;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    fn other_key() -> SigningKey {
+        SigningKey::from_bytes(&[8; 32])
+    }
+
+    fn wallet(key: &SigningKey) -> String {
+        bs58::encode(key.verifying_key().as_bytes()).into_string()
+    }
+
+    /// Headers signing a request to `uri` by `key` over `body`, at `timestamp`
+    fn sign(
+        key: &SigningKey,
+        method: &str,
+        uri: &str,
+        timestamp: u64,
+        nonce: &str,
+        body: &[u8],
+    ) -> Headers {
+        let (path, query) = uri.split_once('?').unwrap_or((uri, ""));
+        let wallet = wallet(key);
+        let digest = request_digest(method, path, query, &wallet, timestamp, nonce, body);
+        let signature = bs58::encode(key.sign(digest.as_bytes()).to_bytes()).into_string();
+        vec![
+            (WALLET_HEADER, wallet),
+            (SIGNATURE_HEADER, signature),
+            (TIMESTAMP_HEADER, timestamp.to_string()),
+            (NONCE_HEADER, nonce.to_string()),
+        ]
+    }
+
+    fn request(method: &str, uri: &str, headers: Headers) -> HttpRequest {
+        headers
+            .into_iter()
+            .fold(
+                TestRequest::default()
+                    .method(method.parse().unwrap())
+                    .uri(uri),
+                |request, header| request.insert_header(header),
+            )
+            .to_http_request()
+    }
+
+    /// Append request signed by the test wallet
+    fn post(timestamp: u64, nonce: &str) -> HttpRequest {
+        let headers = sign(
+            &signing_key(),
+            "POST",
+            "/append_data",
+            timestamp,
+            nonce,
+            BODY,
+        );
+        request("POST", "/append_data", headers)
+    }
+
+    #[test]
+    fn digest_matches_the_canonical_request() {
+        // SHA-256 of "NTC-REQUEST-V1\nPOST\n/append_data\nformat=csv\n<wallet>\n
+        // 1700000000\n0123456789abcdef\n<SHA-256 of the body>", computed independently
+        let digest = request_digest(
+            "post",
+            "/append_data",
+            "format=csv",
+            "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM",
+            1_700_000_000,
+            NONCE,
+            BODY,
+        );
+        assert_eq!(
+            digest,
+            "a907878c20029ad091e32bfd3c68c05f61d4fcb83701ac0d5a629b804a0678ad"
+        );
+    }
+
+    #[test]
+    fn digest_covers_method_path_query_and_body() {
+        let digest = |method, path, query, body: &[u8]| {
+            request_digest(method, path, query, "wallet", 1, NONCE, body)
+        };
+        let original = digest("POST", "/append_data", "", BODY);
+        assert_ne!(original, digest("PUT", "/append_data", "", BODY));
+        assert_ne!(original, digest("POST", "/delete_data_pool", "", BODY));
+        assert_ne!(original, digest("POST", "/append_data", "a=1", BODY));
+        assert_ne!(original, digest("POST", "/append_data", "", b"{}"));
+    }
+
+    #[test]
+    fn accepts_a_signed_request() {
+        let authenticator = RequestAuthenticator::new(SKEW);
+        let signer = authenticator
+            .authenticate(&post(now(), NONCE), BODY)
+            .unwrap();
+        assert_eq!(signer, wallet(&signing_key()));
+
+        let headers = sign(
+            &signing_key(),
+            "GET",
+            "/job_status?job_id=1",
+            now(),
+            "get-nonce-0123456789",
+            b"",
+        );
+        let request = request("GET", "/job_status?job_id=1", headers);
+        assert!(authenticator.authenticate(&request, b"").is_ok());
+    }
+
+    #[test]
+    fn rejects_a_request_other_than_the_signed_one() {
+        let authenticator = RequestAuthenticator::new(SKEW);
+        assert!(authenticator
+            .authenticate(&post(now(), NONCE), b"{}")
+            .is_err());
+
+        let headers = || sign(&signing_key(), "POST", "/append_data", now(), NONCE, BODY);
+        let other_method = request("PUT", "/append_data", headers());
+        let other_path = request("POST", "/delete_data_pool", headers());
+        let other_query = request("POST", "/append_data?format=csv", headers());
+        for request in [other_method, other_path, other_query] {
+            assert!(authenticator.authenticate(&request, BODY).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_a_signature_by_another_wallet() {
+        let authenticator = RequestAuthenticator::new(SKEW);
+        let mut headers = sign(&other_key(), "POST", "/append_data", now(), NONCE, BODY);
+        headers[0].1 = wallet(&signing_key());
+        let request = request("POST", "/append_data", headers);
+        assert!(authenticator.authenticate(&request, BODY).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_headers() {
+        let authenticator = RequestAuthenticator::new(SKEW);
+        for missing in [
+            WALLET_HEADER,
+            SIGNATURE_HEADER,
+            TIMESTAMP_HEADER,
+            NONCE_HEADER,
+        ] {
+            let mut headers = sign(&signing_key(), "POST", "/append_data", now(), NONCE, BODY);
+            headers.retain(|(name, _)| *name != missing);
+            let request = request("POST", "/append_data", headers);
+            assert!(
+                authenticator.authenticate(&request, BODY).is_err(),
+                "{}",
+                missing
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_timestamps_outside_the_clock_skew() {
+        let authenticator = RequestAuthenticator::new(SKEW);
+        let now = now();
+        assert!(authenticator
+            .authenticate(&post(now - SKEW - 1, "old-nonce-0123456789"), BODY)
+            .is_err());
+        assert!(authenticator
+            .authenticate(&post(now + SKEW + 10, "new-nonce-0123456789"), BODY)
+            .is_err());
+        assert!(authenticator
+            .authenticate(&post(now - SKEW + 5, "edge-nonce-0123456789"), BODY)
+            .is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_nonces() {
+        let authenticator = RequestAuthenticator::new(SKEW);
+        let long = "a".repeat(129);
+        for nonce in ["short", "0123456789abcdef!", long.as_str()] {
+            assert!(authenticator
+                .authenticate(&post(now(), nonce), BODY)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_a_replayed_nonce() {
+        let authenticator = RequestAuthenticator::new(SKEW);
+        let timestamp = now();
+        assert!(authenticator
+            .authenticate(&post(timestamp, NONCE), BODY)
+            .is_ok());
+        assert!(authenticator
+            .authenticate(&post(timestamp, NONCE), BODY)
+            .is_err());
+
+        // Another wallet may use the same nonce
+        let headers = sign(&other_key(), "POST", "/append_data", timestamp, NONCE, BODY);
+        let request = request("POST", "/append_data", headers);
+        assert!(authenticator.authenticate(&request, BODY).is_ok());
+    }
+
+    #[test]
+    fn forgets_nonces_once_their_timestamp_expires() {
+        let authenticator = RequestAuthenticator::new(SKEW);
+        let now = now();
+        authenticator
+            .seen_nonces
+            .lock()
+            .unwrap()
+            .insert("wallet:expired-nonce-0123".to_string(), now - 1);
+
+        authenticator.authenticate(&post(now, NONCE), BODY).unwrap();
+        let seen_nonces = authenticator.seen_nonces.lock().unwrap();
+        assert_eq!(seen_nonces.len(), 1);
+        let key = format!("{}:{}", wallet(&signing_key()), NONCE);
+        assert_eq!(seen_nonces[&key], now + SKEW + 1);
+    }
+
+    #[test]
+    fn unsigned_requests_leave_no_nonce_behind() {
+        let authenticator = RequestAuthenticator::new(SKEW);
+        let request = post(now(), NONCE);
+        assert!(authenticator.authenticate(&request, b"{}").is_err());
+        assert!(authenticator.seen_nonces.lock().unwrap().is_empty());
+        assert!(authenticator.authenticate(&request, BODY).is_ok());
+    }
+}
@@ -0,0 +1,879 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/config.rs
+
+use crate::keys::KeyProviderKind;
+use crate::logging::LogFormat;
+use crate::migration::parse_mrenclave;
+use crate::pools::PoolRegistry;
+use crate::sealing::SealingPolicy;
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use drt_verify::{DEFAULT_RPC_URL, DRT_MANAGER_PROGRAM_ID};
+use sealed_store::DEFAULT_IPFS_API_URL;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use tracing_subscriber::EnvFilter;
+
+/// Names of the endpoints that can be disabled, as served under `/<name>`
+pub const ENDPOINTS: &[&str] = &[
+    "health",
+    "attestation",
+    "metrics",
+    "audit_log",
+    "execute_python",
+    "execute_wasm",
+    "create_data_pool",
+    "view_data",
+    "append_data",
+    "list_pools",
+    "redemption_status",
+    "delete_data_pool",
+    "migrate_data_pools",
+    "migration_key",
+    "export_migration_bundle",
+    "import_migration_bundle",
+    "submit_job",
+    "job_status",
+    "job_result",
+    "cancel_job",
+];
+
+/// Server configuration, loaded from an optional TOML file and then overridden
+/// by environment variables and command-line options
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub storage: StorageConfig,
+    pub sealing: SealingConfig,
+    pub solana: SolanaConfig,
+    pub limits: LimitsConfig,
+    pub executor: ExecutorConfig,
+    pub auth: AuthConfig,
+    pub endpoints: EndpointsConfig,
+    pub logging: LoggingConfig,
+}
+
+/// HTTPS listener settings
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub listen_address: String, // Address and port to listen on
+    pub workers: usize,         // Number of HTTP worker threads
+    pub tls_cert_path: String,  // RA-TLS certificate written by gramine-ratls
+    pub tls_key_path: String,   // RA-TLS private key written by gramine-ratls
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_address: "127.0.0.1:8080".to_string(),
+            workers: 2,
+            tls_cert_path: "/tmp/tlscert.pem".to_string(),
+            tls_key_path: "/tmp/tlskey.pem".to_string(),
+        }
+    }
+}
+
+/// Backends that sealed data pools can be stored in
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+    Local,
+    Ipfs,
+    S3,
+}
+
+impl StoreBackend {
+    /// Parses a backend name (`local`, `ipfs` or `s3`)
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "local" => Ok(StoreBackend::Local),
+            "ipfs" => Ok(StoreBackend::Ipfs),
+            "s3" => Ok(StoreBackend::S3),
+            other => Err(anyhow!("Unknown sealed store: {}", other)),
+        }
+    }
+}
+
+/// Where sealed data is kept
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub data_dir: PathBuf, // Encrypted mount holding the ledger and, for the local store, the pools
+    pub backend: StoreBackend, // Store for sealed data pools
+    pub ipfs_api_url: String, // kubo RPC API used by the IPFS store
+    pub s3: S3Config,      // Bucket used by the S3 store
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("/data"),
+            backend: StoreBackend::Local,
+            ipfs_api_url: DEFAULT_IPFS_API_URL.to_string(),
+            s3: S3Config::default(),
+        }
+    }
+}
+
+/// S3-compatible bucket settings
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct S3Config {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub prefix: String,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://127.0.0.1:9000".to_string(),
+            bucket: "ntc-pools".to_string(),
+            region: "us-east-1".to_string(),
+            prefix: "pools/".to_string(),
+            access_key_id: None,
+            secret_access_key: None,
+        }
+    }
+}
+
+/// Sealing key settings
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SealingConfig {
+    pub key_provider: KeyProviderKind, // Source of sealing keys
+    pub policy: SealingPolicy,         // Enclave identity new sealed data is bound to
+    pub approved_predecessor_mrenclaves: Vec<String>, // Hex MRENCLAVE values allowed to hand over pools
+}
+
+impl Default for SealingConfig {
+    fn default() -> Self {
+        Self {
+            key_provider: KeyProviderKind::Gramine,
+            policy: SealingPolicy::MrEnclave,
+            approved_predecessor_mrenclaves: Vec::new(),
+        }
+    }
+}
+
+/// Solana settings used to verify DRT redemptions
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SolanaConfig {
+    pub rpc_url: String,
+    pub drt_manager_program_id: String,
+    pub max_redemption_age_secs: u64, // How long after it is made on-chain a redemption can be used
+}
+
+impl Default for SolanaConfig {
+    fn default() -> Self {
+        Self {
+            rpc_url: DEFAULT_RPC_URL.to_string(),
+            drt_manager_program_id: DRT_MANAGER_PROGRAM_ID.to_string(),
+            max_redemption_age_secs: 3600,
+        }
+    }
+}
+
+/// Size limits, in bytes
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub max_request_bytes: usize, // Largest accepted JSON request body
+    pub max_pool_bytes: usize,    // Largest data pool, measured as serialized JSON
+    pub max_download_bytes: u64,  // Largest script or WASM binary that will be downloaded
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_request_bytes: 2 * 1024 * 1024,
+            max_pool_bytes: 64 * 1024 * 1024,
+            max_download_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Settings for Python and WASM executions
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecutorConfig {
+    pub threads: usize,         // Worker threads for executions, downloads and sealing
+    pub max_queued_jobs: usize, // Jobs that may wait for a worker before requests are rejected
+    pub job_retention_secs: u64, // How long results of submitted jobs are kept
+    pub download_dir: PathBuf,  // Directory downloaded code is written to
+    pub wasm_fuel: Option<u64>, // Fuel budget per WASM execution, unlimited if unset
+    pub wasm_max_memory_pages: Option<u32>, // WASM memory limit in 64 KiB pages, unlimited if unset
+}
+
+impl Default for ExecutorConfig {
+    fn default() -> Self {
+        Self {
+            threads: 4,
+            max_queued_jobs: 64,
+            job_retention_secs: 3600,
+            download_dir: PathBuf::from("/tmp"),
+            wasm_fuel: None,
+            wasm_max_memory_pages: None,
+        }
+    }
+}
+
+/// Settings for wallet-signed requests
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub max_clock_skew_secs: u64, // How far a request timestamp may be from the enclave clock
+    pub operator_wallets: Vec<String>, // Base58 wallets allowed to export and import migration bundles
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            max_clock_skew_secs: 300,
+            operator_wallets: Vec::new(),
+        }
+    }
+}
+
+/// Endpoints to switch off
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct EndpointsConfig {
+    pub disabled: Vec<String>, // Names from `ENDPOINTS`
+}
+
+impl EndpointsConfig {
+    /// Returns whether the endpoint with the given name is served
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.iter().any(|disabled| disabled == name)
+    }
+}
+
+/// Log output settings
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub level: String,     // Level filter, e.g. `info` or `sgx_mvp=debug,warn`
+    pub format: LogFormat, // Text lines or one JSON object per line
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Text,
+        }
+    }
+}
+
+/// Command-line options. Each one can also be set through the environment
+/// variable shown in `--help`, and overrides the configuration file.
+#[derive(Parser)]
+#[command(name = "sgx-mvp", about = "Nautilus Trusted Compute enclave server")]
+struct Cli {
+    /// TOML configuration file
+    #[arg(long, env = "NTC_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Address and port to listen on
+    #[arg(long, env = "LISTEN_ADDRESS")]
+    listen_address: Option<String>,
+
+    /// Number of HTTP worker threads
+    #[arg(long, env = "WORKERS")]
+    workers: Option<usize>,
+
+    /// RA-TLS certificate written by gramine-ratls
+    #[arg(long, env = "TLS_CERT_PATH")]
+    tls_cert_path: Option<String>,
+
+    /// RA-TLS private key written by gramine-ratls
+    #[arg(long, env = "TLS_KEY_PATH")]
+    tls_key_path: Option<String>,
+
+    /// Directory holding the redemption ledger and locally stored pools
+    #[arg(long, env = "DATA_DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Store for sealed data pools: local, ipfs or s3
+    #[arg(long, env = "SEALED_STORE", value_parser = StoreBackend::from_name)]
+    sealed_store: Option<StoreBackend>,
+
+    /// kubo RPC API used by the IPFS store
+    #[arg(long, env = "IPFS_API_URL")]
+    ipfs_api_url: Option<String>,
+
+    /// Endpoint of the S3-compatible service
+    #[arg(long, env = "S3_ENDPOINT")]
+    s3_endpoint: Option<String>,
+
+    /// Bucket used by the S3 store
+    #[arg(long, env = "S3_BUCKET")]
+    s3_bucket: Option<String>,
+
+    /// Region used to sign S3 requests
+    #[arg(long, env = "S3_REGION")]
+    s3_region: Option<String>,
+
+    /// Key prefix of sealed pools in the bucket
+    #[arg(long, env = "S3_PREFIX")]
+    s3_prefix: Option<String>,
+
+    /// S3 access key ID
+    #[arg(long, env = "S3_ACCESS_KEY_ID", hide_env_values = true)]
+    s3_access_key_id: Option<String>,
+
+    /// S3 secret access key
+    #[arg(long, env = "S3_SECRET_ACCESS_KEY", hide_env_values = true)]
+    s3_secret_access_key: Option<String>,
+
+    /// Source of sealing keys: gramine or dev
+    #[arg(long, env = "KEY_PROVIDER", value_parser = KeyProviderKind::from_name)]
+    key_provider: Option<KeyProviderKind>,
+
+    /// Enclave identity new sealed data is bound to: mrenclave or mrsigner
+    #[arg(long, env = "SEALING_POLICY", value_parser = SealingPolicy::from_name)]
+    sealing_policy: Option<SealingPolicy>,
+
+    /// Comma-separated hex MRENCLAVE values allowed to hand over their pools
+    #[arg(long, env = "APPROVED_PREDECESSOR_MRENCLAVES", value_delimiter = ',')]
+    approved_predecessor_mrenclaves: Option<Vec<String>>,
+
+    /// Solana JSON-RPC endpoint used to verify DRT redemptions
+    #[arg(long, env = "SOLANA_RPC_URL")]
+    solana_rpc_url: Option<String>,
+
+    /// Program ID of the drt-manager program
+    #[arg(long, env = "DRT_MANAGER_PROGRAM_ID")]
+    drt_manager_program_id: Option<String>,
+
+    /// Seconds after it is made on-chain that a DRT redemption can be used
+    #[arg(long, env = "MAX_REDEMPTION_AGE_SECS")]
+    max_redemption_age_secs: Option<u64>,
+
+    /// Largest accepted JSON request body, in bytes
+    #[arg(long, env = "MAX_REQUEST_BYTES")]
+    max_request_bytes: Option<usize>,
+
+    /// Largest data pool, in bytes of serialized JSON
+    #[arg(long, env = "MAX_POOL_BYTES")]
+    max_pool_bytes: Option<usize>,
+
+    /// Largest script or WASM binary that will be downloaded, in bytes
+    #[arg(long, env = "MAX_DOWNLOAD_BYTES")]
+    max_download_bytes: Option<u64>,
+
+    /// Worker threads for executions, downloads and sealing
+    #[arg(long, env = "EXECUTOR_THREADS")]
+    executor_threads: Option<usize>,
+
+    /// Jobs that may wait for a worker thread before requests are rejected
+    #[arg(long, env = "MAX_QUEUED_JOBS")]
+    max_queued_jobs: Option<usize>,
+
+    /// Seconds the results of submitted jobs are kept
+    #[arg(long, env = "JOB_RETENTION_SECS")]
+    job_retention_secs: Option<u64>,
+
+    /// Directory downloaded code is written to
+    #[arg(long, env = "DOWNLOAD_DIR")]
+    download_dir: Option<PathBuf>,
+
+    /// Fuel budget per WASM execution
+    #[arg(long, env = "WASM_FUEL")]
+    wasm_fuel: Option<u64>,
+
+    /// WASM memory limit in 64 KiB pages
+    #[arg(long, env = "WASM_MAX_MEMORY_PAGES")]
+    wasm_max_memory_pages: Option<u32>,
+
+    /// Seconds a signed request's timestamp may be from the enclave clock
+    #[arg(long, env = "AUTH_MAX_CLOCK_SKEW_SECS")]
+    auth_max_clock_skew_secs: Option<u64>,
+
+    /// Comma-separated base58 wallets allowed to export and import migration bundles
+    #[arg(long, env = "OPERATOR_WALLETS", value_delimiter = ',')]
+    operator_wallets: Option<Vec<String>>,
+
+    /// Comma-separated names of endpoints to disable
+    #[arg(long, env = "DISABLED_ENDPOINTS", value_delimiter = ',')]
+    disabled_endpoints: Option<Vec<String>>,
+
+    /// Log level filter, e.g. info or sgx_mvp=debug,warn
+    #[arg(long, env = "LOG_LEVEL")]
+    log_level: Option<String>,
+
+    /// Log format: text or json
+    #[arg(long, env = "LOG_FORMAT", value_parser = LogFormat::from_name)]
+    log_format: Option<LogFormat>,
+}
+
+impl Config {
+    /// Loads the configuration file named on the command line, applies the
+    /// environment and command-line overrides and validates the result
+    pub fn load() -> Result<Self> {
+        let cli = Cli::parse();
+        let mut config = match &cli.config {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply(cli);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads a configuration file, using defaults for anything it does not set
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read config file {}: {}", path.display(), e))?;
+        toml::from_str(&contents)
+            .map_err(|e| anyhow!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    /// Applies command-line and environment overrides
+    fn apply(&mut self, cli: Cli) {
+        fn set<T>(target: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *target = value;
+            }
+        }
+
+        set(&mut self.server.listen_address, cli.listen_address);
+        set(&mut self.server.workers, cli.workers);
+        set(&mut self.server.tls_cert_path, cli.tls_cert_path);
+        set(&mut self.server.tls_key_path, cli.tls_key_path);
+        set(&mut self.storage.data_dir, cli.data_dir);
+        set(&mut self.storage.backend, cli.sealed_store);
+        set(&mut self.storage.ipfs_api_url, cli.ipfs_api_url);
+        set(&mut self.storage.s3.endpoint, cli.s3_endpoint);
+        set(&mut self.storage.s3.bucket, cli.s3_bucket);
+        set(&mut self.storage.s3.region, cli.s3_region);
+        set(&mut self.storage.s3.prefix, cli.s3_prefix);
+        if cli.s3_access_key_id.is_some() {
+            self.storage.s3.access_key_id = cli.s3_access_key_id;
+        }
+        if cli.s3_secret_access_key.is_some() {
+            self.storage.s3.secret_access_key = cli.s3_secret_access_key;
+        }
+        set(&mut self.sealing.key_provider, cli.key_provider);
+        set(&mut self.sealing.policy, cli.sealing_policy);
+        set(
+            &mut self.sealing.approved_predecessor_mrenclaves,
+            cli.approved_predecessor_mrenclaves,
+        );
+        set(&mut self.solana.rpc_url, cli.solana_rpc_url);
+        set(
+            &mut self.solana.drt_manager_program_id,
+            cli.drt_manager_program_id,
+        );
+        set(
+            &mut self.solana.max_redemption_age_secs,
+            cli.max_redemption_age_secs,
+        );
+        set(&mut self.limits.max_request_bytes, cli.max_request_bytes);
+        set(&mut self.limits.max_pool_bytes, cli.max_pool_bytes);
+        set(&mut self.limits.max_download_bytes, cli.max_download_bytes);
+        set(&mut self.executor.threads, cli.executor_threads);
+        set(&mut self.executor.max_queued_jobs, cli.max_queued_jobs);
+        set(
+            &mut self.executor.job_retention_secs,
+            cli.job_retention_secs,
+        );
+        set(&mut self.executor.download_dir, cli.download_dir);
+        if cli.wasm_fuel.is_some() {
+            self.executor.wasm_fuel = cli.wasm_fuel;
+        }
+        if cli.wasm_max_memory_pages.is_some() {
+            self.executor.wasm_max_memory_pages = cli.wasm_max_memory_pages;
+        }
+        set(
+            &mut self.auth.max_clock_skew_secs,
+            cli.auth_max_clock_skew_secs,
+        );
+        set(&mut self.auth.operator_wallets, cli.operator_wallets);
+        set(&mut self.endpoints.disabled, cli.disabled_endpoints);
+        set(&mut self.logging.level, cli.log_level);
+        set(&mut self.logging.format, cli.log_format);
+
+        // Empty list entries come from unset values such as `APPROVED_PREDECESSOR_MRENCLAVES=`
+        self.sealing
+            .approved_predecessor_mrenclaves
+            .retain(|entry| !entry.trim().is_empty());
+        self.auth
+            .operator_wallets
+            .retain(|entry| !entry.trim().is_empty());
+        self.endpoints
+            .disabled
+            .retain(|entry| !entry.trim().is_empty());
+    }
+
+    /// Checks that the configuration is usable, naming the offending setting otherwise
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |setting: &str, reason: String| {
+            Err(anyhow!("Invalid configuration: {} {}", setting, reason))
+        };
+
+        if let Err(e) = self.server.listen_address.parse::<SocketAddr>() {
+            return invalid(
+                "server.listen_address",
+                format!("'{}': {}", self.server.listen_address, e),
+            );
+        }
+        if self.server.workers == 0 {
+            return invalid("server.workers", "must be at least 1".to_string());
+        }
+        if self.server.tls_cert_path.is_empty() {
+            return invalid("server.tls_cert_path", "must not be empty".to_string());
+        }
+        if self.server.tls_key_path.is_empty() {
+            return invalid("server.tls_key_path", "must not be empty".to_string());
+        }
+
+        if !self.storage.data_dir.is_absolute() {
+            return invalid("storage.data_dir", "must be an absolute path".to_string());
+        }
+        match self.storage.backend {
+            StoreBackend::Local => {}
+            StoreBackend::Ipfs => check_url("storage.ipfs_api_url", &self.storage.ipfs_api_url)?,
+            StoreBackend::S3 => {
+                let s3 = &self.storage.s3;
+                check_url("storage.s3.endpoint", &s3.endpoint)?;
+                if s3.bucket.is_empty() {
+                    return invalid("storage.s3.bucket", "must not be empty".to_string());
+                }
+                if s3.region.is_empty() {
+                    return invalid("storage.s3.region", "must not be empty".to_string());
+                }
+                if s3.access_key_id.as_deref().unwrap_or_default().is_empty()
+                    || s3
+                        .secret_access_key
+                        .as_deref()
+                        .unwrap_or_default()
+                        .is_empty()
+                {
+                    return invalid(
+                        "storage.s3",
+                        "needs S3_ACCESS_KEY_ID and S3_SECRET_ACCESS_KEY".to_string(),
+                    );
+                }
+            }
+        }
+
+        for mrenclave in &self.sealing.approved_predecessor_mrenclaves {
+            if let Err(e) = parse_mrenclave(mrenclave) {
+                return invalid("sealing.approved_predecessor_mrenclaves", e.to_string());
+            }
+        }
+
+        check_url("solana.rpc_url", &self.solana.rpc_url)?;
+        if let Err(e) = PoolRegistry::validate_pool_id(&self.solana.drt_manager_program_id) {
+            return invalid("solana.drt_manager_program_id", e.to_string());
+        }
+        if self.solana.max_redemption_age_secs == 0 {
+            return invalid(
+                "solana.max_redemption_age_secs",
+                "must be positive".to_string(),
+            );
+        }
+
+        if self.limits.max_request_bytes == 0 {
+            return invalid("limits.max_request_bytes", "must be positive".to_string());
+        }
+        if self.limits.max_pool_bytes == 0 {
+            return invalid("limits.max_pool_bytes", "must be positive".to_string());
+        }
+        if self.limits.max_download_bytes == 0 {
+            return invalid("limits.max_download_bytes", "must be positive".to_string());
+        }
+
+        if self.executor.threads == 0 {
+            return invalid("executor.threads", "must be at least 1".to_string());
+        }
+        if self.executor.job_retention_secs == 0 {
+            return invalid(
+                "executor.job_retention_secs",
+                "must be positive".to_string(),
+            );
+        }
+        if !self.executor.download_dir.is_absolute() {
+            return invalid(
+                "executor.download_dir",
+                "must be an absolute path".to_string(),
+            );
+        }
+        if self.executor.wasm_fuel == Some(0) {
+            return invalid("executor.wasm_fuel", "must be positive".to_string());
+        }
+        if self
+            .executor
+            .wasm_max_memory_pages
+            .is_some_and(|pages| pages < 17)
+        {
+            // Executions always start with 17 pages of memory
+            return invalid(
+                "executor.wasm_max_memory_pages",
+                "must be at least 17".to_string(),
+            );
+        }
+
+        if self.auth.max_clock_skew_secs == 0 {
+            return invalid("auth.max_clock_skew_secs", "must be positive".to_string());
+        }
+        for wallet in &self.auth.operator_wallets {
+            if !bs58::decode(wallet)
+                .into_vec()
+                .is_ok_and(|key| key.len() == 32)
+            {
+                return invalid(
+                    "auth.operator_wallets",
+                    format!("'{}' is not a base58 32-byte public key", wallet),
+                );
+            }
+        }
+
+        for name in &self.endpoints.disabled {
+            if !ENDPOINTS.contains(&name.as_str()) {
+                return invalid(
+                    "endpoints.disabled",
+                    format!("contains unknown endpoint '{}'", name),
+                );
+            }
+        }
+
+        if let Err(e) = EnvFilter::try_new(&self.logging.level) {
+            return invalid("logging.level", format!("'{}': {}", self.logging.level, e));
+        }
+
+        Ok(())
+    }
+
+    /// Directory the local store keeps sealed data pools in
+    pub fn pools_dir(&self) -> PathBuf {
+        self.storage.data_dir.join("pools")
+    }
+
+    /// Sealed file holding consumed redemptions
+    pub fn ledger_path(&self) -> PathBuf {
+        self.storage.data_dir.join("redemptions")
+    }
+
+    /// Append-only file of sealed audit log entries
+    pub fn audit_log_path(&self) -> PathBuf {
+        self.storage.data_dir.join("audit_log")
+    }
+
+    /// Single data pool sealed by enclaves predating the pool registry
+    pub fn legacy_data_pool_path(&self) -> PathBuf {
+        self.storage.data_dir.join("data_pool")
+    }
+}
+
+/// Checks that a setting holds an HTTP or HTTPS URL
+fn check_url(setting: &str, url: &str) -> Result<()> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Invalid configuration: {} '{}' must be an http:// or https:// URL",
+            setting,
+            url
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPERATOR: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+
+    fn parse(toml: &str) -> Result<Config> {
+        toml::from_str(toml).map_err(|e| anyhow!("{}", e))
+    }
+
+    /// Checks that `validate` names `setting` once `change` is made to the defaults
+    fn assert_rejects(setting: &str, change: impl FnOnce(&mut Config)) {
+        let mut config = Config::default();
+        change(&mut config);
+        let error = config.validate().unwrap_err().to_string();
+        assert!(
+            error.contains(setting),
+            "'{}' does not name {}",
+            error,
+            setting
+        );
+    }
+
+    #[test]
+    fn example_file_holds_the_defaults() {
+        let config = Config::from_file(Path::new("config.example.toml")).unwrap();
+        let defaults = Config::default();
+        assert_eq!(config.server.listen_address, defaults.server.listen_address);
+        assert_eq!(config.storage.data_dir, defaults.storage.data_dir);
+        assert_eq!(config.storage.backend, defaults.storage.backend);
+        assert_eq!(config.solana.rpc_url, defaults.solana.rpc_url);
+        assert_eq!(config.limits.max_pool_bytes, defaults.limits.max_pool_bytes);
+        assert_eq!(config.executor.threads, defaults.executor.threads);
+        config.validate().unwrap();
+        defaults.validate().unwrap();
+    }
+
+    #[test]
+    fn file_settings_override_only_what_they_set() {
+        let config = parse(
+            r#"
+            [server]
+            workers = 8
+
+            [storage]
+            backend = "s3"
+
+            [storage.s3]
+            bucket = "other-bucket"
+
+            [auth]
+            operator_wallets = ["9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM"]
+            "#,
+        )
+        .unwrap();
+        let defaults = Config::default();
+        assert_eq!(config.server.workers, 8);
+        assert_eq!(config.server.listen_address, defaults.server.listen_address);
+        assert_eq!(config.storage.backend, StoreBackend::S3);
+        assert_eq!(config.storage.s3.bucket, "other-bucket");
+        assert_eq!(config.storage.s3.region, defaults.storage.s3.region);
+        assert_eq!(config.auth.operator_wallets, vec![OPERATOR.to_string()]);
+    }
+
+    #[test]
+    fn rejects_unknown_settings() {
+        assert!(parse("[server]\nworker = 8\n").is_err());
+        assert!(parse("[storage.s3]\nsecret = \"x\"\n").is_err());
+        assert!(parse("[unknown]\n").is_err());
+        assert!(parse("listen_address = \"127.0.0.1:8080\"\n").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_values_while_parsing() {
+        assert!(parse("[storage]\nbackend = \"ftp\"\n").is_err());
+        assert!(parse("[sealing]\npolicy = \"mrsomething\"\n").is_err());
+        assert!(parse("[server]\nworkers = -1\n").is_err());
+    }
+
+    #[test]
+    fn command_line_overrides_environment_which_overrides_file() {
+        let mut config = parse("[server]\nworkers = 3\n[limits]\nmax_pool_bytes = 1000\n").unwrap();
+        std::env::set_var("WORKERS", "5");
+        std::env::set_var("MAX_POOL_BYTES", "2000");
+        std::env::set_var("OPERATOR_WALLETS", "");
+        let cli = Cli::try_parse_from(["sgx-mvp", "--workers", "7"]);
+        std::env::remove_var("WORKERS");
+        std::env::remove_var("MAX_POOL_BYTES");
+        std::env::remove_var("OPERATOR_WALLETS");
+        config.apply(cli.unwrap());
+
+        assert_eq!(config.server.workers, 7);
+        assert_eq!(config.limits.max_pool_bytes, 2000);
+        assert!(config.auth.operator_wallets.is_empty());
+
+        // Settings given nowhere else keep the file's value
+        let mut config = parse("[limits]\nmax_pool_bytes = 1000\n").unwrap();
+        config.apply(Cli::try_parse_from(["sgx-mvp"]).unwrap());
+        assert_eq!(config.limits.max_pool_bytes, 1000);
+    }
+
+    #[test]
+    fn validate_names_the_offending_setting() {
+        assert_rejects("server.listen_address", |c| {
+            c.server.listen_address = "localhost".to_string()
+        });
+        assert_rejects("server.workers", |c| c.server.workers = 0);
+        assert_rejects("server.tls_cert_path", |c| c.server.tls_cert_path.clear());
+        assert_rejects("server.tls_key_path", |c| c.server.tls_key_path.clear());
+        assert_rejects("storage.data_dir", |c| {
+            c.storage.data_dir = PathBuf::from("data")
+        });
+        assert_rejects("storage.ipfs_api_url", |c| {
+            c.storage.backend = StoreBackend::Ipfs;
+            c.storage.ipfs_api_url = "127.0.0.1:5001".to_string();
+        });
+        assert_rejects("storage.s3.endpoint", |c| {
+            c.storage.backend = StoreBackend::S3;
+            c.storage.s3.endpoint = "s3.local".to_string();
+        });
+        assert_rejects("storage.s3.bucket", |c| {
+            c.storage.backend = StoreBackend::S3;
+            c.storage.s3.bucket.clear();
+        });
+        assert_rejects("storage.s3.region", |c| {
+            c.storage.backend = StoreBackend::S3;
+            c.storage.s3.region.clear();
+        });
+        assert_rejects("storage.s3", |c| {
+            c.storage.backend = StoreBackend::S3;
+            c.storage.s3.access_key_id = Some("key".to_string());
+        });
+        assert_rejects("sealing.approved_predecessor_mrenclaves", |c| {
+            c.sealing.approved_predecessor_mrenclaves = vec!["abcd".to_string()]
+        });
+        assert_rejects("solana.rpc_url", |c| c.solana.rpc_url = "rpc".to_string());
+        assert_rejects("solana.drt_manager_program_id", |c| {
+            c.solana.drt_manager_program_id = "not-a-key".to_string()
+        });
+        assert_rejects("solana.max_redemption_age_secs", |c| {
+            c.solana.max_redemption_age_secs = 0
+        });
+        assert_rejects("limits.max_request_bytes", |c| {
+            c.limits.max_request_bytes = 0
+        });
+        assert_rejects("limits.max_pool_bytes", |c| c.limits.max_pool_bytes = 0);
+        assert_rejects("limits.max_download_bytes", |c| {
+            c.limits.max_download_bytes = 0
+        });
+        assert_rejects("executor.threads", |c| c.executor.threads = 0);
+        assert_rejects("executor.job_retention_secs", |c| {
+            c.executor.job_retention_secs = 0
+        });
+        assert_rejects("executor.download_dir", |c| {
+            c.executor.download_dir = PathBuf::from("tmp")
+        });
+        assert_rejects("executor.wasm_fuel", |c| c.executor.wasm_fuel = Some(0));
+        assert_rejects("executor.wasm_max_memory_pages", |c| {
+            c.executor.wasm_max_memory_pages = Some(16)
+        });
+        assert_rejects("auth.max_clock_skew_secs", |c| {
+            c.auth.max_clock_skew_secs = 0
+        });
+        assert_rejects("auth.operator_wallets", |c| {
+            c.auth.operator_wallets = vec!["operator".to_string()]
+        });
+        assert_rejects("endpoints.disabled", |c| {
+            c.endpoints.disabled = vec!["view_everything".to_string()]
+        });
+        assert_rejects("logging.level", |c| {
+            c.logging.level = "sgx_mvp=loud".to_string()
+        });
+    }
+}
@@ -0,0 +1,338 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/encryption.rs
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::VerifyingKey;
+use hkdf::Hkdf;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// HPKE suite results are encrypted with (RFC 9180, base mode)
+pub const RESULT_SUITE: &str = "DHKEM(X25519, HKDF-SHA256), HKDF-SHA256, AES-256-GCM";
+
+/// HPKE `info` every encrypted result is bound to
+pub const RESULT_INFO: &[u8] = b"ntc-result-v1";
+
+const KEM_ID: u16 = 0x0020; // DHKEM(X25519, HKDF-SHA256)
+const KDF_ID: u16 = 0x0001; // HKDF-SHA256
+const AEAD_ID: u16 = 0x0002; // AES-256-GCM
+
+/// Public key a computation result is encrypted to, as given in a request
+#[derive(Deserialize, Clone)]
+#[serde(tag = "type", content = "key", rename_all = "lowercase")]
+pub enum ResultRecipient {
+    X25519(String), // Base64-encoded X25519 public key
+    Solana(String), // Base58-encoded Solana wallet, converted to its X25519 form
+}
+
+impl ResultRecipient {
+    /// Decodes the recipient into the X25519 public key results are encrypted to
+    pub fn public_key(&self) -> Result<PublicKey> {
+        let bytes: [u8; 32] = match self {
+            ResultRecipient::X25519(key) => BASE64
+                .decode(key)
+                .map_err(|e| anyhow!("Invalid X25519 public key: {}", e))?
+                .try_into()
+                .map_err(|_| anyhow!("X25519 public key must be 32 bytes"))?,
+            ResultRecipient::Solana(wallet) => {
+                let bytes: [u8; 32] = bs58::decode(wallet)
+                    .into_vec()
+                    .map_err(|e| anyhow!("Invalid Solana public key: {}", e))?
+                    .try_into()
+                    .map_err(|_| anyhow!("Solana public key must be 32 bytes"))?;
+
+                // The Edwards point of the Ed25519 key maps onto the Montgomery
+                // form used by X25519; the wallet's secret key maps the same way
+                VerifyingKey::from_bytes(&bytes)
+                    .map_err(|_| anyhow!("Solana public key is not a valid Ed25519 key"))?
+                    .to_montgomery()
+                    .to_bytes()
+            }
+        };
+        Ok(PublicKey::from(bytes))
+    }
+}
+
+/// Encrypts a computation result to the recipient with single-shot HPKE.
+///
+/// Returns the encapsulated key and ciphertext, both base64-encoded, so any
+/// RFC 9180 implementation holding the recipient's private key can open it.
+pub fn encrypt_result(recipient: &PublicKey, result: &Value) -> Result<Value> {
+    let plaintext =
+        serde_json::to_vec(result).map_err(|e| anyhow!("Failed to serialize result: {}", e))?;
+    let (enc, ciphertext) = hpke_seal(recipient, RESULT_INFO, &plaintext)?;
+
+    Ok(json!({
+        "suite": RESULT_SUITE,
+        "info": String::from_utf8_lossy(RESULT_INFO),
+        "enc": BASE64.encode(enc),
+        "ciphertext": BASE64.encode(ciphertext),
+    }))
+}
+
+/// Encrypts a message to the recipient with single-shot HPKE in base mode,
+/// returning the encapsulated key and the ciphertext
+pub fn hpke_seal(
+    recipient: &PublicKey,
+    info: &[u8],
+    plaintext: &[u8],
+) -> Result<([u8; 32], Vec<u8>)> {
+    let ephemeral = StaticSecret::random_from_rng(rand::rngs::OsRng);
+    seal_with_ephemeral(&ephemeral, recipient, info, plaintext)
+}
+
+/// Opens a message encrypted with `hpke_seal` to the public key of `secret`
+pub fn hpke_open(
+    secret: &StaticSecret,
+    info: &[u8],
+    enc: &[u8; 32],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>> {
+    let enc = PublicKey::from(*enc);
+    let dh = secret.diffie_hellman(&enc);
+    if !dh.was_contributory() {
+        return Err(anyhow!("Encapsulated key is a low-order point"));
+    }
+
+    let shared_secret = shared_secret(dh.as_bytes(), &enc, &PublicKey::from(secret))?;
+    let (key, base_nonce) = key_schedule(&shared_secret, info)?;
+    let cipher = Aes256Gcm::new_from_slice(&key)
+        .map_err(|e| anyhow!("Failed to initialize cipher: {}", e))?;
+    cipher
+        .decrypt(
+            Nonce::from_slice(&base_nonce),
+            Payload {
+                msg: ciphertext,
+                aad: b"",
+            },
+        )
+        .map_err(|e| anyhow!("Decryption failed: {}", e))
+}
+
+/// Encap and single-shot seal with a given ephemeral key
+fn seal_with_ephemeral(
+    ephemeral: &StaticSecret,
+    recipient: &PublicKey,
+    info: &[u8],
+    plaintext: &[u8],
+) -> Result<([u8; 32], Vec<u8>)> {
+    // Encap: DH between the ephemeral key and the recipient
+    let enc = PublicKey::from(ephemeral);
+    let dh = ephemeral.diffie_hellman(recipient);
+    if !dh.was_contributory() {
+        return Err(anyhow!("Recipient public key is a low-order point"));
+    }
+
+    // A single message uses sequence number 0, so the nonce is the base nonce
+    let shared_secret = shared_secret(dh.as_bytes(), &enc, recipient)?;
+    let (key, base_nonce) = key_schedule(&shared_secret, info)?;
+    let cipher = Aes256Gcm::new_from_slice(&key)
+        .map_err(|e| anyhow!("Failed to initialize cipher: {}", e))?;
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&base_nonce),
+            Payload {
+                msg: plaintext,
+                aad: b"",
+            },
+        )
+        .map_err(|e| anyhow!("Encryption failed: {}", e))?;
+
+    Ok((enc.to_bytes(), ciphertext))
+}
+
+/// Derives the DHKEM shared secret from the DH output and both public keys
+fn shared_secret(dh: &[u8; 32], enc: &PublicKey, recipient: &PublicKey) -> Result<[u8; 32]> {
+    let mut kem_context = Vec::with_capacity(64);
+    kem_context.extend_from_slice(enc.as_bytes());
+    kem_context.extend_from_slice(recipient.as_bytes());
+
+    let kem_suite = suite_id(b"KEM", &[KEM_ID]);
+    let eae_prk = labeled_extract(&kem_suite, b"", b"eae_prk", dh);
+    labeled_expand::<32>(&kem_suite, &eae_prk, b"shared_secret", &kem_context)
+}
+
+/// Derives the AEAD key and base nonce of the key schedule in base mode,
+/// without a PSK
+fn key_schedule(shared_secret: &[u8; 32], info: &[u8]) -> Result<([u8; 32], [u8; 12])> {
+    let hpke_suite = suite_id(b"HPKE", &[KEM_ID, KDF_ID, AEAD_ID]);
+    let psk_id_hash = labeled_extract(&hpke_suite, b"", b"psk_id_hash", b"");
+    let info_hash = labeled_extract(&hpke_suite, b"", b"info_hash", info);
+    let mut context = vec![0u8]; // mode_base
+    context.extend_from_slice(&psk_id_hash);
+    context.extend_from_slice(&info_hash);
+
+    let secret = labeled_extract(&hpke_suite, shared_secret, b"secret", b"");
+    let key = labeled_expand::<32>(&hpke_suite, &secret, b"key", &context)?;
+    let base_nonce = labeled_expand::<12>(&hpke_suite, &secret, b"base_nonce", &context)?;
+    Ok((key, base_nonce))
+}
+
+/// Builds an HPKE suite ID from a prefix and big-endian algorithm IDs
+fn suite_id(prefix: &[u8], ids: &[u16]) -> Vec<u8> {
+    let mut suite = prefix.to_vec();
+    for id in ids {
+        suite.extend_from_slice(&id.to_be_bytes());
+    }
+    suite
+}
+
+/// `LabeledExtract` of RFC 9180
+fn labeled_extract(suite: &[u8], salt: &[u8], label: &[u8], ikm: &[u8]) -> Vec<u8> {
+    let labeled_ikm = [b"HPKE-v1", suite, label, ikm].concat();
+    let (prk, _) = Hkdf::<Sha256>::extract(Some(salt), &labeled_ikm);
+    prk.to_vec()
+}
+
+/// `LabeledExpand` of RFC 9180
+fn labeled_expand<const L: usize>(
+    suite: &[u8],
+    prk: &[u8],
+    label: &[u8],
+    info: &[u8],
+) -> Result<[u8; L]> {
+    let length = (L as u16).to_be_bytes();
+    let labeled_info = [&length[..], b"HPKE-v1", suite, label, info].concat();
+    let hkdf = Hkdf::<Sha256>::from_prk(prk).map_err(|e| anyhow!("Invalid HPKE secret: {}", e))?;
+    let mut output = [0u8; L];
+    hkdf.expand(&labeled_info, &mut output)
+        .map_err(|e| anyhow!("HPKE key derivation failed: {}", e))?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex_vec(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn unhex<const N: usize>(hex: &str) -> [u8; N] {
+        unhex_vec(hex).try_into().unwrap()
+    }
+
+    #[test]
+    fn kem_matches_rfc_9180_vector() {
+        // RFC 9180 A.1.1: DHKEM(X25519, HKDF-SHA256), base mode
+        let ephemeral = StaticSecret::from(unhex::<32>(
+            "52c4a758a802cd8b936eceea314432798d5baf2d7e9235dc084ab1b9cfa2f736",
+        ));
+        let secret = StaticSecret::from(unhex::<32>(
+            "4612c550263fc8ad58375df3f557aac531d26850903e55a9f23f21d8534e8ac8",
+        ));
+        let recipient = PublicKey::from(&secret);
+        assert_eq!(
+            recipient.to_bytes(),
+            unhex::<32>("3948cfe0ad1ddb695d780e59077195da6c56506b027329794ab02bca80815c4d")
+        );
+
+        let enc = PublicKey::from(&ephemeral);
+        assert_eq!(
+            enc.to_bytes(),
+            unhex::<32>("37fda3567bdbd628e88668c3c8d7e97d1d1253b6d4ea6d44c150f741f1bf4431")
+        );
+        let dh = ephemeral.diffie_hellman(&recipient);
+        assert_eq!(
+            shared_secret(dh.as_bytes(), &enc, &recipient).unwrap(),
+            unhex::<32>("fe0e18c9f024ce43799ae393c7e8fe8fce9d218875e8227b0187c04e7d2ea1fc")
+        );
+    }
+
+    #[test]
+    fn seal_matches_known_answer() {
+        // The RFC has no vector for AES-256-GCM with X25519; this one was
+        // generated and opened with an independent HPKE implementation
+        let ephemeral = StaticSecret::from(unhex::<32>(
+            "df5d54b0a942b593318506a3603d8563be664834f75de111d3bfe38b9b39886f",
+        ));
+        let secret = StaticSecret::from(unhex::<32>(
+            "f293c18f7a7a25079463e483fbb5e3890fe79ae8664f7a5d2a62ebcfab91aea4",
+        ));
+        let recipient = PublicKey::from(&secret);
+        let dh = ephemeral.diffie_hellman(&recipient);
+        let shared =
+            shared_secret(dh.as_bytes(), &PublicKey::from(&ephemeral), &recipient).unwrap();
+        assert_eq!(
+            shared,
+            unhex::<32>("592f3d061c1ccf876d359bcad96c4aa9603b81988723a3c1265d6f90d768f7f3")
+        );
+        let (key, base_nonce) = key_schedule(&shared, RESULT_INFO).unwrap();
+        assert_eq!(
+            key,
+            unhex::<32>("7862d4456f64fa0045ea826c9d79c2c649ec5f8373ca0249b2f1bb172ea270aa")
+        );
+        assert_eq!(base_nonce, unhex::<12>("83fdf393e5ece8b14ac17b21"));
+
+        let (enc, ciphertext) =
+            seal_with_ephemeral(&ephemeral, &recipient, RESULT_INFO, br#"{"result":42}"#).unwrap();
+        assert_eq!(
+            enc,
+            unhex::<32>("332022db2d2866f03a8a856d07b01fd8c3835c31e48b7b16b94220da45355328")
+        );
+        assert_eq!(
+            ciphertext,
+            unhex_vec("e09885e1496a4bb6e84ba3ed134c2a79228797fd4bdea5432f473085cd")
+        );
+        assert_eq!(
+            hpke_open(&secret, RESULT_INFO, &enc, &ciphertext).unwrap(),
+            br#"{"result":42}"#
+        );
+    }
+
+    #[test]
+    fn open_rejects_wrong_key_info_or_tampering() {
+        let secret = StaticSecret::from([7u8; 32]);
+        let (enc, ciphertext) =
+            hpke_seal(&PublicKey::from(&secret), RESULT_INFO, b"result").unwrap();
+        assert_eq!(
+            hpke_open(&secret, RESULT_INFO, &enc, &ciphertext).unwrap(),
+            b"result"
+        );
+
+        let other = StaticSecret::from([8u8; 32]);
+        assert!(hpke_open(&other, RESULT_INFO, &enc, &ciphertext).is_err());
+        assert!(hpke_open(&secret, b"ntc-other", &enc, &ciphertext).is_err());
+        let mut tampered = ciphertext.clone();
+        tampered[0] ^= 1;
+        assert!(hpke_open(&secret, RESULT_INFO, &enc, &tampered).is_err());
+    }
+
+    #[test]
+    fn solana_recipient_maps_to_x25519() {
+        let signing = ed25519_dalek::SigningKey::from_bytes(&[9u8; 32]);
+        let wallet = bs58::encode(signing.verifying_key().as_bytes()).into_string();
+        let recipient = ResultRecipient::Solana(wallet).public_key().unwrap();
+
+        // The wallet's secret key opens results after the same conversion
+        let secret = StaticSecret::from(signing.to_scalar_bytes());
+        assert_eq!(recipient, PublicKey::from(&secret));
+        assert!(ResultRecipient::X25519(BASE64.encode([0u8; 31]))
+            .public_key()
+            .is_err());
+    }
+}
@@ -0,0 +1,391 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/errors.rs
+
+use crate::schema::SchemaViolation;
+use actix_web::dev::ServiceResponse;
+use actix_web::error::{JsonPayloadError, QueryPayloadError};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use python_rust_impl::PythonException;
+use serde_json::json;
+use std::fmt;
+use wasmi_impl::WasmErrorCode;
+
+/// Response header carrying the ID of the request
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Error returned to an API client.
+///
+/// Each variant has a stable machine-readable code and is sent as a JSON body
+/// together with the request ID. Messages are fixed so that no internal detail,
+/// such as sealed data or file paths, reaches the client; the detail is logged
+/// where the error occurs instead. Only errors about the data a client sent
+/// describe that data, so that the client can correct it.
+#[derive(Debug, Clone)]
+pub enum ApiError {
+    InvalidRequest(String), // Malformed request body or query
+    InvalidPoolId,          // Pool ID is not a base58 32-byte key
+    InvalidData(String),    // Data does not have the shape of the data pool, and why
+    InvalidSchema(String),  // Schema of a new data pool is not a valid JSON Schema
+    // Data does not satisfy the schema of the data pool, with each offending value
+    SchemaViolation(Vec<SchemaViolation>),
+    MigrationRejected,       // Migration bundle or target key was not accepted
+    Unauthorized,            // Wallet signature is missing or invalid
+    RedemptionRejected,      // DRT redemption does not cover the request
+    NotPoolOwner,            // Request is not signed by the owner of the data pool
+    NotRedeemer,             // Request is not signed by the wallet that redeemed the DRT
+    NotOperator,             // Request is not signed by an operator wallet
+    NotFound,                // No such endpoint
+    PoolNotFound,            // No data pool with the given ID
+    JobNotFound,             // No job with the given ID, or it has expired
+    PoolExists,              // Data pool with the given ID already exists
+    RedemptionUsed,          // DRT redemption has already paid for a request
+    JobNotFinished,          // Job has no result yet
+    JobCancelled,            // Job was cancelled before it finished
+    RequestTooLarge,         // Request body exceeds the size limit
+    PoolTooLarge,            // Data pool would exceed the size limit
+    CodeVerificationFailed,  // Code could not be downloaded or did not match its hash
+    Wasm(WasmErrorCode),     // WASM binary returned an error code
+    PythonException(String), // Python script raised the named exception
+    ExecutionFailed,         // Code failed in any other way, e.g. a trap or running out of fuel
+    Internal(&'static str),  // Server-side failure, with a short description
+    Busy,                    // Worker pool queue is full
+}
+
+impl ApiError {
+    /// Returns the stable machine-readable code of the error
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::InvalidRequest(_) => "invalid_request",
+            ApiError::InvalidPoolId => "invalid_pool_id",
+            ApiError::InvalidData(_) => "invalid_data",
+            ApiError::InvalidSchema(_) => "invalid_schema",
+            ApiError::SchemaViolation(_) => "schema_violation",
+            ApiError::MigrationRejected => "migration_rejected",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::RedemptionRejected => "redemption_rejected",
+            ApiError::NotPoolOwner => "not_pool_owner",
+            ApiError::NotRedeemer => "not_redeemer",
+            ApiError::NotOperator => "not_operator",
+            ApiError::NotFound => "not_found",
+            ApiError::PoolNotFound => "pool_not_found",
+            ApiError::JobNotFound => "job_not_found",
+            ApiError::PoolExists => "pool_exists",
+            ApiError::RedemptionUsed => "redemption_used",
+            ApiError::JobNotFinished => "job_not_finished",
+            ApiError::JobCancelled => "job_cancelled",
+            ApiError::RequestTooLarge => "request_too_large",
+            ApiError::PoolTooLarge => "pool_too_large",
+            ApiError::CodeVerificationFailed => "code_verification_failed",
+            ApiError::Wasm(code) => match code {
+                WasmErrorCode::ParseInputData => "wasm_parse_input_data",
+                WasmErrorCode::ParseSchema => "wasm_parse_schema",
+                WasmErrorCode::SerializeOutput => "wasm_serialize_output",
+                WasmErrorCode::OutputBufferTooSmall => "wasm_output_buffer_too_small",
+                WasmErrorCode::ExecutionFailed => "wasm_execution_failed",
+                WasmErrorCode::Success | WasmErrorCode::Unknown(_) => "wasm_unknown_error",
+            },
+            ApiError::PythonException(_) => "python_exception",
+            ApiError::ExecutionFailed => "execution_failed",
+            ApiError::Internal(_) => "internal_error",
+            ApiError::Busy => "server_busy",
+        }
+    }
+
+    /// Renders the error as a JSON response, tagged with the request ID if known.
+    ///
+    /// Schema violations are listed in a `violations` field, so the data
+    /// provider can see every value that was rejected.
+    pub fn to_response(&self, request_id: Option<&str>) -> HttpResponse {
+        let mut body = json!({
+            "code": self.code(),
+            "message": self.to_string(),
+            "request_id": request_id,
+        });
+        if let ApiError::SchemaViolation(violations) = self {
+            body["violations"] = json!(violations);
+        }
+        HttpResponse::build(self.status_code()).json(body)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::InvalidRequest(reason) => write!(f, "Invalid request: {}", reason),
+            ApiError::InvalidPoolId => write!(f, "Invalid pool ID"),
+            ApiError::InvalidData(reason) => {
+                write!(f, "Data does not match the data pool: {}", reason)
+            }
+            ApiError::InvalidSchema(reason) => write!(f, "{}", reason),
+            ApiError::SchemaViolation(_) => {
+                write!(f, "Data does not satisfy the schema of the data pool")
+            }
+            ApiError::MigrationRejected => write!(f, "Migration rejected by this enclave"),
+            ApiError::Unauthorized => write!(f, "Missing or invalid request signature"),
+            ApiError::RedemptionRejected => write!(f, "DRT redemption verification failed"),
+            ApiError::NotPoolOwner => write!(f, "Request is not signed by the pool owner"),
+            ApiError::NotRedeemer => write!(f, "Request is not signed by the DRT redeemer"),
+            ApiError::NotOperator => write!(f, "Request is not signed by an operator"),
+            ApiError::NotFound => write!(f, "Endpoint not found"),
+            ApiError::PoolNotFound => write!(f, "Data pool not found"),
+            ApiError::JobNotFound => write!(f, "Job not found"),
+            ApiError::PoolExists => write!(f, "Data pool already exists"),
+            ApiError::RedemptionUsed => write!(f, "DRT redemption has already been used"),
+            ApiError::JobNotFinished => write!(f, "Job has not finished yet"),
+            ApiError::JobCancelled => write!(f, "Job was cancelled"),
+            ApiError::RequestTooLarge => write!(f, "Request exceeds the size limit"),
+            ApiError::PoolTooLarge => write!(f, "Data pool exceeds the size limit"),
+            ApiError::CodeVerificationFailed => write!(f, "Failed to download or verify code"),
+            ApiError::Wasm(code) => write!(f, "{}", code),
+            ApiError::PythonException(exception) => {
+                write!(f, "Python script raised {}", exception)
+            }
+            ApiError::ExecutionFailed => write!(f, "Code execution failed"),
+            ApiError::Internal(description) => write!(f, "{}", description),
+            ApiError::Busy => write!(f, "Server is busy, please try again later"),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::InvalidRequest(_)
+            | ApiError::InvalidPoolId
+            | ApiError::InvalidData(_)
+            | ApiError::InvalidSchema(_)
+            | ApiError::SchemaViolation(_)
+            | ApiError::MigrationRejected => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::RedemptionRejected
+            | ApiError::NotPoolOwner
+            | ApiError::NotRedeemer
+            | ApiError::NotOperator => StatusCode::FORBIDDEN,
+            ApiError::NotFound | ApiError::PoolNotFound | ApiError::JobNotFound => {
+                StatusCode::NOT_FOUND
+            }
+            ApiError::PoolExists
+            | ApiError::RedemptionUsed
+            | ApiError::JobNotFinished
+            | ApiError::JobCancelled => StatusCode::CONFLICT,
+            ApiError::RequestTooLarge | ApiError::PoolTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::CodeVerificationFailed
+            | ApiError::Wasm(_)
+            | ApiError::PythonException(_)
+            | ApiError::ExecutionFailed => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Busy => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        self.to_response(None)
+    }
+}
+
+/// Maps the error of a failed WASM execution onto the API error for the client
+pub fn wasm_error(error: &anyhow::Error) -> ApiError {
+    match error.downcast_ref::<WasmErrorCode>() {
+        Some(code) => ApiError::Wasm(*code),
+        None => ApiError::ExecutionFailed,
+    }
+}
+
+/// Maps the error of a failed Python execution onto the API error for the client
+pub fn python_error(error: &anyhow::Error) -> ApiError {
+    match error.downcast_ref::<PythonException>() {
+        Some(exception) => ApiError::PythonException(exception.exception_type.clone()),
+        None => ApiError::ExecutionFailed,
+    }
+}
+
+/// Turns a rejected JSON body into an API error
+pub fn json_error(error: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    match error {
+        JsonPayloadError::Overflow { .. } | JsonPayloadError::OverflowKnownLength { .. } => {
+            ApiError::RequestTooLarge.into()
+        }
+        other => ApiError::InvalidRequest(other.to_string()).into(),
+    }
+}
+
+/// Turns a rejected query string into an API error
+pub fn query_error(error: QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    ApiError::InvalidRequest(error.to_string()).into()
+}
+
+/// Generates a random ID to tell requests apart in responses and logs
+pub fn new_request_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+/// Tags a response with its request ID, rendering an API error again so that
+/// its body carries the ID as well
+pub fn finish_response(response: ServiceResponse, request_id: &str) -> ServiceResponse {
+    let rendered = response
+        .response()
+        .error()
+        .and_then(|error| error.as_error::<ApiError>())
+        .map(|error| error.to_response(Some(request_id)));
+
+    let mut response = match rendered {
+        Some(rendered) => response.into_response(rendered),
+        None => response,
+    };
+    if let Ok(value) = HeaderValue::from_str(request_id) {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+    use actix_web::test::TestRequest;
+    use serde_json::Value;
+
+    async fn body(response: HttpResponse) -> Value {
+        serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn maps_errors_to_status_and_code() {
+        let cases = [
+            (ApiError::InvalidRequest("x".into()), 400, "invalid_request"),
+            (ApiError::InvalidPoolId, 400, "invalid_pool_id"),
+            (ApiError::InvalidData("x".into()), 400, "invalid_data"),
+            (ApiError::InvalidSchema("x".into()), 400, "invalid_schema"),
+            (
+                ApiError::SchemaViolation(Vec::new()),
+                400,
+                "schema_violation",
+            ),
+            (ApiError::MigrationRejected, 400, "migration_rejected"),
+            (ApiError::Unauthorized, 401, "unauthorized"),
+            (ApiError::RedemptionRejected, 403, "redemption_rejected"),
+            (ApiError::NotPoolOwner, 403, "not_pool_owner"),
+            (ApiError::NotRedeemer, 403, "not_redeemer"),
+            (ApiError::NotOperator, 403, "not_operator"),
+            (ApiError::NotFound, 404, "not_found"),
+            (ApiError::PoolNotFound, 404, "pool_not_found"),
+            (ApiError::JobNotFound, 404, "job_not_found"),
+            (ApiError::PoolExists, 409, "pool_exists"),
+            (ApiError::RedemptionUsed, 409, "redemption_used"),
+            (ApiError::JobNotFinished, 409, "job_not_finished"),
+            (ApiError::JobCancelled, 409, "job_cancelled"),
+            (ApiError::RequestTooLarge, 413, "request_too_large"),
+            (ApiError::PoolTooLarge, 413, "pool_too_large"),
+            (
+                ApiError::CodeVerificationFailed,
+                422,
+                "code_verification_failed",
+            ),
+            (
+                ApiError::Wasm(WasmErrorCode::ParseInputData),
+                422,
+                "wasm_parse_input_data",
+            ),
+            (
+                ApiError::Wasm(WasmErrorCode::OutputBufferTooSmall),
+                422,
+                "wasm_output_buffer_too_small",
+            ),
+            (
+                ApiError::Wasm(WasmErrorCode::Unknown(42)),
+                422,
+                "wasm_unknown_error",
+            ),
+            (
+                ApiError::PythonException("ValueError".into()),
+                422,
+                "python_exception",
+            ),
+            (ApiError::ExecutionFailed, 422, "execution_failed"),
+            (ApiError::Internal("Failed"), 500, "internal_error"),
+            (ApiError::Busy, 503, "server_busy"),
+        ];
+        for (error, status, code) in cases {
+            assert_eq!(error.status_code().as_u16(), status, "{}", code);
+            assert_eq!(error.code(), code);
+        }
+    }
+
+    #[actix_web::test]
+    async fn response_carries_code_message_and_request_id() {
+        let body = body(ApiError::PoolNotFound.to_response(Some("abc"))).await;
+        assert_eq!(body["code"], "pool_not_found");
+        assert_eq!(body["message"], "Data pool not found");
+        assert_eq!(body["request_id"], "abc");
+        assert!(body.get("violations").is_none());
+    }
+
+    #[actix_web::test]
+    async fn response_lists_schema_violations() {
+        let violation = SchemaViolation {
+            path: "/age/3".to_string(),
+            schema_path: "/properties/age/items/minimum".to_string(),
+            message: "-1 is less than the minimum of 0".to_string(),
+        };
+        let body = body(ApiError::SchemaViolation(vec![violation]).to_response(None)).await;
+        assert_eq!(body["violations"][0]["path"], "/age/3");
+        assert!(body["request_id"].is_null());
+    }
+
+    #[actix_web::test]
+    async fn finished_response_is_tagged_with_the_request_id() {
+        let request = TestRequest::default().to_http_request();
+        let response = ServiceResponse::from_err(ApiError::JobNotFound, request);
+        let response = finish_response(response, "0123");
+
+        assert_eq!(response.headers().get(REQUEST_ID_HEADER).unwrap(), "0123");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = body(response.into_parts().1).await;
+        assert_eq!(body["request_id"], "0123");
+    }
+
+    #[test]
+    fn classifies_wasm_errors() {
+        let error = anyhow::Error::new(WasmErrorCode::ParseSchema).context("Execution failed");
+        assert!(matches!(
+            wasm_error(&error),
+            ApiError::Wasm(WasmErrorCode::ParseSchema)
+        ));
+
+        let trap = anyhow::anyhow!("wasm trap: out of fuel");
+        assert!(matches!(wasm_error(&trap), ApiError::ExecutionFailed));
+    }
+
+    #[test]
+    fn classifies_python_errors_by_exception_type_only() {
+        let error = anyhow::Error::new(PythonException {
+            exception_type: "KeyError".to_string(),
+            message: "'salary' of row 7: 120000".to_string(),
+        });
+        let api_error = python_error(&error);
+        assert!(matches!(&api_error, ApiError::PythonException(name) if name == "KeyError"));
+        assert!(!api_error.to_string().contains("120000"));
+
+        let other = anyhow::anyhow!("Failed to start interpreter");
+        assert!(matches!(python_error(&other), ApiError::ExecutionFailed));
+    }
+}
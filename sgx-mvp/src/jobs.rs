@@ -0,0 +1,506 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/jobs.rs
+
+use crate::audit::CommittedResult;
+use crate::errors::ApiError;
+use crate::workers::WorkerPool;
+use actix_web::ResponseError;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Lifecycle of an execution job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn is_finished(self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+/// Execution submitted through the job API
+struct Job {
+    wallet: String, // Wallet that submitted the job, the only one that may see it
+    execution_type: &'static str,
+    pool_id: String,
+    status: JobStatus,
+    submitted_at: u64, // Unix timestamps (seconds)
+    started_at: Option<u64>,
+    finished_at: Option<u64>,
+    outcome: Option<Result<CommittedResult, ApiError>>, // Result or error, once the job has run
+}
+
+/// Execution jobs and their results, kept in enclave memory.
+///
+/// Finished jobs are dropped once they are older than the retention period, so
+/// results have to be collected within it. Nothing is written to disk and all
+/// jobs are lost when the enclave restarts.
+pub struct JobStore {
+    jobs: Mutex<HashMap<String, Job>>,
+    retention_secs: u64,
+}
+
+impl JobStore {
+    /// Creates a store keeping finished jobs for `retention_secs` seconds
+    pub fn new(retention_secs: u64) -> Self {
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            retention_secs,
+        }
+    }
+
+    /// Records a new queued job for `wallet` and returns its ID
+    pub fn submit(&self, wallet: &str, execution_type: &'static str, pool_id: &str) -> String {
+        let now = now();
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // Forget finished jobs whose results were not collected in time
+        jobs.retain(|_, job| {
+            job.finished_at
+                .is_none_or(|finished_at| finished_at.saturating_add(self.retention_secs) > now)
+        });
+
+        let job_id = format!("{:032x}", rand::random::<u128>());
+        jobs.insert(
+            job_id.clone(),
+            Job {
+                wallet: wallet.to_string(),
+                execution_type,
+                pool_id: pool_id.to_string(),
+                status: JobStatus::Queued,
+                submitted_at: now,
+                started_at: None,
+                finished_at: None,
+                outcome: None,
+            },
+        );
+        job_id
+    }
+
+    /// Submits a job for `wallet` and queues `execute` to run it on a worker
+    /// thread, returning the job ID.
+    ///
+    /// Fails with `503 Service Unavailable` if the queue is full, in which case
+    /// the job is forgotten again.
+    pub fn queue<F>(
+        self: Arc<Self>,
+        worker_pool: &WorkerPool,
+        wallet: &str,
+        execution_type: &'static str,
+        pool_id: &str,
+        execute: F,
+    ) -> Result<String, ApiError>
+    where
+        F: FnOnce() -> Result<CommittedResult, ApiError> + Send + 'static,
+    {
+        let job_id = self.submit(wallet, execution_type, pool_id);
+
+        let job = job_id.clone();
+        let store = self.clone();
+        let queued = worker_pool.spawn(move || {
+            // Skip jobs that were cancelled while queued
+            if !store.start(&job) {
+                return;
+            }
+            let outcome = catch_unwind(AssertUnwindSafe(execute))
+                .unwrap_or(Err(ApiError::Internal("Internal server error")));
+            store.finish(&job, outcome);
+        });
+        if let Err(e) = queued {
+            self.remove(&job_id);
+            return Err(e);
+        }
+        Ok(job_id)
+    }
+
+    /// Returns the number of jobs that are queued and running
+    pub fn in_flight(&self) -> (usize, usize) {
+        let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = |status| jobs.values().filter(|job| job.status == status).count();
+        (count(JobStatus::Queued), count(JobStatus::Running))
+    }
+
+    /// Forgets a job that could not be queued
+    fn remove(&self, job_id: &str) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        jobs.remove(job_id);
+    }
+
+    /// Marks a queued job as running. Returns false if it was cancelled meanwhile.
+    fn start(&self, job_id: &str) -> bool {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        match jobs.get_mut(job_id) {
+            Some(job) if job.status == JobStatus::Queued => {
+                job.status = JobStatus::Running;
+                job.started_at = Some(now());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Records the outcome of a running job, unless it was cancelled meanwhile
+    fn finish(&self, job_id: &str, outcome: Result<CommittedResult, ApiError>) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(job) = jobs.get_mut(job_id) {
+            if job.status == JobStatus::Running {
+                job.status = match outcome {
+                    Ok(_) => JobStatus::Succeeded,
+                    Err(_) => JobStatus::Failed,
+                };
+                job.finished_at = Some(now());
+                job.outcome = Some(outcome);
+            }
+        }
+    }
+
+    /// Cancels a job of `wallet` that has not finished yet and returns its status.
+    ///
+    /// A queued job never runs. A running job cannot be interrupted, so it runs to
+    /// completion but its result is discarded.
+    pub fn cancel(&self, job_id: &str, wallet: &str) -> Result<Value, ApiError> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = jobs
+            .get_mut(job_id)
+            .filter(|job| job.wallet == wallet)
+            .ok_or(ApiError::JobNotFound)?;
+        if !job.status.is_finished() {
+            job.status = JobStatus::Cancelled;
+            job.finished_at = Some(now());
+        }
+        Ok(Self::describe(job_id, job))
+    }
+
+    /// Returns the status of a job of `wallet`
+    pub fn status(&self, job_id: &str, wallet: &str) -> Result<Value, ApiError> {
+        let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = Self::get(&jobs, job_id, wallet)?;
+        Ok(Self::describe(job_id, job))
+    }
+
+    /// Returns the result of a job of `wallet`, or the error it failed with
+    pub fn result(&self, job_id: &str, wallet: &str) -> Result<CommittedResult, ApiError> {
+        let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let job = Self::get(&jobs, job_id, wallet)?;
+        match (&job.status, &job.outcome) {
+            (JobStatus::Cancelled, _) => Err(ApiError::JobCancelled),
+            (_, Some(outcome)) => outcome.clone(),
+            (_, None) => Err(ApiError::JobNotFinished),
+        }
+    }
+
+    /// Looks up a job, treating jobs of other wallets as if they did not exist
+    fn get<'a>(
+        jobs: &'a HashMap<String, Job>,
+        job_id: &str,
+        wallet: &str,
+    ) -> Result<&'a Job, ApiError> {
+        jobs.get(job_id)
+            .filter(|job| job.wallet == wallet)
+            .ok_or(ApiError::JobNotFound)
+    }
+
+    fn describe(job_id: &str, job: &Job) -> Value {
+        let error = match (&job.status, &job.outcome) {
+            (JobStatus::Failed, Some(Err(e))) => json!({
+                "code": e.code(),
+                "message": e.to_string(),
+                "status": e.status_code().as_u16(),
+            }),
+            _ => Value::Null,
+        };
+
+        json!({
+            "job_id": job_id,
+            "execution_type": job.execution_type,
+            "pool_id": job.pool_id,
+            "status": job.status,
+            "submitted_at": job.submitted_at,
+            "started_at": job.started_at,
+            "finished_at": job.finished_at,
+            "error": error,
+        })
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::mpsc::{channel, Sender};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    const WALLET: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+    const POOL_ID: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+
+    /// Returns a result as an execution does, with a salt
+    fn committed(value: Value) -> CommittedResult {
+        CommittedResult {
+            value,
+            salt: Some("00".repeat(32)),
+        }
+    }
+
+    /// Runs a job to completion with the given outcome and returns its ID
+    fn finished_job(store: &JobStore, outcome: Result<CommittedResult, ApiError>) -> String {
+        let job_id = store.submit(WALLET, "wasm", POOL_ID);
+        assert!(store.start(&job_id));
+        store.finish(&job_id, outcome);
+        job_id
+    }
+
+    /// Occupies the only worker of a pool until the returned sender is dropped
+    fn block_worker(pool: &WorkerPool) -> Sender<()> {
+        let (release, blocked) = channel::<()>();
+        let (started, wait_started) = channel();
+        pool.spawn(move || {
+            started.send(()).unwrap();
+            let _ = blocked.recv();
+        })
+        .unwrap();
+        wait_started.recv().unwrap();
+        release
+    }
+
+    /// Waits until a job has finished and returns its status
+    fn wait_for(store: &JobStore, job_id: &str) -> Value {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let status = store.status(job_id, WALLET).unwrap();
+            if status["finished_at"].is_u64() || Instant::now() > deadline {
+                return status;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn returns_the_result_or_error_of_a_finished_job() {
+        let store = JobStore::new(3600);
+        let succeeded = finished_job(&store, Ok(committed(json!({"mean": 4.5}))));
+        assert_eq!(
+            store.status(&succeeded, WALLET).unwrap()["status"],
+            "succeeded"
+        );
+        assert_eq!(store.result(&succeeded, WALLET).unwrap().value["mean"], 4.5);
+
+        let failed = finished_job(&store, Err(ApiError::ExecutionFailed));
+        let status = store.status(&failed, WALLET).unwrap();
+        assert_eq!(status["status"], "failed");
+        assert_eq!(status["error"]["code"], "execution_failed");
+        assert_eq!(status["error"]["status"], 422);
+        assert!(matches!(
+            store.result(&failed, WALLET),
+            Err(ApiError::ExecutionFailed)
+        ));
+    }
+
+    #[test]
+    fn jobs_are_only_visible_to_their_wallet() {
+        let store = JobStore::new(3600);
+        let job_id = finished_job(&store, Ok(committed(json!(1))));
+        let other = "CME2Dg7UEW82Hf99rQetEi7Hc5Db9JQPx6Azmx1eWbEE";
+        assert!(matches!(
+            store.status(&job_id, other),
+            Err(ApiError::JobNotFound)
+        ));
+        assert!(matches!(
+            store.result(&job_id, other),
+            Err(ApiError::JobNotFound)
+        ));
+        assert!(matches!(
+            store.cancel(&job_id, other),
+            Err(ApiError::JobNotFound)
+        ));
+    }
+
+    #[test]
+    fn finished_jobs_expire_after_the_retention_period() {
+        let store = JobStore::new(0);
+        let finished = finished_job(&store, Ok(committed(json!(1))));
+        let queued = store.submit(WALLET, "wasm", POOL_ID);
+        let running = store.submit(WALLET, "python", POOL_ID);
+        assert!(store.start(&running));
+
+        // Expired jobs are dropped when the next job is submitted
+        store.submit(WALLET, "wasm", POOL_ID);
+        assert!(matches!(
+            store.status(&finished, WALLET),
+            Err(ApiError::JobNotFound)
+        ));
+        assert_eq!(store.status(&queued, WALLET).unwrap()["status"], "queued");
+        assert_eq!(store.status(&running, WALLET).unwrap()["status"], "running");
+
+        let store = JobStore::new(3600);
+        let finished = finished_job(&store, Ok(committed(json!(1))));
+        store.submit(WALLET, "wasm", POOL_ID);
+        assert!(store.result(&finished, WALLET).is_ok());
+    }
+
+    #[test]
+    fn result_of_an_unfinished_job_is_not_ready() {
+        let store = JobStore::new(3600);
+        let job_id = store.submit(WALLET, "wasm", POOL_ID);
+        assert!(matches!(
+            store.result(&job_id, WALLET),
+            Err(ApiError::JobNotFinished)
+        ));
+        assert!(store.start(&job_id));
+        assert!(matches!(
+            store.result(&job_id, WALLET),
+            Err(ApiError::JobNotFinished)
+        ));
+    }
+
+    #[test]
+    fn cancelled_queued_job_never_starts() {
+        let store = JobStore::new(3600);
+        let job_id = store.submit(WALLET, "wasm", POOL_ID);
+        assert_eq!(
+            store.cancel(&job_id, WALLET).unwrap()["status"],
+            "cancelled"
+        );
+        assert!(!store.start(&job_id));
+        assert!(matches!(
+            store.result(&job_id, WALLET),
+            Err(ApiError::JobCancelled)
+        ));
+    }
+
+    #[test]
+    fn cancelled_running_job_discards_its_result() {
+        let store = JobStore::new(3600);
+        let job_id = store.submit(WALLET, "wasm", POOL_ID);
+        assert!(store.start(&job_id));
+        store.cancel(&job_id, WALLET).unwrap();
+        store.finish(&job_id, Ok(committed(json!("result"))));
+
+        assert_eq!(
+            store.status(&job_id, WALLET).unwrap()["status"],
+            "cancelled"
+        );
+        assert!(matches!(
+            store.result(&job_id, WALLET),
+            Err(ApiError::JobCancelled)
+        ));
+    }
+
+    #[test]
+    fn cancelling_a_finished_job_keeps_its_result() {
+        let store = JobStore::new(3600);
+        let job_id = finished_job(&store, Ok(committed(json!("result"))));
+        assert_eq!(
+            store.cancel(&job_id, WALLET).unwrap()["status"],
+            "succeeded"
+        );
+        assert_eq!(store.result(&job_id, WALLET).unwrap().value, "result");
+    }
+
+    #[test]
+    fn queued_job_runs_on_the_worker_pool() {
+        let store = Arc::new(JobStore::new(3600));
+        let pool = WorkerPool::new(1, 4).unwrap();
+        let job_id = store
+            .clone()
+            .queue(&pool, WALLET, "wasm", POOL_ID, || Ok(committed(json!(42))))
+            .unwrap();
+
+        assert_eq!(wait_for(&store, &job_id)["status"], "succeeded");
+        assert_eq!(store.result(&job_id, WALLET).unwrap().value, 42);
+    }
+
+    #[test]
+    fn panicking_job_fails() {
+        let store = Arc::new(JobStore::new(3600));
+        let pool = WorkerPool::new(1, 4).unwrap();
+        let job_id = store
+            .clone()
+            .queue(&pool, WALLET, "wasm", POOL_ID, || {
+                panic!("execution failed")
+            })
+            .unwrap();
+
+        assert_eq!(wait_for(&store, &job_id)["status"], "failed");
+        assert!(matches!(
+            store.result(&job_id, WALLET),
+            Err(ApiError::Internal(_))
+        ));
+    }
+
+    #[test]
+    fn job_is_rejected_and_forgotten_when_the_queue_is_full() {
+        let store = Arc::new(JobStore::new(3600));
+        let pool = WorkerPool::new(1, 1).unwrap();
+        let release = block_worker(&pool);
+        let queued = store
+            .clone()
+            .queue(&pool, WALLET, "wasm", POOL_ID, || Ok(committed(json!(1))))
+            .unwrap();
+
+        let rejected = store
+            .clone()
+            .queue(&pool, WALLET, "python", POOL_ID, || Ok(committed(json!(2))));
+        assert!(matches!(rejected, Err(ApiError::Busy)));
+        assert_eq!(store.in_flight(), (1, 0));
+
+        drop(release);
+        assert_eq!(wait_for(&store, &queued)["status"], "succeeded");
+    }
+
+    #[test]
+    fn job_cancelled_while_queued_is_skipped_by_the_worker() {
+        let store = Arc::new(JobStore::new(3600));
+        let pool = WorkerPool::new(1, 4).unwrap();
+        let release = block_worker(&pool);
+        let (ran, was_run) = channel();
+        let job_id = store
+            .clone()
+            .queue(&pool, WALLET, "wasm", POOL_ID, move || {
+                ran.send(()).unwrap();
+                Ok(committed(json!(1)))
+            })
+            .unwrap();
+
+        store.cancel(&job_id, WALLET).unwrap();
+        drop(release);
+        assert!(was_run.recv_timeout(Duration::from_millis(200)).is_err());
+        assert_eq!(
+            store.status(&job_id, WALLET).unwrap()["status"],
+            "cancelled"
+        );
+    }
+}
@@ -0,0 +1,310 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/keys.rs
+
+use crate::sealing::SealingPolicy;
+use anyhow::{anyhow, Result};
+use hkdf::Hkdf;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs::{read, write};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+/// Key provider used for all sealing operations, set once at startup
+static KEY_PROVIDER: OnceLock<Box<dyn KeyProvider>> = OnceLock::new();
+
+/// Source of the base keys that sealing keys are derived from, and of the
+/// evidence that attests the enclave holding them
+pub trait KeyProvider: Send + Sync {
+    /// Returns the 16-byte base key bound to the enclave identity named by `policy`
+    fn base_key(&self, policy: SealingPolicy) -> Result<[u8; 16]>;
+
+    /// Returns the MRENCLAVE of the running enclave
+    fn mrenclave(&self) -> Result<[u8; 32]>;
+
+    /// Returns an SGX quote carrying `report_data`, or `None` when the provider
+    /// cannot produce one
+    fn quote(&self, report_data: &[u8; 64]) -> Result<Option<Vec<u8>>>;
+
+    /// Returns which kind of provider this is
+    fn kind(&self) -> KeyProviderKind;
+}
+
+/// Reads keys from the pseudo-files Gramine exposes under `/dev/attestation`
+pub struct GramineKeyProvider;
+
+impl KeyProvider for GramineKeyProvider {
+    fn base_key(&self, policy: SealingPolicy) -> Result<[u8; 16]> {
+        let path = match policy {
+            SealingPolicy::MrEnclave => "/dev/attestation/keys/_sgx_mrenclave",
+            SealingPolicy::MrSigner => "/dev/attestation/keys/_sgx_mrsigner",
+        };
+        let base_key = read(path).map_err(|e| anyhow!("Failed to read key: {}", e))?;
+        base_key.as_slice().try_into().map_err(|_| {
+            anyhow!(
+                "Invalid key length: expected 16 bytes, got {}",
+                base_key.len()
+            )
+        })
+    }
+
+    fn mrenclave(&self) -> Result<[u8; 32]> {
+        let target_info = read("/dev/attestation/my_target_info")
+            .map_err(|e| anyhow!("Failed to read target info: {}", e))?;
+        if target_info.len() < 32 {
+            return Err(anyhow!("Invalid target info: too short"));
+        }
+        let mut mrenclave = [0u8; 32];
+        mrenclave.copy_from_slice(&target_info[..32]);
+        Ok(mrenclave)
+    }
+
+    fn quote(&self, report_data: &[u8; 64]) -> Result<Option<Vec<u8>>> {
+        // Gramine generates the quote over whatever was last written as report
+        // data, so another quote must not be requested in between
+        static QUOTE_LOCK: Mutex<()> = Mutex::new(());
+        let _guard = QUOTE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        write("/dev/attestation/user_report_data", report_data)
+            .map_err(|e| anyhow!("Failed to write report data: {}", e))?;
+        let quote =
+            read("/dev/attestation/quote").map_err(|e| anyhow!("Failed to read quote: {}", e))?;
+        Ok(Some(quote))
+    }
+
+    fn kind(&self) -> KeyProviderKind {
+        KeyProviderKind::Gramine
+    }
+}
+
+/// Development key provider for running the server outside an enclave.
+///
+/// Both base keys are derived from a single master key supplied by the operator,
+/// so anyone holding it can unseal the data. Never use this in production.
+pub struct DevKeyProvider {
+    master_key: Vec<u8>,
+    mrenclave: [u8; 32],
+}
+
+impl DevKeyProvider {
+    /// Creates a provider from a master key and the MRENCLAVE it should report.
+    ///
+    /// If no MRENCLAVE is given, one is derived from the master key.
+    pub fn new(master_key: &[u8], mrenclave: Option<[u8; 32]>) -> Result<Self> {
+        if master_key.len() < 16 {
+            return Err(anyhow!("Development master key must be at least 16 bytes"));
+        }
+        let mrenclave = mrenclave.unwrap_or_else(|| {
+            Sha256::new()
+                .chain_update(b"dev-mrenclave")
+                .chain_update(master_key)
+                .finalize()
+                .into()
+        });
+        Ok(Self {
+            master_key: master_key.to_vec(),
+            mrenclave,
+        })
+    }
+
+    /// Loads the master key from `DEV_SEALING_KEY` (hex) or the file named by
+    /// `DEV_SEALING_KEY_FILE`, and the optional `DEV_MRENCLAVE` (hex)
+    pub fn from_env() -> Result<Self> {
+        let master_key = if let Ok(key) = std::env::var("DEV_SEALING_KEY") {
+            hex::decode(key.trim()).map_err(|e| anyhow!("Invalid DEV_SEALING_KEY: {}", e))?
+        } else if let Ok(path) = std::env::var("DEV_SEALING_KEY_FILE") {
+            read(&path).map_err(|e| anyhow!("Failed to read key file {}: {}", path, e))?
+        } else {
+            return Err(anyhow!(
+                "Set DEV_SEALING_KEY or DEV_SEALING_KEY_FILE to use the development key provider"
+            ));
+        };
+
+        let mrenclave = match std::env::var("DEV_MRENCLAVE") {
+            Ok(value) => {
+                let bytes = hex::decode(value.trim())
+                    .map_err(|e| anyhow!("Invalid DEV_MRENCLAVE: {}", e))?;
+                Some(
+                    bytes
+                        .try_into()
+                        .map_err(|_| anyhow!("Invalid DEV_MRENCLAVE: expected 32 bytes"))?,
+                )
+            }
+            Err(_) => None,
+        };
+
+        Self::new(&master_key, mrenclave)
+    }
+}
+
+impl KeyProvider for DevKeyProvider {
+    fn base_key(&self, policy: SealingPolicy) -> Result<[u8; 16]> {
+        // The MRENCLAVE key also depends on the reported measurement, like the hardware key
+        let (salt, label): (Option<&[u8]>, &[u8]) = match policy {
+            SealingPolicy::MrEnclave => (Some(&self.mrenclave), b"dev-mrenclave-key"),
+            SealingPolicy::MrSigner => (None, b"dev-mrsigner-key"),
+        };
+        let hkdf = Hkdf::<Sha256>::new(salt, &self.master_key);
+        let mut base_key = [0u8; 16];
+        hkdf.expand(label, &mut base_key)
+            .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+        Ok(base_key)
+    }
+
+    fn mrenclave(&self) -> Result<[u8; 32]> {
+        Ok(self.mrenclave)
+    }
+
+    fn quote(&self, _report_data: &[u8; 64]) -> Result<Option<Vec<u8>>> {
+        // Outside an enclave there is nothing that could sign a quote
+        Ok(None)
+    }
+
+    fn kind(&self) -> KeyProviderKind {
+        KeyProviderKind::Dev
+    }
+}
+
+/// Key providers that can be selected at startup
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyProviderKind {
+    Gramine,
+    Dev,
+}
+
+impl KeyProviderKind {
+    /// Parses a key provider name (`gramine` or `dev`)
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "gramine" => Ok(KeyProviderKind::Gramine),
+            "dev" => Ok(KeyProviderKind::Dev),
+            other => Err(anyhow!("Unknown key provider: {}", other)),
+        }
+    }
+
+    /// Builds a provider of this kind
+    pub fn build(&self) -> Result<Box<dyn KeyProvider>> {
+        match self {
+            KeyProviderKind::Gramine => Ok(Box::new(GramineKeyProvider)),
+            KeyProviderKind::Dev => Ok(Box::new(DevKeyProvider::from_env()?)),
+        }
+    }
+}
+
+/// Sets the key provider used for sealing. Can only be called once.
+pub fn set_key_provider(provider: Box<dyn KeyProvider>) -> Result<()> {
+    KEY_PROVIDER
+        .set(provider)
+        .map_err(|_| anyhow!("Key provider has already been set"))
+}
+
+/// Returns the key provider used for sealing, defaulting to Gramine
+pub fn key_provider() -> &'static dyn KeyProvider {
+    KEY_PROVIDER
+        .get_or_init(|| Box::new(GramineKeyProvider))
+        .as_ref()
+}
+
+/// Seals with development keys in tests, which run outside an enclave
+#[cfg(test)]
+pub(crate) fn use_dev_keys() {
+    KEY_PROVIDER.get_or_init(|| {
+        Box::new(DevKeyProvider::new(b"ntc-test-master-key", None).expect("valid dev key"))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER_KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn dev_keys_are_deterministic() {
+        let first = DevKeyProvider::new(MASTER_KEY, None).unwrap();
+        let second = DevKeyProvider::new(MASTER_KEY, None).unwrap();
+        for policy in [SealingPolicy::MrEnclave, SealingPolicy::MrSigner] {
+            assert_eq!(
+                first.base_key(policy).unwrap(),
+                second.base_key(policy).unwrap()
+            );
+        }
+        assert_eq!(first.mrenclave().unwrap(), second.mrenclave().unwrap());
+    }
+
+    #[test]
+    fn dev_policies_derive_different_keys() {
+        let provider = DevKeyProvider::new(MASTER_KEY, None).unwrap();
+        assert_ne!(
+            provider.base_key(SealingPolicy::MrEnclave).unwrap(),
+            provider.base_key(SealingPolicy::MrSigner).unwrap()
+        );
+    }
+
+    #[test]
+    fn dev_mrenclave_key_follows_the_measurement() {
+        let old = DevKeyProvider::new(MASTER_KEY, Some([1; 32])).unwrap();
+        let new = DevKeyProvider::new(MASTER_KEY, Some([2; 32])).unwrap();
+
+        // Like hardware keys, only the MRSIGNER key survives a rebuild
+        assert_ne!(
+            old.base_key(SealingPolicy::MrEnclave).unwrap(),
+            new.base_key(SealingPolicy::MrEnclave).unwrap()
+        );
+        assert_eq!(
+            old.base_key(SealingPolicy::MrSigner).unwrap(),
+            new.base_key(SealingPolicy::MrSigner).unwrap()
+        );
+        assert_eq!(new.mrenclave().unwrap(), [2; 32]);
+    }
+
+    #[test]
+    fn dev_keys_depend_on_the_master_key() {
+        let provider = DevKeyProvider::new(MASTER_KEY, Some([1; 32])).unwrap();
+        let other =
+            DevKeyProvider::new(b"another master key, 32 bytes....", Some([1; 32])).unwrap();
+        assert_ne!(
+            provider.base_key(SealingPolicy::MrSigner).unwrap(),
+            other.base_key(SealingPolicy::MrSigner).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_short_dev_master_key() {
+        assert!(DevKeyProvider::new(b"too short", None).is_err());
+        assert!(DevKeyProvider::new(&MASTER_KEY[..16], None).is_ok());
+    }
+
+    #[test]
+    fn dev_provider_has_no_quote() {
+        let provider = DevKeyProvider::new(MASTER_KEY, None).unwrap();
+        assert!(provider.quote(&[0; 64]).unwrap().is_none());
+    }
+
+    #[test]
+    fn parses_provider_names() {
+        assert_eq!(
+            KeyProviderKind::from_name("gramine").unwrap(),
+            KeyProviderKind::Gramine
+        );
+        assert_eq!(
+            KeyProviderKind::from_name("DEV").unwrap(),
+            KeyProviderKind::Dev
+        );
+        assert!(KeyProviderKind::from_name("tpm").is_err());
+    }
+}
@@ -0,0 +1,403 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/ledger.rs
+
+use crate::sealing::{migrate_sealed_data, seal_data, unseal_data};
+use anyhow::{anyhow, Result};
+use drt_verify::DrtRedeemed;
+use sealed_store::write_atomic;
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Record of a DRT redemption that has already been used for a request
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ConsumedRedemption {
+    pub pool: String,
+    pub drt_type: String,
+    pub execution_type: String,
+    pub redeemer: String,
+    pub consumed_at: u64, // Unix timestamp (seconds) of the request that used it
+}
+
+/// Contents of the sealed ledger file
+#[derive(Serialize, Deserialize)]
+struct LedgerState {
+    watermark: i64, // On-chain Unix time at or before which no redemption is accepted
+    consumed: BTreeMap<String, ConsumedRedemption>,
+}
+
+/// Sealed, persistent ledger of consumed `redeem_drt` transaction signatures.
+///
+/// Each on-chain redemption pays for exactly one request. The ledger is held in
+/// memory behind a mutex and re-sealed to disk on every change, so a signature
+/// is either recorded durably or not at all. The sealed ledger is authoritative:
+/// a redemption it has no record of can be used, also after a restart.
+///
+/// Sealing cannot show that the file on disk is the latest one: the host can put
+/// back an older ledger, or none at all, and restart the enclave. So the ledger
+/// also seals a watermark, an on-chain time at or before which it accepts no
+/// redemption. It trails the chain by `max_age` seconds, moving up when the
+/// ledger is opened and whenever a redemption is used, and never moves down. A
+/// redemption must thus be used within `max_age` seconds of being made, and a
+/// ledger put back by the host can only forget redemptions made within
+/// `max_age` seconds of the restart.
+pub struct RedemptionLedger {
+    path: PathBuf,
+    max_age: i64, // Seconds after its on-chain time that a redemption can be used
+    state: Mutex<LedgerState>,
+}
+
+impl RedemptionLedger {
+    /// Opens the ledger at `path`, unsealing any previously consumed redemptions.
+    ///
+    /// `chain_time` is the on-chain time of the latest finalized block, and
+    /// `max_age` how long after it was made a redemption can be used.
+    pub fn open<P: AsRef<Path>>(path: P, chain_time: i64, max_age: u64) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let max_age = i64::try_from(max_age).map_err(|_| anyhow!("Redemption age too large"))?;
+        let watermark = chain_time.saturating_sub(max_age);
+
+        let state = if path.is_file() {
+            let mut sealed_data =
+                fs::read(&path).map_err(|e| anyhow!("Failed to read redemption ledger: {}", e))?;
+
+            // Bring a ledger sealed in an older format or under another policy up to date
+            if let Some(migrated) = migrate_sealed_data(&sealed_data, "")? {
+                Self::write(&path, &migrated)?;
+                sealed_data = migrated;
+            }
+
+            let ledger = unseal_data(&sealed_data, "")?;
+            match serde_json::from_value::<LedgerState>(ledger.clone()) {
+                Ok(mut state) => {
+                    state.watermark = state.watermark.max(watermark);
+                    state
+                }
+                // A ledger written before watermarks were sealed was only
+                // trusted for redemptions made after it was opened
+                Err(_) => LedgerState {
+                    watermark: chain_time,
+                    consumed: serde_json::from_value(ledger)
+                        .map_err(|e| anyhow!("Failed to parse redemption ledger: {}", e))?,
+                },
+            }
+        } else {
+            LedgerState {
+                watermark,
+                consumed: BTreeMap::new(),
+            }
+        };
+
+        let ledger = Self {
+            path,
+            max_age,
+            state: Mutex::new(state),
+        };
+        // Seal the watermark, which may have moved up
+        {
+            let state = ledger.lock()?;
+            ledger.persist(&state)?;
+        }
+        Ok(ledger)
+    }
+
+    /// On-chain time at or before which no redemption is accepted
+    pub fn watermark(&self) -> Result<i64> {
+        Ok(self.lock()?.watermark)
+    }
+
+    /// Marks a redemption as used, failing if it has been used before
+    pub fn consume(&self, signature: &str, redemption: &DrtRedeemed) -> Result<()> {
+        let mut state = self.lock()?;
+
+        if state.consumed.contains_key(signature) {
+            return Err(anyhow!("Redemption {} has already been used", signature));
+        }
+        if redemption.timestamp <= state.watermark {
+            return Err(anyhow!(
+                "Redemption {} is too old for the ledger to vouch for it",
+                signature
+            ));
+        }
+
+        let consumed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        state.consumed.insert(
+            signature.to_string(),
+            ConsumedRedemption {
+                pool: redemption.pool.clone(),
+                drt_type: redemption.drt_type.clone(),
+                execution_type: redemption.execution_type.clone(),
+                redeemer: redemption.redeemer.clone(),
+                consumed_at,
+            },
+        );
+        // The redemption was made on-chain no later than now, so the watermark
+        // can follow it
+        let watermark = state.watermark;
+        state.watermark = watermark.max(redemption.timestamp.saturating_sub(self.max_age));
+
+        // Only keep the entry if it was persisted, so a failed write can be retried
+        if let Err(e) = self.persist(&state) {
+            state.consumed.remove(signature);
+            state.watermark = watermark;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Whether a redemption was made late enough for this ledger to vouch that
+    /// it has not been used
+    pub fn accepts(&self, redemption: &DrtRedeemed) -> Result<bool> {
+        Ok(redemption.timestamp > self.lock()?.watermark)
+    }
+
+    /// Forgets that a redemption was used, after the request it paid for was undone
+    pub fn release(&self, signature: &str) -> Result<()> {
+        let mut state = self.lock()?;
+
+        let Some(redemption) = state.consumed.remove(signature) else {
+            return Ok(());
+        };
+
+        // Keep the entry if its removal could not be persisted, so it is never spent twice
+        if let Err(e) = self.persist(&state) {
+            state.consumed.insert(signature.to_string(), redemption);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Returns the consumption record for a redemption, if it has been used
+    pub fn status(&self, signature: &str) -> Result<Option<ConsumedRedemption>> {
+        Ok(self.lock()?.consumed.get(signature).cloned())
+    }
+
+    /// Returns a copy of every consumed redemption, keyed by transaction signature
+    pub fn snapshot(&self) -> Result<BTreeMap<String, ConsumedRedemption>> {
+        Ok(self.lock()?.consumed.clone())
+    }
+
+    /// Adds redemptions consumed by another enclave, keeping existing records.
+    ///
+    /// Returns the number of redemptions that were not already in the ledger.
+    pub fn merge(&self, entries: BTreeMap<String, ConsumedRedemption>) -> Result<usize> {
+        let mut state = self.lock()?;
+
+        let previous = state.consumed.clone();
+        let mut added = 0;
+        for (signature, redemption) in entries {
+            if let Entry::Vacant(entry) = state.consumed.entry(signature) {
+                entry.insert(redemption);
+                added += 1;
+            }
+        }
+
+        if added > 0 {
+            if let Err(e) = self.persist(&state) {
+                state.consumed = previous;
+                return Err(e);
+            }
+        }
+        Ok(added)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, LedgerState>> {
+        self.state
+            .lock()
+            .map_err(|_| anyhow!("Redemption ledger lock poisoned"))
+    }
+
+    /// Seals the ledger and writes it to disk
+    fn persist(&self, state: &LedgerState) -> Result<()> {
+        let ledger = serde_json::to_value(state)
+            .map_err(|e| anyhow!("Failed to serialize redemption ledger: {}", e))?;
+        let sealed_data = seal_data(&ledger, "")?;
+        Self::write(&self.path, &sealed_data)
+    }
+
+    /// Atomically writes a sealed ledger to disk
+    fn write(path: &Path, sealed_data: &[u8]) -> Result<()> {
+        write_atomic(path, sealed_data)
+            .map_err(|e| anyhow!("Failed to write redemption ledger: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::use_dev_keys;
+    use crate::testing::ScratchDir;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    const MAX_AGE: u64 = 3600;
+
+    fn open(path: &Path, chain_time: i64) -> RedemptionLedger {
+        use_dev_keys();
+        RedemptionLedger::open(path, chain_time, MAX_AGE).unwrap()
+    }
+
+    fn redemption(timestamp: i64) -> DrtRedeemed {
+        DrtRedeemed {
+            pool: "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU".to_string(),
+            drt_type: "append".to_string(),
+            execution_type: "append".to_string(),
+            redeemer: "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM".to_string(),
+            github_url: None,
+            code_hash: None,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn concurrent_requests_consume_a_redemption_once() {
+        let dir = ScratchDir::new();
+        let ledger = Arc::new(open(&dir.0.join("redemptions"), 10_000));
+        let threads = 8;
+        let barrier = Arc::new(Barrier::new(threads));
+
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                let ledger = ledger.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    ledger.consume("signature", &redemption(10_000)).is_ok()
+                })
+            })
+            .collect();
+        let consumed = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .filter(|&consumed| consumed)
+            .count();
+
+        assert_eq!(consumed, 1);
+        assert!(ledger.status("signature").unwrap().is_some());
+    }
+
+    #[test]
+    fn released_redemption_can_be_used_again() {
+        let dir = ScratchDir::new();
+        let path = dir.0.join("redemptions");
+        let ledger = open(&path, 10_000);
+
+        ledger.consume("signature", &redemption(10_000)).unwrap();
+        ledger.release("signature").unwrap();
+        assert!(ledger.status("signature").unwrap().is_none());
+
+        // The release is persisted too
+        drop(ledger);
+        let ledger = open(&path, 10_000);
+        assert!(ledger.status("signature").unwrap().is_none());
+        ledger.consume("signature", &redemption(10_000)).unwrap();
+        assert!(ledger.consume("signature", &redemption(10_000)).is_err());
+    }
+
+    #[test]
+    fn reopened_ledger_keeps_used_and_accepts_unused_redemptions() {
+        let dir = ScratchDir::new();
+        let path = dir.0.join("redemptions");
+        let ledger = open(&path, 10_000);
+        ledger.consume("used", &redemption(9_990)).unwrap();
+        drop(ledger);
+
+        // Restart a little later: the redemption made before the restart but
+        // not used yet is still good, the used one is not
+        let ledger = open(&path, 10_100);
+        assert!(ledger.status("used").unwrap().is_some());
+        assert!(ledger.consume("used", &redemption(9_990)).is_err());
+        assert!(ledger.accepts(&redemption(9_995)).unwrap());
+        ledger.consume("unused", &redemption(9_995)).unwrap();
+    }
+
+    #[test]
+    fn watermark_trails_the_chain_and_never_moves_down() {
+        let dir = ScratchDir::new();
+        let path = dir.0.join("redemptions");
+        let ledger = open(&path, 10_000);
+        assert_eq!(ledger.watermark().unwrap(), 10_000 - MAX_AGE as i64);
+
+        // A redemption too old for the ledger to vouch for is refused
+        assert!(!ledger
+            .accepts(&redemption(10_000 - MAX_AGE as i64))
+            .unwrap());
+        assert!(ledger.consume("old", &redemption(5_000)).is_err());
+
+        // Using a redemption shows the chain has moved on
+        ledger.consume("new", &redemption(20_000)).unwrap();
+        assert_eq!(ledger.watermark().unwrap(), 20_000 - MAX_AGE as i64);
+        drop(ledger);
+
+        // An earlier chain time on restart does not lower the sealed watermark
+        let ledger = open(&path, 10_000);
+        assert_eq!(ledger.watermark().unwrap(), 20_000 - MAX_AGE as i64);
+    }
+
+    #[test]
+    fn ledger_put_back_by_the_host_only_forgets_recent_redemptions() {
+        let dir = ScratchDir::new();
+        let path = dir.0.join("redemptions");
+        let ledger = open(&path, 10_000);
+        let older = fs::read(&path).unwrap();
+        ledger.consume("early", &redemption(10_000)).unwrap();
+        ledger.consume("late", &redemption(20_000)).unwrap();
+        drop(ledger);
+
+        // The host restores the ledger written before either was used
+        fs::write(&path, older).unwrap();
+        let ledger = open(&path, 20_100);
+        assert!(ledger.consume("early", &redemption(10_000)).is_err());
+        assert!(ledger.accepts(&redemption(20_000)).unwrap());
+
+        // A ledger that was removed starts from the chain time alone
+        fs::remove_file(&path).unwrap();
+        let ledger = open(&path, 20_100);
+        assert!(ledger.consume("early", &redemption(10_000)).is_err());
+    }
+
+    #[test]
+    fn ledger_without_a_watermark_only_accepts_new_redemptions() {
+        let dir = ScratchDir::new();
+        let path = dir.0.join("redemptions");
+        use_dev_keys();
+        let consumed = serde_json::json!({
+            "used": {
+                "pool": "pool",
+                "drt_type": "append",
+                "execution_type": "append",
+                "redeemer": "wallet",
+                "consumed_at": 1,
+            }
+        });
+        fs::write(&path, seal_data(&consumed, "").unwrap()).unwrap();
+
+        let ledger = open(&path, 10_000);
+        assert!(ledger.status("used").unwrap().is_some());
+        assert_eq!(ledger.watermark().unwrap(), 10_000);
+        assert!(!ledger.accepts(&redemption(9_999)).unwrap());
+        assert!(ledger.accepts(&redemption(10_001)).unwrap());
+    }
+}
@@ -0,0 +1,515 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/logging.rs
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fmt;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Builds with debug endpoints may log anything; all others redact
+const REDACT: bool = !cfg!(feature = "debug-endpoints");
+
+/// Fields whose values are never logged, as they may carry pool contents,
+/// results or keys
+const SENSITIVE_FIELDS: &[&str] = &[
+    "data",
+    "result",
+    "output",
+    "detail",
+    "body",
+    "key",
+    "secret",
+    "plaintext",
+];
+
+/// Longest value logged for any other field
+const MAX_VALUE_CHARS: usize = 512;
+
+const REDACTED: &str = "[redacted]";
+
+/// Formats that log lines can be written in
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    /// Parses a log format name (`text` or `json`)
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(anyhow!("Unknown log format: {}", other)),
+        }
+    }
+}
+
+/// Installs the global logger, writing to stderr.
+///
+/// Every field goes through the redaction in `redact`, so pool contents,
+/// results and keys never reach the host-visible log, whatever a call site
+/// passes in.
+pub fn init(level: &str, format: LogFormat) -> Result<()> {
+    let json = format == LogFormat::Json;
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_new(level).map_err(|e| anyhow!("Invalid log level: {}", e))?,
+        )
+        .fmt_fields(RedactingFields { json })
+        .event_format(RedactingFormat { json })
+        .with_writer(std::io::stderr)
+        .try_init()
+        .map_err(|e| anyhow!("Failed to set up logging: {}", e))
+}
+
+/// Returns the value to log for a field
+fn redact(field: &str, value: Value) -> Value {
+    if !REDACT {
+        return value;
+    }
+    if SENSITIVE_FIELDS.contains(&field) {
+        return Value::String(REDACTED.to_string());
+    }
+    match value {
+        Value::String(text) => Value::String(truncate(scrub(&text))),
+        other => other,
+    }
+}
+
+/// Replaces values that error messages can echo from the data they failed on
+/// with a placeholder: anything quoted, JSON objects and arrays, numbers, and
+/// the bare word following "value", as in "Error while parsing value alice".
+fn scrub(text: &str) -> String {
+    scrub_bare_values(&scrub_quoted(text))
+}
+
+/// Replaces quoted text and JSON objects and arrays with a placeholder. A single
+/// quote only counts as one at the edge of a word, so apostrophes are kept.
+fn scrub_quoted(text: &str) -> String {
+    let mut scrubbed = String::with_capacity(text.len());
+    let mut depth = 0usize; // Nesting of the JSON value being skipped
+    let mut quote = None; // Quote character of the string being skipped
+    let mut escaped = false;
+    let mut chars = text.chars().peekable();
+    let mut previous = None;
+
+    while let Some(c) = chars.next() {
+        let at_word_edge = |c: Option<&char>| !c.is_some_and(|c| c.is_alphanumeric());
+        if let Some(open) = quote {
+            match (escaped, c) {
+                (true, _) => escaped = false,
+                (false, '\\') if open != '\'' => escaped = true,
+                // A single quote inside a word, as in "O'Brien", does not close it
+                (false, c) if c == open && (c != '\'' || at_word_edge(chars.peek())) => {
+                    quote = None
+                }
+                _ => {}
+            }
+            previous = Some(c);
+            continue;
+        }
+        match c {
+            '"' | '`' => {
+                if depth == 0 {
+                    scrubbed.push_str(REDACTED);
+                }
+                quote = Some(c);
+            }
+            '\'' if at_word_edge(previous.as_ref()) => {
+                if depth == 0 {
+                    scrubbed.push_str(REDACTED);
+                }
+                quote = Some(c);
+            }
+            '{' | '[' => {
+                if depth == 0 {
+                    scrubbed.push_str(REDACTED);
+                }
+                depth += 1;
+            }
+            '}' | ']' if depth > 0 => depth -= 1,
+            _ if depth == 0 => scrubbed.push(c),
+            _ => {}
+        }
+        previous = Some(c);
+    }
+    scrubbed
+}
+
+/// Replaces numbers, and the word after "value" or "values", with a placeholder
+fn scrub_bare_values(text: &str) -> String {
+    // Words that can follow "value" in a message without being one
+    const CONNECTIVES: &[&str] = &["of", "for", "in", "at", "is", "to", "was", "must", "does"];
+
+    let mut after_value = false;
+    let words: Vec<String> = text
+        .split(' ')
+        .map(|word| {
+            let core = word
+                .trim_end_matches([',', '.', ';', ':', ')'])
+                .trim_start_matches('(');
+            let lowercase = core.to_ascii_lowercase();
+            let is_number = core.chars().any(|c| c.is_ascii_digit()) && core.parse::<f64>().is_ok();
+            let is_value = after_value && !CONNECTIVES.contains(&lowercase.as_str());
+            after_value = matches!(lowercase.as_str(), "value" | "values");
+
+            if !core.is_empty() && core != REDACTED && (is_number || is_value) {
+                word.replacen(core, REDACTED, 1)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect();
+    words.join(" ")
+}
+
+fn truncate(text: String) -> String {
+    match text.char_indices().nth(MAX_VALUE_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+/// Collects the fields of an event or span, redacted
+#[derive(Default)]
+struct FieldVisitor {
+    fields: Map<String, Value>,
+}
+
+impl FieldVisitor {
+    fn record(&mut self, field: &Field, value: Value) {
+        let value = redact(field.name(), value);
+        self.fields.insert(field.name().to_string(), value);
+    }
+
+    /// Writes the fields other than the message as `name=value` pairs
+    fn write_text(&self, writer: &mut Writer<'_>) -> fmt::Result {
+        let mut first = true;
+        for (name, value) in self.fields.iter().filter(|(name, _)| *name != "message") {
+            if !first {
+                writer.write_char(' ')?;
+            }
+            first = false;
+            match value {
+                Value::String(text) => write!(writer, "{}={}", name, text)?,
+                other => write!(writer, "{}={}", name, other)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, Value::String(format!("{:?}", value)));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, Value::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record(field, Value::from(value));
+    }
+}
+
+/// Formats span fields, redacted, as `name=value` pairs or a JSON object
+struct RedactingFields {
+    json: bool,
+}
+
+impl<'writer> FormatFields<'writer> for RedactingFields {
+    fn format_fields<R: RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> fmt::Result {
+        let mut visitor = FieldVisitor::default();
+        fields.record(&mut visitor);
+        if self.json {
+            write!(writer, "{}", Value::Object(visitor.fields))
+        } else {
+            visitor.write_text(&mut writer)
+        }
+    }
+}
+
+/// Formats events as text or JSON lines, with the spans they happened in
+struct RedactingFormat {
+    json: bool,
+}
+
+impl<S, N> FormatEvent<S, N> for RedactingFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+
+        let mut timestamp = String::new();
+        SystemTime.format_time(&mut Writer::new(&mut timestamp))?;
+
+        // Span fields were already redacted when they were recorded
+        let mut spans = Vec::new();
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let extensions = span.extensions();
+                let fields = extensions
+                    .get::<FormattedFields<N>>()
+                    .map(|fields| fields.fields.clone())
+                    .unwrap_or_default();
+                spans.push((span.name(), fields));
+            }
+        }
+
+        if self.json {
+            let spans: Vec<Value> = spans
+                .into_iter()
+                .map(|(name, fields)| {
+                    let mut span = match serde_json::from_str(&fields) {
+                        Ok(Value::Object(fields)) => fields,
+                        _ => Map::new(),
+                    };
+                    span.insert("name".to_string(), Value::from(name));
+                    Value::Object(span)
+                })
+                .collect();
+            let message = visitor.fields.remove("message").unwrap_or(Value::Null);
+
+            let mut line = Map::new();
+            line.insert("timestamp".to_string(), Value::from(timestamp));
+            line.insert("level".to_string(), Value::from(metadata.level().as_str()));
+            line.insert("target".to_string(), Value::from(metadata.target()));
+            line.insert("message".to_string(), message);
+            line.insert("fields".to_string(), Value::Object(visitor.fields));
+            line.insert("spans".to_string(), Value::Array(spans));
+            writeln!(writer, "{}", Value::Object(line))
+        } else {
+            write!(writer, "{} {:>5} ", timestamp, metadata.level())?;
+            for (name, fields) in &spans {
+                if fields.is_empty() {
+                    write!(writer, "{}:", name)?;
+                } else {
+                    write!(writer, "{}{{{}}}:", name, fields)?;
+                }
+            }
+            if !spans.is_empty() {
+                writer.write_char(' ')?;
+            }
+            write!(writer, "{}: ", metadata.target())?;
+            if let Some(Value::String(message)) = visitor.fields.get("message") {
+                write!(writer, "{}", message)?;
+                if visitor.fields.len() > 1 {
+                    writer.write_char(' ')?;
+                }
+            }
+            visitor.write_text(&mut writer)?;
+            writeln!(writer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use tracing::{error, info, info_span, warn};
+
+    /// Log output collected in memory
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Returns what `emit` logs through the redacting formatter, in both formats
+    fn logged(emit: impl Fn()) -> Vec<String> {
+        [false, true]
+            .into_iter()
+            .map(|json| {
+                let capture = Capture::default();
+                let writer = capture.clone();
+                let subscriber = tracing_subscriber::fmt()
+                    .with_max_level(tracing::Level::TRACE)
+                    .fmt_fields(RedactingFields { json })
+                    .event_format(RedactingFormat { json })
+                    .with_writer(move || writer.clone())
+                    .finish();
+                tracing::subscriber::with_default(subscriber, &emit);
+                let output = capture.0.lock().unwrap().clone();
+                String::from_utf8(output).unwrap()
+            })
+            .collect()
+    }
+
+    fn assert_clean(output: &[String], secrets: &[&str]) {
+        for output in output {
+            assert!(output.contains(REDACTED), "{}", output);
+            for secret in secrets {
+                assert!(!output.contains(secret), "{} leaked in {}", secret, output);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg_attr(feature = "debug-endpoints", ignore)]
+    fn sensitive_fields_are_never_logged() {
+        let output = logged(|| {
+            info!(
+                data = %"alice,120000",
+                result = ?serde_json::json!({"mean": 4521.5}),
+                output = "bob",
+                detail = "carol",
+                body = "dave",
+                key = "c2VjcmV0LWtleQ",
+                secret = "hunter2",
+                plaintext = "erin",
+                pool_id = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU",
+                "Handled request"
+            );
+        });
+        assert_clean(
+            &output,
+            &[
+                "alice",
+                "4521",
+                "bob",
+                "carol",
+                "dave",
+                "c2VjcmV0LWtleQ",
+                "hunter2",
+                "erin",
+            ],
+        );
+        for output in &output {
+            assert!(output.contains("Handled request"));
+            assert!(output.contains("7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU"));
+        }
+    }
+
+    #[test]
+    #[cfg_attr(feature = "debug-endpoints", ignore)]
+    fn pool_data_in_error_messages_is_not_logged() {
+        let errors = [
+            "Invalid CSV: Error while parsing value alice for column 0 at line 2",
+            "Cast error: Cannot cast string 'bob' to value of Int64 type",
+            "Failed to parse rows: invalid value: integer `120000`, expected u8",
+            "Invalid JSON in row 3: invalid type: string \"carol\", expected f64",
+            "Unexpected field 'salary' at record 7",
+            "{\"name\": \"dave\"} is not of type \"array\"",
+            "Values [\"erin\", 52000] do not match",
+            "Column 'O'Brien' is not in the data pool",
+        ];
+        let output = logged(|| {
+            for message in errors {
+                let e = anyhow!(message);
+                warn!(error = %e, "Failed to append data");
+                error!("Failed to convert data pool: {}", e);
+            }
+        });
+        assert_clean(
+            &output,
+            &[
+                "alice", "bob", "120000", "carol", "salary", "dave", "erin", "52000", "Brien",
+            ],
+        );
+        for output in &output {
+            assert!(output.contains("Failed to append data"));
+            assert!(output.contains("Invalid CSV: Error while parsing value"));
+        }
+    }
+
+    #[test]
+    #[cfg_attr(feature = "debug-endpoints", ignore)]
+    fn span_fields_are_redacted() {
+        let output = logged(|| {
+            let span = info_span!("request", body = "alice", route = "/append_data");
+            let _entered = span.enter();
+            info!("Request finished");
+        });
+        assert_clean(&output, &["alice"]);
+        for output in &output {
+            assert!(output.contains("/append_data"));
+        }
+    }
+
+    #[test]
+    fn scrub_keeps_text_without_values() {
+        for text in [
+            "Data pool not found",
+            "Pool doesn't exist",
+            "Pool 7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU is sealed",
+            "Cannot cast string to value of Int64 type",
+        ] {
+            assert_eq!(scrub(text), text);
+        }
+    }
+
+    #[test]
+    fn scrub_redacts_quoted_and_bare_values() {
+        assert_eq!(
+            scrub("Parsing value alice for column 0 at line 2"),
+            "Parsing value [redacted] for column [redacted] at line [redacted]"
+        );
+        assert_eq!(
+            scrub("Got 'a b c' and `d`"),
+            "Got [redacted] and [redacted]"
+        );
+        assert_eq!(
+            scrub("Got -4.5e3, not 7."),
+            "Got [redacted], not [redacted]."
+        );
+        // An unterminated quote hides everything after it
+        assert_eq!(scrub("Field 'salary at 3"), "Field [redacted]");
+    }
+}
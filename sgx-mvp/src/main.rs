@@ -14,33 +14,88 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+extern crate drt_verify;
 extern crate github_download;
 extern crate json_append;
 extern crate python_rust_impl;
+extern crate sealed_store;
 extern crate wasmi_impl;
 
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
-use aes_gcm::aead::Aead;
-use aes_gcm::{Aes128Gcm, KeyInit, Nonce};
+mod attestation;
+mod audit;
+mod auth;
+mod config;
+mod encryption;
+mod errors;
+mod jobs;
+mod keys;
+mod ledger;
+mod logging;
+mod metrics;
+mod migration;
+mod pools;
+mod schema;
+mod sealing;
+#[cfg(test)]
+mod testing;
+mod workers;
+
+use actix_web::dev::Service;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use anyhow::{anyhow, Result};
+use arrow_array::RecordBatch;
+use attestation::{Attestation, DcapQuoteVerifier};
+use audit::{
+    audit_signing_key, hash_bytes, AuditEvent, AuditLog, AuditOperation, CommittedResult,
+    MAX_EXPORT_ENTRIES, RESULT_SALT_HEADER,
+};
+use auth::{RequestAuthenticator, SignedJson};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use config::{Config, StoreBackend};
+use drt_verify::{DrtRedeemed, ExpectedRedemption, RedemptionVerifier};
+use encryption::{encrypt_result, ResultRecipient};
+use errors::{
+    finish_response, json_error, new_request_id, python_error, query_error, wasm_error, ApiError,
+};
 use github_download::{verify_and_download_python_github, verify_and_download_wasm};
-use hkdf::Hkdf;
-use json_append::append_json;
+use jobs::JobStore;
+use json_append::{
+    append_record_batch, appended_json_len, csv_to_columns, parquet_to_columns,
+    record_batch_to_json, row_count, to_columnar, write_columns, write_rows, ColumnPolicy,
+    CsvOptions,
+};
+use keys::{set_key_provider, KeyProviderKind};
+use ledger::RedemptionLedger;
+use metrics::metrics;
+use migration::{
+    export_bundle, import_bundle, parse_approved_predecessors, MigrationBundle, MigrationKey,
+    TargetKey,
+};
+use pools::{PoolContents, PoolRegistry};
 use python_rust_impl::run_python;
-use rand::RngCore;
 use rustls::pki_types::CertificateDer;
 use rustls::server::ServerConfig;
+use schema::PoolSchema;
+use sealed_store::{IpfsStore, LocalStore, S3Store, SealedStore};
+use sealing::{
+    migrate_sealed_pool, seal_pool, set_sealing_policy, unseal_legacy_pool, unseal_pool,
+};
 use serde::Deserialize;
-use serde_json::Value;
-use sha2::Sha256;
-use std::fs::{read, File};
-use std::io::{BufReader, Write};
+use serde_json::{json, Value};
+use std::cell::Cell;
+use std::fs::File;
+use std::io::BufReader;
 use std::path::Path;
-use wasmi_impl::wasm_execution;
+use std::sync::PoisonError;
+use std::time::Instant;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+use wasmi_impl::{wasm_execution, WasmLimits};
+use workers::WorkerPool;
 
 /// Loads certificates from a PEM file generated by gramine-ratls
 fn load_ratls_certificates(cert_path: &str) -> Result<Vec<CertificateDer<'static>>> {
-    println!("[+] Loading RA-TLS certificate from: {}", cert_path);
+    info!(path = cert_path, "Loading RA-TLS certificate");
 
     // Read the entire file content
     let cert_content = std::fs::read_to_string(cert_path)
@@ -64,11 +119,11 @@ fn load_ratls_certificates(cert_path: &str) -> Result<Vec<CertificateDer<'static
     let certs: Vec<_> = rustls_pemfile::certs(&mut reader)
         .filter_map(|result| match result {
             Ok(cert) => {
-                println!("[+] Successfully parsed certificate");
+                debug!("Parsed certificate");
                 Some(cert)
             }
             Err(e) => {
-                eprintln!("[!] Error parsing certificate: {}", e);
+                warn!(error = %e, "Skipping certificate that failed to parse");
                 None
             }
         })
@@ -78,13 +133,13 @@ fn load_ratls_certificates(cert_path: &str) -> Result<Vec<CertificateDer<'static
         return Err(anyhow!("No valid certificates found in {}", cert_path));
     }
 
-    println!("[+] Loaded {} certificate(s)", certs.len());
+    info!(count = certs.len(), "Loaded certificates");
     Ok(certs)
 }
 
 /// Loads private key from a PEM file generated by gramine-ratls
 fn load_ratls_private_key(key_path: &str) -> Result<rustls::pki_types::PrivateKeyDer<'static>> {
-    println!("[+] Loading RA-TLS private key from: {}", key_path);
+    info!(path = key_path, "Loading RA-TLS private key");
 
     // Open the key file
     let key_file = File::open(key_path)
@@ -97,7 +152,7 @@ fn load_ratls_private_key(key_path: &str) -> Result<rustls::pki_types::PrivateKe
         .transpose()
         .map_err(|e| anyhow!("Failed to parse EC private key: {}", e))?
     {
-        println!("[+] Found EC private key");
+        debug!("Found EC private key");
         return Ok(rustls::pki_types::PrivateKeyDer::Sec1(key));
     }
 
@@ -119,7 +174,7 @@ fn read_pem_file(path: &str) -> Result<String> {
 
 /// Verifies that the PEM files exist and contain valid PEM data
 pub fn verify_ratls_files(cert_path: &str, key_path: &str) -> Result<()> {
-    println!("[+] Verifying RA-TLS PEM files");
+    debug!("Verifying RA-TLS PEM files");
 
     // Check if files exist
     if !Path::new(cert_path).exists() {
@@ -131,31 +186,35 @@ pub fn verify_ratls_files(cert_path: &str, key_path: &str) -> Result<()> {
 
     // Validate PEM format with detailed error reporting
     if let Err(e) = read_pem_file(cert_path) {
-        eprintln!("[!] Certificate validation failed:");
-        eprintln!("[!] Error: {}", e);
-        eprintln!("[!] File path: {}", cert_path);
+        error!(error = %e, path = cert_path, "Certificate validation failed");
         return Err(anyhow!("Certificate validation failed: {}", e));
     }
 
     if let Err(e) = read_pem_file(key_path) {
-        eprintln!("[!] Private key validation failed:");
-        eprintln!("[!] Error: {}", e);
-        eprintln!("[!] File path: {}", key_path);
+        error!(error = %e, path = key_path, "Private key validation failed");
         return Err(anyhow!("Private key validation failed: {}", e));
     }
 
-    println!("[+] RA-TLS PEM files verified successfully");
+    debug!("RA-TLS PEM files verified");
     Ok(())
 }
 
-/// Configures TLS with Gramine RA-TLS certificates
-pub fn configure_ratls(cert_path: &str, key_path: &str) -> Result<ServerConfig> {
+/// Configures TLS with Gramine RA-TLS certificates, also returning the RA-TLS
+/// certificate the server presents
+pub fn configure_ratls(
+    cert_path: &str,
+    key_path: &str,
+) -> Result<(ServerConfig, CertificateDer<'static>)> {
     // First verify the PEM files
     verify_ratls_files(cert_path, key_path)?;
 
     // Load certificates and private key
     let certs = load_ratls_certificates(cert_path)?;
     let key = load_ratls_private_key(key_path)?;
+    let ratls_cert = certs
+        .first()
+        .cloned()
+        .ok_or_else(|| anyhow!("No certificates found in {}", cert_path))?;
 
     // Create server config
     let config = ServerConfig::builder()
@@ -163,8 +222,8 @@ pub fn configure_ratls(cert_path: &str, key_path: &str) -> Result<ServerConfig>
         .with_single_cert(certs, key)
         .map_err(|e| anyhow!("Failed to create TLS config: {}", e))?;
 
-    println!("[+] Successfully configured RA-TLS with PEM files");
-    Ok(config)
+    info!("Configured RA-TLS");
+    Ok((config, ratls_cert))
 }
 
 // Define a health check route
@@ -174,351 +233,1918 @@ async fn health_check() -> impl Responder {
         .body("Server is running")
 }
 
-/// Derives a new key using HKDF with a given base key, salt, and purpose.
-fn derive_key(base_key: &[u8], salt: &[u8]) -> Result<[u8; 16]> {
-    let hkdf = Hkdf::<Sha256>::new(Some(salt), base_key);
-    let mut derived_key = [0u8; 16];
-    hkdf.expand(b"sealing", &mut derived_key)
-        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
-    Ok(derived_key)
+/// Handler for the `attestation` API
+async fn attestation_handler(
+    worker_pool: web::Data<WorkerPool>,
+    attestation: web::Data<Attestation>,
+) -> Result<HttpResponse, ApiError> {
+    let evidence = worker_pool
+        .run(move || {
+            attestation.evidence().map_err(|e| {
+                error!(error = %e, "Failed to generate attestation evidence");
+                ApiError::Internal("Failed to generate attestation evidence")
+            })
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(evidence))
+}
+
+/// Handler for the `metrics` API
+async fn metrics_handler(jobs: web::Data<JobStore>) -> Result<HttpResponse, ApiError> {
+    let (queued, running) = jobs.in_flight();
+    metrics().set_jobs(queued, running);
+
+    let body = metrics().render().map_err(|e| {
+        error!(error = %e, "Failed to render metrics");
+        ApiError::Internal("Failed to render metrics")
+    })?;
+    Ok(HttpResponse::Ok()
+        .append_header(("Content-Type", "text/plain; version=0.0.4"))
+        .body(body))
+}
+
+/// Query parameters for the `audit_log` API
+#[derive(Deserialize)]
+struct AuditLogQuery {
+    from: Option<u64>,    // Index of the first entry to return, 0 if unset
+    limit: Option<usize>, // Most entries to return, capped at `MAX_EXPORT_ENTRIES`
+}
+
+/// Handler for the `audit_log` API
+async fn audit_log_handler(
+    worker_pool: web::Data<WorkerPool>,
+    audit: web::Data<AuditLog>,
+    attestation: web::Data<Attestation>,
+    query: web::Query<AuditLogQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let AuditLogQuery { from, limit } = query.into_inner();
+    let export = worker_pool
+        .run(move || {
+            let limit = limit.unwrap_or(MAX_EXPORT_ENTRIES);
+            let (entries, head) = audit.export(from.unwrap_or(0), limit).map_err(|e| {
+                error!(error = %e, "Failed to export audit log");
+                ApiError::Internal("Failed to export audit log")
+            })?;
+            // Lets auditors check offline that the head was signed inside the enclave
+            let evidence = attestation.evidence().map_err(|e| {
+                error!(error = %e, "Failed to generate attestation evidence");
+                ApiError::Internal("Failed to generate attestation evidence")
+            })?;
+            Ok(json!({
+                "entries": entries,
+                "head": head,
+                "attestation": evidence,
+            }))
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(export))
 }
 
-/// Generates a 16-byte random salt.
-fn generate_salt() -> [u8; 16] {
-    let mut salt = [0u8; 16];
-    rand::rngs::OsRng.fill_bytes(&mut salt);
-    salt
+/// Compiles the schema sealed with a data pool
+fn compile_schema(schema: &Value) -> Result<PoolSchema, ApiError> {
+    PoolSchema::compile(schema).map_err(|e| {
+        error!(error = %e, "Failed to compile data pool schema");
+        ApiError::Internal("Failed to compile data pool schema")
+    })
 }
 
-/// Reads the attestation key and derives the encryption key using a salt.
-fn read_and_derive_key(salt: &[u8]) -> Result<[u8; 16]> {
-    let base_key = read("/dev/attestation/keys/_sgx_mrenclave")
-        .map_err(|e| anyhow!("Failed to read key: {}", e))?;
-    if base_key.len() != 16 {
-        return Err(anyhow!(
-            "Invalid key length: expected 16 bytes, got {}",
-            base_key.len()
-        ));
+/// Checks that data satisfies the schema of a data pool
+fn check_schema(schema: &PoolSchema, data: &Value) -> Result<(), ApiError> {
+    let violations = schema.violations(data);
+    if violations.is_empty() {
+        return Ok(());
     }
-    derive_key(&base_key, salt)
+    warn!(
+        violations = violations.len(),
+        "Data does not satisfy the pool schema"
+    );
+    Err(ApiError::SchemaViolation(violations))
 }
 
-/// Encrypts and seals the data.
-fn seal_data(data: &Value, salt: Option<&[u8]>) -> Result<Vec<u8>> {
-    // Generate or use the provided salt
-    let salt = match salt {
-        Some(existing_salt) => existing_salt.to_vec(),
-        None => generate_salt().to_vec(),
+/// Checks that the value the column policy fills columns with satisfies the
+/// schema, by checking the data as it would be after an append that leaves out
+/// every column
+fn check_fill_value(
+    schema: &PoolSchema,
+    data: &Value,
+    policy: &ColumnPolicy,
+) -> Result<(), ApiError> {
+    let (Some(fill_value), Some(columns)) = (policy.fill_value(), data.as_object()) else {
+        return Ok(());
     };
+    let mut filled = columns.clone();
+    for values in filled.values_mut() {
+        if let Some(values) = values.as_array_mut() {
+            values.push(fill_value.clone());
+        }
+    }
 
-    // Derive the encryption key
-    let derived_key = read_and_derive_key(&salt)?;
-    let cipher = Aes128Gcm::new_from_slice(&derived_key)
-        .map_err(|e| anyhow!("Failed to initialize AES-GCM: {}", e))?;
-
-    // Generate a secure nonce (12 bytes as required by AES-GCM)
-    let mut nonce_bytes = [0u8; 12];
-    rand::rngs::OsRng.fill_bytes(&mut nonce_bytes);
-    let nonce = Nonce::from_slice(&nonce_bytes);
+    match schema.violations(&Value::Object(filled)).first() {
+        None => Ok(()),
+        Some(violation) => {
+            warn!(detail = %violation.message, "Rejected column policy fill value");
+            Err(ApiError::InvalidRequest(format!(
+                "Fill value of the column policy does not satisfy the schema: {}",
+                violation.message
+            )))
+        }
+    }
+}
 
-    // Serialize the data to JSON
-    let serialized_data =
-        serde_json::to_vec(data).map_err(|e| anyhow!("Failed to serialize JSON: {}", e))?;
+/// Format in which data is uploaded to a data pool
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum DataFormat {
+    /// Columns, records or NDJSON
+    #[default]
+    Json,
+    /// CSV text, read with the request's CSV options
+    Csv,
+    /// Base64-encoded Parquet file
+    Parquet,
+}
 
-    // Encrypt the serialized data
-    let ciphertext = cipher
-        .encrypt(nonce, serialized_data.as_ref())
-        .map_err(|e| anyhow!("Encryption failed: {}", e))?;
+/// Converts uploaded data to the columnar layout of data pools, with the given
+/// columns if the pool already has some. A Parquet file may decode to at most
+/// `max_bytes` of JSON.
+fn columnar_data(
+    data: Value,
+    format: DataFormat,
+    csv: &CsvOptions,
+    schema: Option<&Value>,
+    columns: Option<&[String]>,
+    policy: &ColumnPolicy,
+    max_bytes: usize,
+) -> Result<Value, ApiError> {
+    let decoded = match (format, data) {
+        (DataFormat::Json, data) => Ok(data),
+        (DataFormat::Csv, Value::String(text)) => csv_to_columns(&text, csv, schema),
+        (DataFormat::Parquet, Value::String(file)) => BASE64
+            .decode(file)
+            .map_err(|e| anyhow!("Parquet data is not valid base64: {}", e))
+            .and_then(|file| parquet_to_columns(file, max_bytes)),
+        (_, _) => Err(anyhow!("CSV and Parquet data must be sent as a string")),
+    };
+    decoded
+        .and_then(|data| to_columnar(data, columns, policy))
+        .map_err(|e| {
+            // Decoding errors can quote the uploaded data, which is logged as a
+            // redacted detail
+            warn!(detail = %e, "Failed to convert data to columns");
+            ApiError::InvalidData(e.to_string())
+        })
+}
 
-    // Combine salt, nonce, and ciphertext into a single Vec<u8>
-    let mut sealed_data = Vec::with_capacity(salt.len() + nonce_bytes.len() + ciphertext.len());
-    sealed_data.extend_from_slice(&salt);
-    sealed_data.extend_from_slice(&nonce_bytes);
-    sealed_data.extend_from_slice(&ciphertext);
+/// Checks that the requested pool ID is valid and that the pool exists
+fn check_pool_exists(registry: &PoolRegistry, pool_id: &str) -> Result<(), ApiError> {
+    match registry.exists(pool_id) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ApiError::PoolNotFound),
+        Err(e) => {
+            warn!(error = %e, "Failed to check data pool");
+            Err(ApiError::InvalidPoolId)
+        }
+    }
+}
 
-    Ok(sealed_data)
+/// Checks that a request is signed by the wallet that owns the pool on-chain
+fn check_pool_owner(
+    verifier: &RedemptionVerifier,
+    pool_id: &str,
+    wallet: &str,
+) -> Result<(), ApiError> {
+    // Look up the owner recorded in the pool account
+    let owner = verifier.fetch_pool_owner(pool_id).map_err(|e| {
+        error!(error = %e, "Failed to look up pool owner");
+        ApiError::NotPoolOwner
+    })?;
+    if owner != wallet {
+        warn!(wallet = %wallet, pool_id = %pool_id, "Wallet does not own pool");
+        return Err(ApiError::NotPoolOwner);
+    }
+    Ok(())
 }
 
-/// Saves data to a file.
-fn save_to_file(data: &[u8]) -> Result<()> {
-    let mut file =
-        File::create("/data/data_pool").map_err(|e| anyhow!("Failed to create file: {}", e))?;
-    file.write_all(data)
-        .map_err(|e| anyhow!("Failed to write to file: {}", e))?;
+/// Checks that a data pool, by the length of its data as columnar JSON, is
+/// within the configured size limit
+fn check_pool_size(config: &Config, data_len: usize) -> Result<(), ApiError> {
+    if data_len > config.limits.max_pool_bytes {
+        return Err(ApiError::PoolTooLarge);
+    }
     Ok(())
 }
 
-// TODO Update threading
-#[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<()> {
-    println!("[+] Enclave created successfully, starting server...");
+/// Verifies the DRT redemption paying for a request signed by `wallet`,
+/// without spending it yet
+fn verify_redemption(
+    verifier: &RedemptionVerifier,
+    ledger: &RedemptionLedger,
+    signature: &str,
+    expected: &ExpectedRedemption,
+    wallet: &str,
+) -> Result<DrtRedeemed, ApiError> {
+    let redemption = verifier.verify(signature, expected).map_err(|e| {
+        warn!(error = %e, "DRT redemption verification failed");
+        ApiError::RedemptionRejected
+    })?;
+
+    // Only the wallet that redeemed the DRT may spend it, so a leaked transaction
+    // signature is of no use to anyone else
+    if redemption.redeemer != wallet {
+        warn!(
+            redeemer = %redemption.redeemer,
+            wallet,
+            "DRT redeemed by a different wallet than the one signing the request"
+        );
+        return Err(ApiError::NotRedeemer);
+    }
+
+    // Redemptions older than the ledger's watermark may have been spent already
+    match ledger.accepts(&redemption) {
+        Ok(true) => {}
+        Ok(false) => {
+            warn!("DRT redemption is older than the redemption ledger's watermark");
+            return Err(ApiError::RedemptionRejected);
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to read redemption ledger");
+            return Err(ApiError::Internal("Failed to read redemption ledger"));
+        }
+    }
+
+    // Turn away a spent redemption before any work is done for it
+    match ledger.status(signature) {
+        Ok(None) => Ok(redemption),
+        Ok(Some(_)) => {
+            warn!("DRT redemption has already been used");
+            Err(ApiError::RedemptionUsed)
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to read redemption ledger");
+            Err(ApiError::Internal("Failed to read redemption ledger"))
+        }
+    }
+}
+
+/// Marks a verified redemption as used. Each redemption pays for a single request.
+fn spend_redemption(
+    ledger: &RedemptionLedger,
+    signature: &str,
+    redemption: &DrtRedeemed,
+) -> Result<(), ApiError> {
+    ledger.consume(signature, redemption).map_err(|e| {
+        warn!(error = %e, "Failed to consume DRT redemption");
+        ApiError::RedemptionUsed
+    })
+}
+
+/// Gives back a redemption whose request was undone, so it can be used again
+fn refund_redemption(ledger: &RedemptionLedger, signature: &str) {
+    if let Err(e) = ledger.release(signature) {
+        error!(error = %e, "Failed to refund DRT redemption");
+    }
+}
+
+/// Decodes the key the request wants its result encrypted to.
+///
+/// Called before the redemption is spent, so a bad key does not cost a DRT.
+fn result_recipient(
+    recipient: Option<&ResultRecipient>,
+) -> Result<Option<x25519_dalek::PublicKey>, ApiError> {
+    recipient
+        .map(|recipient| {
+            recipient
+                .public_key()
+                .map_err(|e| ApiError::InvalidRequest(e.to_string()))
+        })
+        .transpose()
+}
+
+/// Encrypts a computation result to the requested key, if any
+fn seal_result(
+    result: Value,
+    recipient: Option<&x25519_dalek::PublicKey>,
+) -> Result<Value, ApiError> {
+    match recipient {
+        Some(recipient) => encrypt_result(recipient, &result).map_err(|e| {
+            error!(error = %e, "Failed to encrypt result");
+            ApiError::Internal("Failed to encrypt result")
+        }),
+        None => Ok(result),
+    }
+}
+
+/// Records an operation in the audit log, returning the salt it commits to the
+/// result under, if there is one
+fn audit_record(audit: &AuditLog, event: &AuditEvent) -> Result<Option<String>, ApiError> {
+    audit.record(event).map_err(|e| {
+        error!(error = %e, "Failed to write audit log");
+        ApiError::Internal("Failed to write audit log")
+    })
+}
+
+/// Records a computation in the audit log, whatever its outcome. The result is
+/// only returned once the entry is on disk, so no computation goes unrecorded.
+///
+/// Returns the salt the entry commits to a successful result under.
+fn audit_execution(
+    audit: &AuditLog,
+    operation: AuditOperation,
+    pool_id: &str,
+    wallet: &str,
+    code_hash: &str,
+    data_version: &str,
+    result: &Result<Value, ApiError>,
+) -> Result<Option<String>, ApiError> {
+    let event = AuditEvent {
+        operation,
+        pool_id,
+        redeemer: Some(wallet),
+        code_hash: Some(code_hash),
+        data_version,
+        outcome: result.as_ref().map_or_else(ApiError::code, |_| "succeeded"),
+        result: result.as_ref().ok(),
+    };
+    audit_record(audit, &event)
+}
+
+/// Returns the result of a computation, with the salt the audit log commits to
+/// it under in a header
+fn result_response(result: CommittedResult) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    if let Some(salt) = result.salt {
+        response.insert_header((RESULT_SALT_HEADER, salt));
+    }
+    response.json(result.value)
+}
+
+/// Gives back the spent redemption of an execution that the enclave, not the
+/// executed code, failed, or whose result is withheld because it could not be
+/// audited
+fn settle_execution(
+    ledger: &RedemptionLedger,
+    signature: &str,
+    result: &Result<Value, ApiError>,
+    audited: &Result<Option<String>, ApiError>,
+) {
+    if audited.is_err() || matches!(result, Err(ApiError::Internal(_))) {
+        refund_redemption(ledger, signature);
+    }
+}
+
+/// Opens the store for sealed data pools selected in the configuration
+fn open_store(config: &Config) -> Result<Box<dyn SealedStore>> {
+    let s3 = &config.storage.s3;
+    let store: Box<dyn SealedStore> = match config.storage.backend {
+        StoreBackend::Local => Box::new(LocalStore::new(config.pools_dir())?),
+        StoreBackend::Ipfs => Box::new(IpfsStore::new(&config.storage.ipfs_api_url)?),
+        StoreBackend::S3 => Box::new(S3Store::new(
+            &s3.endpoint,
+            &s3.bucket,
+            &s3.prefix,
+            &s3.region,
+            s3.access_key_id.as_deref().unwrap_or_default(),
+            s3.secret_access_key.as_deref().unwrap_or_default(),
+        )?),
+    };
+    Ok(store)
+}
+
+/// Registers the routes of every endpoint that is not disabled in the configuration
+fn configure_routes(cfg: &mut web::ServiceConfig, config: &Config) {
+    let routes = [
+        ("/health", web::get().to(health_check)), // Health check route
+        ("/attestation", web::get().to(attestation_handler)), // Quote and measurements of the enclave
+        ("/metrics", web::get().to(metrics_handler)),         // Prometheus metrics
+        ("/audit_log", web::get().to(audit_log_handler)), // Signed record of operations on pools
+        ("/execute_python", web::post().to(execute_python_handler)), // Python execution route
+        ("/execute_wasm", web::post().to(execute_wasm_handler)), // WASM execution route
+        (
+            "/create_data_pool",
+            web::post().to(create_data_pool_handler),
+        ), // Create new data pool
+        #[cfg(feature = "debug-endpoints")]
+        ("/view_data", web::get().to(view_data_handler)), // View decrypted data as the pool owner
+        ("/append_data", web::post().to(append_data_handler)), // Append data into data pool
+        ("/list_pools", web::get().to(list_pools_handler)), // List sealed data pools
+        (
+            "/redemption_status",
+            web::get().to(redemption_status_handler),
+        ), // Check if a DRT redemption was used
+        (
+            "/delete_data_pool",
+            web::post().to(delete_data_pool_handler),
+        ), // Delete a data pool
+        (
+            "/migrate_data_pools",
+            web::post().to(migrate_data_pools_handler),
+        ), // Re-seal pools in the current format and policy
+        ("/migration_key", web::get().to(migration_key_handler)), // Key bundles are encrypted to
+        (
+            "/export_migration_bundle",
+            web::post().to(export_migration_bundle_handler),
+        ), // Hand data pools over to a newer enclave build
+        (
+            "/import_migration_bundle",
+            web::post().to(import_migration_bundle_handler),
+        ), // Take over data pools from an approved predecessor
+        ("/submit_job", web::post().to(submit_job_handler)), // Queue an execution as a job
+        ("/job_status", web::get().to(job_status_handler)), // Check the progress of a job
+        ("/job_result", web::get().to(job_result_handler)), // Collect the result of a job
+        ("/cancel_job", web::post().to(cancel_job_handler)), // Cancel an unfinished job
+    ];
+
+    for (path, route) in routes {
+        if config.endpoints.is_enabled(&path[1..]) {
+            cfg.route(path, route);
+        }
+    }
+}
+
+/// Loads and unseals the data pool with the given ID, holding its lock shared.
+///
+/// Also returns the data version recorded in the audit log: the hash of the
+/// sealed pool that was read.
+fn load_pool(registry: &PoolRegistry, pool_id: &str) -> Result<(RecordBatch, String)> {
+    let pool_lock = registry.lock(pool_id);
+    let _guard = pool_lock.read().unwrap_or_else(PoisonError::into_inner);
+    let sealed_data = registry.load(pool_id)?;
+    let contents = unseal_pool(&sealed_data, pool_id)?;
+    Ok((contents.data, hash_bytes(&sealed_data)))
+}
 
-    // Path to certificate and key files
-    let certs_file_path = "/tmp/tlscert.pem";
-    let key_file_path = "/tmp/tlskey.pem";
+/// Returns a path in the download directory that no concurrent request will use
+fn download_path(config: &Config, prefix: &str, extension: &str) -> Result<String> {
+    let file_name = format!("{}_{:016x}.{}", prefix, rand::random::<u64>(), extension);
+    config
+        .executor
+        .download_dir
+        .join(file_name)
+        .to_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("Invalid download directory"))
+}
+
+/// Deletes a downloaded binary or script
+fn remove_download(path: &str) {
+    if let Err(e) = std::fs::remove_file(path) {
+        warn!(error = %e, path, "Failed to delete temporary file");
+    }
+}
+
+fn main() -> Result<()> {
+    // Load and validate the configuration before touching any sealed data
+    let config = Config::load()?;
+
+    // Everything from here on is logged, with fields that may hold data redacted
+    logging::init(&config.logging.level, config.logging.format)?;
+    info!("Enclave created successfully, starting server");
 
     // Load RA-TLS configuration
-    let tls_config = configure_ratls(certs_file_path, key_file_path)?;
+    let (tls_config, ratls_cert) =
+        configure_ratls(&config.server.tls_cert_path, &config.server.tls_key_path)?;
+
+    // Select where sealing keys come from; only Gramine keys are bound to the enclave
+    let key_provider = config.sealing.key_provider;
+    set_key_provider(key_provider.build()?)?;
+    info!(?key_provider, "Sealing keys provided");
+    if key_provider != KeyProviderKind::Gramine {
+        warn!("Development key provider in use, sealed data is NOT protected by SGX");
+    }
+
+    // Select the enclave identity that newly sealed data is bound to
+    set_sealing_policy(config.sealing.policy)?;
+    info!(policy = ?config.sealing.policy, "Sealing policy selected");
+
+    // Enclave builds allowed to hand their data pools over to this one, and the
+    // key their bundles are encrypted to
+    let approved_predecessors = web::Data::new(parse_approved_predecessors(
+        &config.sealing.approved_predecessor_mrenclaves,
+    )?);
+    let migration_key = web::Data::new(MigrationKey::new(
+        keys::key_provider(),
+        Box::new(DcapQuoteVerifier),
+    ));
+
+    // Open the registry of sealed data pools
+    let store = open_store(&config)?;
+    info!(store = ?config.storage.backend, "Storing sealed data pools");
+    let registry = web::Data::new(PoolRegistry::new(store));
+
+    // Set up DRT redemption verification against the configured Solana RPC endpoint
+    info!(rpc_url = %config.solana.rpc_url, "Verifying DRT redemptions");
+    let verifier = web::Data::new(RedemptionVerifier::new(
+        &config.solana.rpc_url,
+        &config.solana.drt_manager_program_id,
+    )?);
+
+    // Open the ledger of redemptions that have already been used. Its watermark
+    // follows the chain, so an older ledger put back by the host can only
+    // forget the most recent redemptions.
+    let chain_time = verifier.fetch_chain_time()?;
+    let ledger = RedemptionLedger::open(
+        config.ledger_path(),
+        chain_time,
+        config.solana.max_redemption_age_secs,
+    )?;
+    info!(
+        chain_time,
+        watermark = ledger.watermark()?,
+        "Accepting DRT redemptions made after the ledger watermark"
+    );
+    let ledger = web::Data::new(ledger);
+
+    // Open the hash-chained record of operations on data pools
+    let audit = web::Data::new(AuditLog::open(
+        config.audit_log_path(),
+        audit_signing_key()?,
+    )?);
+
+    // Evidence binding the RA-TLS key and the audit log key to this enclave,
+    // served by /attestation
+    let attestation = web::Data::new(Attestation::new(
+        &ratls_cert,
+        key_provider,
+        audit.public_key(),
+    )?);
+
+    // Start the threads that run executions, downloads and sealing
+    let worker_pool = web::Data::new(WorkerPool::new(
+        config.executor.threads,
+        config.executor.max_queued_jobs,
+    )?);
+    info!(
+        threads = config.executor.threads,
+        "Running blocking work on worker threads"
+    );
+
+    // Jobs submitted for asynchronous execution
+    let jobs = web::Data::new(JobStore::new(config.executor.job_retention_secs));
+
+    // Verifies wallet signatures on requests
+    let authenticator = web::Data::new(RequestAuthenticator::new(config.auth.max_clock_skew_secs));
+
+    #[cfg(feature = "debug-endpoints")]
+    warn!("Debug endpoints enabled, data pools can leave the enclave unprocessed");
+
+    let http_workers = config.server.workers;
+    let listen_address = config.server.listen_address.clone();
+    let json_config = web::JsonConfig::default()
+        .limit(config.limits.max_request_bytes)
+        .error_handler(json_error);
+    let query_config = web::QueryConfig::default().error_handler(query_error);
+    let payload_config = web::PayloadConfig::new(config.limits.max_request_bytes);
+    let config = web::Data::new(config);
+
+    // The blocking HTTP clients above must be created outside the async runtime.
+    // Each HTTP worker runs its own event loop and blocking work goes to the
+    // worker pool, so this runtime only has to drive the server itself.
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(http_workers)
+        .enable_all()
+        .build()
+        .map_err(|e| anyhow!("Failed to start async runtime: {}", e))?;
 
     // Start the Actix Web server
-    HttpServer::new(|| {
-        App::new()
-            .route("/health", web::get().to(health_check)) // Health check route
-            .route("/execute_python", web::post().to(execute_python_handler)) // Python execution route
-            .route("/execute_wasm", web::post().to(execute_wasm_handler)) // WASM execution route
-            .route("/create_data_pool", web::post().to(create_data_pool_handler)) // Create new data pool
-            .route("/view_data", web::get().to(view_data_handler)) // View decrypted data, remove in production
-            .route("/append_data", web::post().to(append_data_handler)) // Append data into data pool
+    runtime.block_on(async move {
+        HttpServer::new(move || {
+            App::new()
+                .wrap_fn(|req, srv| {
+                    // Give every request an ID that error bodies and the response header carry
+                    let request_id = new_request_id();
+                    let started = Instant::now();
+                    let route = req
+                        .match_pattern()
+                        .unwrap_or_else(|| "unmatched".to_string());
+                    let method = req.method().to_string();
+                    // Everything logged while handling the request, including on
+                    // worker threads, is tagged with its ID
+                    let span = info_span!("request", %request_id, %method, %route);
+                    let response = span.in_scope(|| srv.call(req));
+                    async move {
+                        let response = finish_response(response.await?, &request_id);
+                        let status = response.status().as_u16();
+                        let elapsed = started.elapsed();
+                        info!(
+                            status,
+                            elapsed_ms = elapsed.as_millis() as u64,
+                            "Request handled"
+                        );
+                        metrics().observe_request(&route, &method, status, elapsed);
+                        Ok(response)
+                    }
+                    .instrument(span)
+                })
+                .app_data(config.clone())
+                .app_data(json_config.clone())
+                .app_data(query_config.clone())
+                .app_data(payload_config.clone())
+                .app_data(worker_pool.clone())
+                .app_data(jobs.clone())
+                .app_data(registry.clone())
+                .app_data(verifier.clone())
+                .app_data(ledger.clone())
+                .app_data(audit.clone())
+                .app_data(approved_predecessors.clone())
+                .app_data(migration_key.clone())
+                .app_data(authenticator.clone())
+                .app_data(attestation.clone())
+                .configure(|cfg| configure_routes(cfg, &config))
+                .default_service(web::to(|| async {
+                    Err::<HttpResponse, _>(ApiError::NotFound)
+                }))
+        })
+        .workers(http_workers)
+        .bind_rustls_0_23(listen_address, tls_config)?
+        .run()
+        .await
+        .map_err(|e| anyhow!("Actix web server error: {}", e))
     })
-    .bind_rustls_0_23("127.0.0.1:8080", tls_config)?
-    .run()
-    .await
-    .map_err(|e| anyhow!("Actix web server error: {}", e))
 }
 
 /// Request structure for the `append_data` API
 #[derive(Deserialize)]
 struct AppendDataRequest {
-    data: Value, // JSON data to append
+    pool_id: String,              // Pool PDA of the data pool to append to
+    drt_type: String,             // Type of the redeemed DRT
+    redemption_signature: String, // Signature of the `redeem_drt` transaction
+    data: Value,                  // Data to append, in the given format
+    #[serde(default)]
+    format: DataFormat, // Format of the data
+    #[serde(default)]
+    csv: CsvOptions, // How to read CSV data
 }
 
 /// Handler for the `append_data` API
-async fn append_data_handler(body: web::Json<AppendDataRequest>) -> impl Responder {
-    // TODO: Verify DRT redemption
+async fn append_data_handler(
+    worker_pool: web::Data<WorkerPool>,
+    config: web::Data<Config>,
+    registry: web::Data<PoolRegistry>,
+    verifier: web::Data<RedemptionVerifier>,
+    ledger: web::Data<RedemptionLedger>,
+    audit: web::Data<AuditLog>,
+    request: SignedJson<AppendDataRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let content_id = worker_pool
+        .run(move || {
+            append_data(
+                &config,
+                &registry,
+                &verifier,
+                &ledger,
+                &audit,
+                &request.wallet,
+                &request.body,
+            )
+        })
+        .await?;
 
-    // Unseal the existing data
-    let unsealed_data = match unseal_data() {
-        Ok(data) => data,
-        Err(e) => {
-            eprintln!("[!] Error unsealing data: {}", e);
-            return HttpResponse::InternalServerError().body("Failed to unseal data");
-        }
-    };
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Data appended, sealed, and saved successfully",
+        "content_id": content_id,
+    })))
+}
 
-    // Append the new data to the unsealed data
-    let updated_data = match append_json(&unsealed_data, &body.data) {
-        Ok(data) => data,
-        Err(e) => {
-            eprintln!("[!] Error appending JSON: {}", e);
-            return HttpResponse::InternalServerError().body("Failed to append data");
-        }
+/// Appends to a data pool, returning the content ID of the resealed pool
+fn append_data(
+    config: &Config,
+    registry: &PoolRegistry,
+    verifier: &RedemptionVerifier,
+    ledger: &RedemptionLedger,
+    audit: &AuditLog,
+    wallet: &str,
+    body: &AppendDataRequest,
+) -> Result<String, ApiError> {
+    check_pool_exists(registry, &body.pool_id)?;
+
+    // Verify the append DRT was redeemed for this pool
+    let expected = ExpectedRedemption {
+        pool: &body.pool_id,
+        drt_type: &body.drt_type,
+        execution_type: "append",
+        github_url: None,
+        code_hash: None,
     };
-
-    // Seal the updated data
-    let sealed_data = match seal_data(&updated_data, None) {
-        Ok(data) => data,
-        Err(e) => {
-            eprintln!("[!] Error sealing data: {}", e);
-            return HttpResponse::InternalServerError().body("Failed to seal data");
-        }
+    // The redemption is only spent once the data has passed every check, so
+    // data that is rejected does not cost a DRT
+    let redemption = verify_redemption(
+        verifier,
+        ledger,
+        &body.redemption_signature,
+        &expected,
+        wallet,
+    )?;
+
+    // Hold the pool exclusively so concurrent appends cannot overwrite each other
+    let pool_lock = registry.lock(&body.pool_id);
+    let _guard = pool_lock.write().unwrap_or_else(PoisonError::into_inner);
+
+    // Unseal the existing data, keeping it sealed in case the append must be undone
+    let unseal_error = |e: anyhow::Error| {
+        error!(error = %e, "Failed to unseal data");
+        ApiError::Internal("Failed to unseal data")
     };
+    let previous_sealed_data = registry.load(&body.pool_id).map_err(unseal_error)?;
+    let contents = unseal_pool(&previous_sealed_data, &body.pool_id).map_err(unseal_error)?;
+
+    // Bring records, NDJSON, CSV or Parquet into the columnar layout of the pool
+    let columns: Vec<String> = contents
+        .data
+        .schema_ref()
+        .fields()
+        .iter()
+        .map(|field| field.name().clone())
+        .collect();
+    let data = columnar_data(
+        body.data.clone(),
+        body.format,
+        &body.csv,
+        contents.schema.as_ref(),
+        Some(&columns),
+        &contents.column_policy,
+        config
+            .limits
+            .max_pool_bytes
+            .saturating_sub(contents.data_len),
+    )?;
+
+    // Reject data that the pool's schema does not allow before merging it
+    if let Some(schema) = &contents.schema {
+        check_schema(&compile_schema(schema)?, &data)?;
+    }
 
-    // TODO save to IPFS or other cloud storage
-    // Save the sealed data back to the file
-    if let Err(e) = save_to_file(&sealed_data) {
-        eprintln!("[!] Error saving sealed data: {}", e);
-        return HttpResponse::InternalServerError().body("Failed to save sealed data");
+    // Append the new data to the unsealed data
+    let updated_data = append_record_batch(
+        &contents.data,
+        &data,
+        contents.schema.as_ref(),
+        &contents.column_policy,
+    )
+    .map_err(|e| {
+        // Append errors can quote the new data, which is logged as a redacted
+        // detail
+        warn!(detail = %e, "Failed to append data");
+        ApiError::InvalidData(e.to_string())
+    })?;
+    // Only the appended rows are measured, not the whole pool again
+    let data_len =
+        appended_json_len(&contents.data, contents.data_len, &updated_data).map_err(|e| {
+            error!(error = %e, "Failed to measure data pool");
+            ApiError::Internal("Failed to serialize data")
+        })?;
+    check_pool_size(config, data_len)?;
+
+    // Columns filled in by the column policy were not part of the checked data,
+    // so check them where they ended up: the new rows, or the existing rows of
+    // added columns
+    let backfilled = data.as_object().is_some_and(|new_columns| {
+        new_columns.len() != columns.len()
+            || columns
+                .iter()
+                .any(|column| !new_columns.contains_key(column))
+    });
+    if let (true, Some(schema)) = (backfilled, &contents.schema) {
+        let merged = record_batch_to_json(&updated_data).map_err(|e| {
+            error!(error = %e, "Failed to convert data pool");
+            ApiError::Internal("Failed to convert data pool")
+        })?;
+        check_schema(&compile_schema(schema)?, &merged)?;
     }
 
-    HttpResponse::Ok().body("Data appended, sealed, and saved successfully")
+    // Seal the updated data together with the schema
+    let contents = PoolContents {
+        data: updated_data,
+        data_len,
+        ..contents
+    };
+    let sealed_data = seal_pool(&contents, &body.pool_id).map_err(|e| {
+        error!(error = %e, "Failed to seal data");
+        ApiError::Internal("Failed to seal data")
+    })?;
+
+    spend_redemption(ledger, &body.redemption_signature, &redemption)?;
+
+    // Save the sealed data back to the pool
+    let content_id = registry.save(&body.pool_id, &sealed_data).map_err(|e| {
+        error!(error = %e, "Failed to save sealed data");
+        refund_redemption(ledger, &body.redemption_signature);
+        ApiError::Internal("Failed to save sealed data")
+    })?;
+
+    // Undo the append if it cannot be recorded, so no change goes unaudited
+    let event = AuditEvent {
+        operation: AuditOperation::AppendData,
+        pool_id: &body.pool_id,
+        redeemer: Some(wallet),
+        code_hash: None,
+        data_version: &hash_bytes(&sealed_data),
+        outcome: "succeeded",
+        result: None,
+    };
+    if let Err(e) = audit_record(audit, &event) {
+        match registry.save(&body.pool_id, &previous_sealed_data) {
+            Ok(_) => refund_redemption(ledger, &body.redemption_signature),
+            Err(e) => error!(error = %e, "Failed to restore data pool after audit log failure"),
+        }
+        return Err(e);
+    }
+    Ok(content_id)
 }
 
 /// Request structure for the `create_data_pool` API
 #[derive(Deserialize)]
 struct CreateDataPoolRequest {
-    data: Value, // JSON data to be sealed
+    pool_id: String,       // Pool PDA of the new data pool
+    data: Value,           // Data to be sealed, in the given format
+    schema: Option<Value>, // JSON Schema that the data and all appends must satisfy
+    #[serde(default)]
+    column_policy: ColumnPolicy, // What appends do with new or missing columns
+    #[serde(default)]
+    format: DataFormat, // Format of the data
+    #[serde(default)]
+    csv: CsvOptions, // How to read CSV data
 }
 
 /// Handler for the `create_data_pool` API
-async fn create_data_pool_handler(body: web::Json<CreateDataPoolRequest>) -> impl Responder {
-    // TODO DRT redemption verification
+async fn create_data_pool_handler(
+    worker_pool: web::Data<WorkerPool>,
+    config: web::Data<Config>,
+    registry: web::Data<PoolRegistry>,
+    verifier: web::Data<RedemptionVerifier>,
+    audit: web::Data<AuditLog>,
+    request: SignedJson<CreateDataPoolRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let content_id = worker_pool
+        .run(move || {
+            create_data_pool(
+                &config,
+                &registry,
+                &verifier,
+                &audit,
+                &request.wallet,
+                request.body,
+            )
+        })
+        .await?;
 
-    let sealed_data = match seal_data(&body.data, None) {
-        Ok(data) => data,
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Data pool created, sealed, and saved successfully",
+        "content_id": content_id,
+    })))
+}
+
+/// Seals a new data pool, returning its content ID
+fn create_data_pool(
+    config: &Config,
+    registry: &PoolRegistry,
+    verifier: &RedemptionVerifier,
+    audit: &AuditLog,
+    wallet: &str,
+    body: CreateDataPoolRequest,
+) -> Result<String, ApiError> {
+    // Only the owner of the pool account may seal data under its ID, so nobody
+    // can claim a pool first with their own schema and column policy
+    check_pool_owner(verifier, &body.pool_id, wallet)?;
+
+    let data = columnar_data(
+        body.data,
+        body.format,
+        &body.csv,
+        body.schema.as_ref(),
+        None,
+        &body.column_policy,
+        config.limits.max_pool_bytes,
+    )?;
+    row_count(&data).map_err(|e| {
+        warn!(error = %e, "Rejected data pool with misaligned columns");
+        ApiError::InvalidData(e.to_string())
+    })?;
+    if let Some(schema) = &body.schema {
+        let schema = PoolSchema::compile(schema).map_err(|e| {
+            warn!(error = %e, "Rejected data pool schema");
+            ApiError::InvalidSchema(e.to_string())
+        })?;
+        check_schema(&schema, &data)?;
+        check_fill_value(&schema, &data, &body.column_policy)?;
+    }
+
+    // Hold the data as an Arrow table typed by the schema
+    let contents = PoolContents::new(body.schema, data, body.column_policy).map_err(|e| {
+        error!(error = %e, "Failed to convert data pool");
+        ApiError::Internal("Failed to convert data pool")
+    })?;
+    check_pool_size(config, contents.data_len)?;
+
+    // Hold the pool exclusively so two requests cannot both create it
+    let pool_lock = registry.lock(&body.pool_id);
+    let _guard = pool_lock.write().unwrap_or_else(PoisonError::into_inner);
+
+    match registry.exists(&body.pool_id) {
+        Ok(false) => {}
+        Ok(true) => return Err(ApiError::PoolExists),
         Err(e) => {
-            eprintln!("[!] Error sealing data: {}", e);
-            return HttpResponse::InternalServerError().body("Failed to seal data");
+            warn!(error = %e, "Failed to check data pool");
+            return Err(ApiError::InvalidPoolId);
         }
+    }
+
+    let sealed_data = seal_pool(&contents, &body.pool_id).map_err(|e| {
+        error!(error = %e, "Failed to seal data");
+        ApiError::Internal("Failed to seal data")
+    })?;
+
+    // Save the sealed data under the pool ID
+    let content_id = registry.save(&body.pool_id, &sealed_data).map_err(|e| {
+        error!(error = %e, "Failed to save sealed data");
+        ApiError::Internal("Failed to save sealed data")
+    })?;
+
+    // Remove the pool again if its creation cannot be recorded
+    let event = AuditEvent {
+        operation: AuditOperation::CreateDataPool,
+        pool_id: &body.pool_id,
+        redeemer: Some(wallet),
+        code_hash: None,
+        data_version: &hash_bytes(&sealed_data),
+        outcome: "succeeded",
+        result: None,
     };
+    if let Err(e) = audit_record(audit, &event) {
+        if let Err(e) = registry.delete(&body.pool_id) {
+            error!(error = %e, "Failed to remove data pool after audit log failure");
+        }
+        return Err(e);
+    }
+    Ok(content_id)
+}
+
+/// Handler for the `list_pools` API
+async fn list_pools_handler(
+    worker_pool: web::Data<WorkerPool>,
+    registry: web::Data<PoolRegistry>,
+) -> Result<HttpResponse, ApiError> {
+    let pool_ids = worker_pool
+        .run(move || {
+            registry.list().map_err(|e| {
+                error!(error = %e, "Failed to list data pools");
+                ApiError::Internal("Failed to list data pools")
+            })
+        })
+        .await?;
 
-    // TODO save to IPFS or other cloud storage
-    // Save the sealed data to the specified path
-    if let Err(e) = save_to_file(&sealed_data) {
-        eprintln!("[!] Error saving sealed data: {}", e);
-        return HttpResponse::InternalServerError().body("Failed to save sealed data");
+    Ok(HttpResponse::Ok().json(pool_ids))
+}
+
+/// Request structure for the `delete_data_pool` API
+#[derive(Deserialize)]
+struct DeleteDataPoolRequest {
+    pool_id: String, // Pool PDA of the data pool to delete
+}
+
+/// Handler for the `delete_data_pool` API
+async fn delete_data_pool_handler(
+    worker_pool: web::Data<WorkerPool>,
+    registry: web::Data<PoolRegistry>,
+    verifier: web::Data<RedemptionVerifier>,
+    audit: web::Data<AuditLog>,
+    request: SignedJson<DeleteDataPoolRequest>,
+) -> Result<HttpResponse, ApiError> {
+    worker_pool
+        .run(move || delete_data_pool(&registry, &verifier, &audit, &request.wallet, &request.body))
+        .await?;
+
+    Ok(HttpResponse::Ok().body("Data pool deleted successfully"))
+}
+
+/// Removes a data pool on behalf of its owner
+fn delete_data_pool(
+    registry: &PoolRegistry,
+    verifier: &RedemptionVerifier,
+    audit: &AuditLog,
+    wallet: &str,
+    body: &DeleteDataPoolRequest,
+) -> Result<(), ApiError> {
+    check_pool_exists(registry, &body.pool_id)?;
+    check_pool_owner(verifier, &body.pool_id, wallet)?;
+
+    let pool_lock = registry.lock(&body.pool_id);
+    let _guard = pool_lock.write().unwrap_or_else(PoisonError::into_inner);
+
+    // Keep the sealed data until the deletion is recorded, so it can be put back
+    let sealed_data = registry.load(&body.pool_id).map_err(|e| {
+        warn!(error = %e, "Failed to load data pool");
+        ApiError::PoolNotFound
+    })?;
+    registry.delete(&body.pool_id).map_err(|e| {
+        error!(error = %e, "Failed to delete data pool");
+        ApiError::Internal("Failed to delete data pool")
+    })?;
+
+    // Restore the pool if its deletion cannot be recorded
+    let event = AuditEvent {
+        operation: AuditOperation::DeleteDataPool,
+        pool_id: &body.pool_id,
+        redeemer: Some(wallet),
+        code_hash: None,
+        data_version: &hash_bytes(&sealed_data),
+        outcome: "succeeded",
+        result: None,
+    };
+    if let Err(e) = audit_record(audit, &event) {
+        if let Err(e) = registry.save(&body.pool_id, &sealed_data) {
+            error!(error = %e, "Failed to restore data pool after audit log failure");
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Request structure for the `migrate_data_pools` API
+#[derive(Deserialize)]
+struct MigrateDataPoolsRequest {
+    legacy_pool_id: Option<String>, // Pool PDA to assign to the legacy `/data/data_pool`, if any
+}
+
+/// Re-seals every pool in an older sealed format or under another sealing policy,
+/// binding it to its pool ID.
+///
+/// If `legacy_pool_id` is given, the single data pool written by earlier enclave
+/// versions to `legacy_path` is also imported into the registry under that ID.
+/// Returns the IDs of the pools that were migrated.
+fn migrate_data_pools(
+    registry: &PoolRegistry,
+    legacy_pool_id: Option<&str>,
+    legacy_path: &Path,
+) -> Result<Vec<String>> {
+    let mut migrated = Vec::new();
+
+    if let Some(pool_id) = legacy_pool_id {
+        let pool_lock = registry.lock(pool_id);
+        let _guard = pool_lock.write().unwrap_or_else(PoisonError::into_inner);
+        if registry.exists(pool_id)? {
+            return Err(anyhow!("Data pool {} already exists", pool_id));
+        }
+        let sealed_data = std::fs::read(legacy_path)
+            .map_err(|e| anyhow!("Failed to read legacy data pool: {}", e))?;
+        let contents = unseal_legacy_pool(&sealed_data)?;
+        registry.save(pool_id, &seal_pool(&contents, pool_id)?)?;
+        std::fs::remove_file(legacy_path)
+            .map_err(|e| anyhow!("Failed to remove legacy data pool: {}", e))?;
+        migrated.push(pool_id.to_string());
     }
 
-    HttpResponse::Ok().body("Data pool created, sealed, and saved successfully")
+    for pool_id in registry.list()? {
+        let pool_lock = registry.lock(&pool_id);
+        let _guard = pool_lock.write().unwrap_or_else(PoisonError::into_inner);
+        let sealed_data = registry.load(&pool_id)?;
+        if let Some(resealed) = migrate_sealed_pool(&sealed_data, &pool_id)? {
+            registry.save(&pool_id, &resealed)?;
+            migrated.push(pool_id);
+        }
+    }
+
+    Ok(migrated)
+}
+
+/// Handler for the `migrate_data_pools` API
+async fn migrate_data_pools_handler(
+    worker_pool: web::Data<WorkerPool>,
+    config: web::Data<Config>,
+    registry: web::Data<PoolRegistry>,
+    verifier: web::Data<RedemptionVerifier>,
+    request: SignedJson<MigrateDataPoolsRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let legacy_path = config.legacy_data_pool_path();
+    let legacy_pool_id = request.body.legacy_pool_id;
+    let migrated = worker_pool
+        .run(move || {
+            // The legacy pool is handed to whoever owns the given pool account,
+            // so only that owner may claim it
+            if let Some(pool_id) = &legacy_pool_id {
+                check_pool_owner(&verifier, pool_id, &request.wallet)?;
+            }
+            migrate_data_pools(&registry, legacy_pool_id.as_deref(), &legacy_path).map_err(|e| {
+                error!(error = %e, "Failed to migrate data pools");
+                ApiError::Internal("Failed to migrate data pools")
+            })
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(json!({ "migrated": migrated })))
+}
+
+/// Checks that a request is signed by one of the configured operator wallets
+fn check_operator(config: &Config, wallet: &str) -> Result<(), ApiError> {
+    if !config
+        .auth
+        .operator_wallets
+        .iter()
+        .any(|operator| operator == wallet)
+    {
+        warn!(wallet = %wallet, "Wallet is not an operator");
+        return Err(ApiError::NotOperator);
+    }
+    Ok(())
+}
+
+/// Handler for the `migration_key` API
+async fn migration_key_handler(
+    worker_pool: web::Data<WorkerPool>,
+    migration_key: web::Data<MigrationKey>,
+) -> Result<HttpResponse, ApiError> {
+    let evidence = worker_pool
+        .run(move || {
+            migration_key.evidence().map_err(|e| {
+                error!(error = %e, "Failed to attest migration key");
+                ApiError::Internal("Failed to attest migration key")
+            })
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(evidence))
+}
+
+/// Request structure for the `export_migration_bundle` API
+#[derive(Deserialize)]
+struct ExportMigrationBundleRequest {
+    target_key: TargetKey, // Migration key of the enclave build taking over the pools
+}
+
+/// Handler for the `export_migration_bundle` API
+async fn export_migration_bundle_handler(
+    worker_pool: web::Data<WorkerPool>,
+    config: web::Data<Config>,
+    registry: web::Data<PoolRegistry>,
+    ledger: web::Data<RedemptionLedger>,
+    migration_key: web::Data<MigrationKey>,
+    request: SignedJson<ExportMigrationBundleRequest>,
+) -> Result<HttpResponse, ApiError> {
+    check_operator(&config, &request.wallet)?;
+
+    let target_key = request.body.target_key;
+    let bundle = worker_pool
+        .run(move || {
+            export_bundle(&registry, &ledger, &migration_key, &target_key).map_err(|e| {
+                error!(error = %e, "Failed to export migration bundle");
+                ApiError::MigrationRejected
+            })
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(bundle))
+}
+
+/// Handler for the `import_migration_bundle` API
+async fn import_migration_bundle_handler(
+    worker_pool: web::Data<WorkerPool>,
+    config: web::Data<Config>,
+    registry: web::Data<PoolRegistry>,
+    ledger: web::Data<RedemptionLedger>,
+    migration_key: web::Data<MigrationKey>,
+    approved_predecessors: web::Data<Vec<[u8; 32]>>,
+    request: SignedJson<MigrationBundle>,
+) -> Result<HttpResponse, ApiError> {
+    check_operator(&config, &request.wallet)?;
+
+    let bundle = request.body;
+    let imported = worker_pool
+        .run(move || {
+            import_bundle(
+                &registry,
+                &ledger,
+                &migration_key,
+                &approved_predecessors,
+                &bundle,
+            )
+            .map_err(|e| {
+                error!(error = %e, "Failed to import migration bundle");
+                ApiError::MigrationRejected
+            })
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(json!({ "imported": imported })))
+}
+
+/// Query parameters for the `redemption_status` API
+#[derive(Deserialize)]
+struct RedemptionStatusQuery {
+    signature: String, // Signature of the `redeem_drt` transaction
+}
+
+/// Handler for the `redemption_status` API
+async fn redemption_status_handler(
+    worker_pool: web::Data<WorkerPool>,
+    ledger: web::Data<RedemptionLedger>,
+    query: web::Query<RedemptionStatusQuery>,
+) -> Result<HttpResponse, ApiError> {
+    // The ledger lock is also held while the ledger is re-sealed to disk
+    let signature = query.into_inner().signature;
+    let record = {
+        let signature = signature.clone();
+        worker_pool
+            .run(move || {
+                ledger.status(&signature).map_err(|e| {
+                    error!(error = %e, "Failed to read redemption ledger");
+                    ApiError::Internal("Failed to read redemption ledger")
+                })
+            })
+            .await?
+    };
+
+    Ok(match record {
+        Some(record) => HttpResponse::Ok().json(json!({
+            "signature": signature,
+            "consumed": true,
+            "pool": record.pool,
+            "drt_type": record.drt_type,
+            "execution_type": record.execution_type,
+            "redeemer": record.redeemer,
+            "consumed_at": record.consumed_at,
+        })),
+        None => HttpResponse::Ok().json(json!({
+            "signature": signature,
+            "consumed": false,
+        })),
+    })
+}
+
+/// Layout in which executed code receives the data of a pool
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum DataLayout {
+    /// `{ "col": [..] }`, as the pool is stored
+    #[default]
+    Columns,
+    /// `[{ "col": v, .. }]`, one record per row
+    Rows,
+}
+
+/// Writes the data of a pool as JSON, in the layout the executed code asked for
+fn lay_out_data(data: RecordBatch, layout: DataLayout) -> Result<String, ApiError> {
+    let mut json_data = Vec::new();
+    let written = match layout {
+        DataLayout::Columns => write_columns(&data, &mut json_data),
+        DataLayout::Rows => write_rows(&data, &mut json_data),
+    };
+    written
+        .and_then(|()| Ok(String::from_utf8(json_data)?))
+        .map_err(|e| {
+            error!(error = %e, "Failed to write data pool as JSON");
+            ApiError::Internal("Failed to write data pool as JSON")
+        })
 }
 
 /// Structure to deserialize incoming API requests
 #[derive(Deserialize)]
 struct ExecuteWasmRequest {
-    github_url: String,    // GitHub URL to the WASM binary
-    expected_hash: String, // Expected SHA256 hash of the WASM binary
-    json_schema: Value,    // JSON schema for the input data
+    pool_id: String,                            // Pool PDA of the data pool to compute on
+    drt_type: String,                           // Type of the redeemed DRT
+    redemption_signature: String,               // Signature of the `redeem_drt` transaction
+    github_url: String,                         // GitHub URL to the WASM binary
+    expected_hash: String,                      // Expected SHA256 hash of the WASM binary
+    json_schema: Value,                         // JSON schema for the input data
+    encrypt_result_to: Option<ResultRecipient>, // Key to encrypt the result to, if any
+    #[serde(default)]
+    data_layout: DataLayout, // Layout of the data passed to the binary
 }
 
 /// Handler for the `execute_wasm` API
-async fn execute_wasm_handler(body: web::Json<ExecuteWasmRequest>) -> impl Responder {
-    let github_url = &body.github_url;
-    let expected_hash = &body.expected_hash;
-    let json_schema = &body.json_schema;
+async fn execute_wasm_handler(
+    worker_pool: web::Data<WorkerPool>,
+    config: web::Data<Config>,
+    registry: web::Data<PoolRegistry>,
+    verifier: web::Data<RedemptionVerifier>,
+    ledger: web::Data<RedemptionLedger>,
+    audit: web::Data<AuditLog>,
+    request: SignedJson<ExecuteWasmRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let result = worker_pool
+        .run(move || {
+            execute_wasm(
+                &config,
+                &registry,
+                &verifier,
+                &ledger,
+                &audit,
+                &request.wallet,
+                &request.body,
+            )
+        })
+        .await?;
+
+    Ok(result_response(result)) // Return successful result
+}
+
+/// Runs a paid-for WASM computation on a data pool
+fn execute_wasm(
+    config: &Config,
+    registry: &PoolRegistry,
+    verifier: &RedemptionVerifier,
+    ledger: &RedemptionLedger,
+    audit: &AuditLog,
+    wallet: &str,
+    body: &ExecuteWasmRequest,
+) -> Result<CommittedResult, ApiError> {
+    check_pool_exists(registry, &body.pool_id)?;
+    let recipient = result_recipient(body.encrypt_result_to.as_ref())?;
+
+    // Verify a WASM compute DRT for this exact binary was redeemed for this pool
+    let expected = ExpectedRedemption {
+        pool: &body.pool_id,
+        drt_type: &body.drt_type,
+        execution_type: "wasm",
+        github_url: Some(&body.github_url),
+        code_hash: Some(&body.expected_hash),
+    };
+    // The redemption is only spent once the binary has been verified, just
+    // before it runs
+    let spent = Cell::new(false);
+    let redemption = verify_redemption(
+        verifier,
+        ledger,
+        &body.redemption_signature,
+        &expected,
+        wallet,
+    )?;
 
     // Unseal the data pool
-    match unseal_data() {
-        Ok(json_data) => {
-            match execute_wasm_binary(github_url, expected_hash, &json_data, json_schema) {
-                Ok(result) => HttpResponse::Ok().json(result), // Return successful result
-                Err(e) => {
-                    eprintln!("[!] Error executing WASM binary: {}", e);
-                    HttpResponse::InternalServerError().body(format!("WASM execution error: {}", e))
-                    // Return detailed error
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("[!] Error unsealing data: {}", e);
-            HttpResponse::InternalServerError().body("Failed to unseal data")
-        }
+    let (data, data_version) = load_pool(registry, &body.pool_id).map_err(|e| {
+        error!(error = %e, "Failed to unseal data");
+        ApiError::Internal("Failed to unseal data")
+    })?;
+    let json_data = lay_out_data(data, body.data_layout)?;
+
+    let result = execute_wasm_binary(
+        config,
+        &body.github_url,
+        &body.expected_hash,
+        &json_data,
+        &body.json_schema,
+        || {
+            spend_redemption(ledger, &body.redemption_signature, &redemption)?;
+            spent.set(true);
+            Ok(())
+        },
+    )
+    .and_then(|result| seal_result(result, recipient.as_ref()));
+    let audited = audit_execution(
+        audit,
+        AuditOperation::ExecuteWasm,
+        &body.pool_id,
+        wallet,
+        &body.expected_hash,
+        &data_version,
+        &result,
+    );
+    if spent.get() {
+        settle_execution(ledger, &body.redemption_signature, &result, &audited);
     }
+    let salt = audited?;
+    result.map(|value| CommittedResult { value, salt })
 }
 
 fn execute_wasm_binary(
+    config: &Config,
     github_url: &str,
     expected_hash: &str,
-    input_data: &Value,
+    input_data: &str,
     input_schema: &Value,
-) -> Result<Value> {
+    spend: impl FnOnce() -> Result<(), ApiError>,
+) -> Result<Value, ApiError> {
     // Temporary path to save the downloaded WASM binary
-    let wasm_path = "/tmp/downloaded_wasm.wasm";
+    let wasm_path = download_path(config, "downloaded_wasm", "wasm").map_err(|e| {
+        error!(error = %e, "Failed to prepare download");
+        ApiError::Internal("Failed to prepare download")
+    })?;
 
     // Step 1: Download and verify the WASM binary
-    verify_and_download_wasm(&github_url, wasm_path, expected_hash)
-        .map_err(|e| anyhow!("Failed to download or verify WASM binary: {}", e))?;
-
-    // Step 2: Execute the WASM binary with the data and schema
-    let result = match wasm_execution(wasm_path, input_data.clone(), input_schema.clone()) {
-        Ok(res) => res,
-        Err(e) => {
-            eprintln!("[!] WASM execution error: {}", e);
-            return Err(anyhow!("WASM execution failed with error: {}", e));
-        }
-    };
-
-    // Step 3: Delete the temporary file
-    if let Err(e) = std::fs::remove_file(wasm_path) {
-        eprintln!(
-            "[!] Warning: Failed to delete temporary file {}: {}",
-            wasm_path, e
-        );
+    let started = Instant::now();
+    let downloaded = verify_and_download_wasm(
+        github_url,
+        &wasm_path,
+        expected_hash,
+        config.limits.max_download_bytes,
+    );
+    metrics().observe_download("wasm", downloaded.is_ok(), started.elapsed());
+    downloaded.map_err(|e| {
+        warn!(error = %e, "Failed to download or verify WASM binary");
+        ApiError::CodeVerificationFailed
+    })?;
+
+    // Step 2: Pay for the execution now that the binary is the one paid for
+    if let Err(e) = spend() {
+        remove_download(&wasm_path);
+        return Err(e);
     }
 
-    // Step 4: Log and return the result
-    println!(
-        "[+] WASM Execution Result: {}",
-        serde_json::to_string_pretty(&result)?
+    // Step 3: Execute the WASM binary with the data and schema
+    let limits = WasmLimits {
+        fuel: config.executor.wasm_fuel,
+        max_memory_pages: config.executor.wasm_max_memory_pages,
+    };
+    let started = Instant::now();
+    let result = wasm_execution(
+        &wasm_path,
+        input_data.as_bytes(),
+        input_schema.clone(),
+        &limits,
     );
+    let elapsed = started.elapsed();
+
+    // Step 4: Delete the temporary file
+    remove_download(&wasm_path);
+
+    let result = result.map_err(|e| {
+        let error = wasm_error(&e);
+        // The error can quote the data the binary failed on, so it is only
+        // logged as a redacted detail
+        warn!(code = error.code(), detail = %e, "WASM execution failed");
+        error
+    });
+    let outcome = result.as_ref().map_or_else(ApiError::code, |_| "succeeded");
+    metrics().observe_execution("wasm", outcome, elapsed);
+    let result = result?;
+
+    // Step 5: Log the result in debug builds only, as host logs are not confidential
+    #[cfg(feature = "debug-endpoints")]
+    debug!(result = %result, "WASM execution finished");
     Ok(result)
 }
 
 /// Structure to deserialize incoming API requests
 #[derive(Deserialize)]
 struct ExecutePythonRequest {
-    github_url: String,    // GitHub URL to the script
-    expected_hash: String, // Expected SHA256 hash of the script
+    pool_id: String,                            // Pool PDA of the data pool to compute on
+    drt_type: String,                           // Type of the redeemed DRT
+    redemption_signature: String,               // Signature of the `redeem_drt` transaction
+    github_url: String,                         // GitHub URL to the script
+    expected_hash: String,                      // Expected SHA256 hash of the script
+    encrypt_result_to: Option<ResultRecipient>, // Key to encrypt the result to, if any
+    #[serde(default)]
+    data_layout: DataLayout, // Layout of the data passed to the script
 }
 
 /// HTTP POST handler to execute a Python script from a GitHub URL
-async fn execute_python_handler(body: web::Json<ExecutePythonRequest>) -> impl Responder {
-    let github_url = &body.github_url;
-    let expected_hash = &body.expected_hash;
+async fn execute_python_handler(
+    worker_pool: web::Data<WorkerPool>,
+    config: web::Data<Config>,
+    registry: web::Data<PoolRegistry>,
+    verifier: web::Data<RedemptionVerifier>,
+    ledger: web::Data<RedemptionLedger>,
+    audit: web::Data<AuditLog>,
+    request: SignedJson<ExecutePythonRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let result = worker_pool
+        .run(move || {
+            execute_python(
+                &config,
+                &registry,
+                &verifier,
+                &ledger,
+                &audit,
+                &request.wallet,
+                &request.body,
+            )
+        })
+        .await?;
+
+    Ok(result_response(result)) // Return the script's output
+}
 
-    // TODO: Verify DRT redemption
+/// Runs a paid-for Python computation on a data pool
+fn execute_python(
+    config: &Config,
+    registry: &PoolRegistry,
+    verifier: &RedemptionVerifier,
+    ledger: &RedemptionLedger,
+    audit: &AuditLog,
+    wallet: &str,
+    body: &ExecutePythonRequest,
+) -> Result<CommittedResult, ApiError> {
+    check_pool_exists(registry, &body.pool_id)?;
+    let recipient = result_recipient(body.encrypt_result_to.as_ref())?;
+
+    // Verify a Python compute DRT for this exact script was redeemed for this pool
+    let expected = ExpectedRedemption {
+        pool: &body.pool_id,
+        drt_type: &body.drt_type,
+        execution_type: "python",
+        github_url: Some(&body.github_url),
+        code_hash: Some(&body.expected_hash),
+    };
+    // The redemption is only spent once the script has been verified, just
+    // before it runs
+    let spent = Cell::new(false);
+    let redemption = verify_redemption(
+        verifier,
+        ledger,
+        &body.redemption_signature,
+        &expected,
+        wallet,
+    )?;
 
     // Unseal data pool
-    match unseal_data() {
-        Ok(json_data) => {
-            match execute_python_script(github_url, expected_hash, &json_data) {
-                Ok(result) => HttpResponse::Ok().json(result), // Return the script's output
-                Err(e) => {
-                    eprintln!("[!] Error executing Python script: {}", e);
-                    HttpResponse::InternalServerError().body(format!("Execution error: {}", e))
-                    // Return error details
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("[!] Error unsealing data: {}", e);
-            HttpResponse::InternalServerError().body("Failed to unseal data")
-        }
+    let (data, data_version) = load_pool(registry, &body.pool_id).map_err(|e| {
+        error!(error = %e, "Failed to unseal data");
+        ApiError::Internal("Failed to unseal data")
+    })?;
+    let json_data = lay_out_data(data, body.data_layout)?;
+
+    let result = execute_python_script(
+        config,
+        &body.github_url,
+        &body.expected_hash,
+        &json_data,
+        || {
+            spend_redemption(ledger, &body.redemption_signature, &redemption)?;
+            spent.set(true);
+            Ok(())
+        },
+    )
+    .and_then(|result| seal_result(result, recipient.as_ref()));
+    let audited = audit_execution(
+        audit,
+        AuditOperation::ExecutePython,
+        &body.pool_id,
+        wallet,
+        &body.expected_hash,
+        &data_version,
+        &result,
+    );
+    if spent.get() {
+        settle_execution(ledger, &body.redemption_signature, &result, &audited);
     }
+    let salt = audited?;
+    result.map(|value| CommittedResult { value, salt })
 }
 
 fn execute_python_script(
+    config: &Config,
     github_url: &str,
     expected_hash: &str,
-    input_data: &Value,
-) -> Result<Value> {
+    input_data: &str,
+    spend: impl FnOnce() -> Result<(), ApiError>,
+) -> Result<Value, ApiError> {
     // Temporary path to save the downloaded Python script
-    let script_path = "/tmp/downloaded_script.py";
+    let script_path = download_path(config, "downloaded_script", "py").map_err(|e| {
+        error!(error = %e, "Failed to prepare download");
+        ApiError::Internal("Failed to prepare download")
+    })?;
 
     // Step 1: Download and verify the script
-    verify_and_download_python_github(&github_url, script_path, expected_hash)
-        .map_err(|e| anyhow!("Failed to download or verify script: {}", e))?;
-
-    // Step 2: Execute the Python script
-    let result = run_python(input_data, script_path)
-        .map_err(|e| anyhow!("Python execution error: {}", e))?;
-
-    // Step 3: Delete the temporary file
-    if let Err(e) = std::fs::remove_file(script_path) {
-        eprintln!(
-            "[!] Warning: Failed to delete temporary file {}: {}",
-            script_path, e
-        );
+    let started = Instant::now();
+    let downloaded = verify_and_download_python_github(
+        github_url,
+        &script_path,
+        expected_hash,
+        config.limits.max_download_bytes,
+    );
+    metrics().observe_download("python", downloaded.is_ok(), started.elapsed());
+    downloaded.map_err(|e| {
+        warn!(error = %e, "Failed to download or verify script");
+        ApiError::CodeVerificationFailed
+    })?;
+
+    // Step 2: Pay for the execution now that the script is the one paid for
+    if let Err(e) = spend() {
+        remove_download(&script_path);
+        return Err(e);
     }
 
-    // Step 4: Format script output
-    // Debug print the result, remove in production
-    println!(
-        "[+] Python Script Result: {}",
-        serde_json::to_string_pretty(&result)?
-    );
+    // Step 3: Execute the Python script
+    let started = Instant::now();
+    let result = run_python(input_data, &script_path);
+    let elapsed = started.elapsed();
+
+    // Step 4: Delete the temporary file
+    remove_download(&script_path);
+
+    let result = result.map_err(|e| {
+        let error = python_error(&e);
+        // The error can quote the data the script failed on, so it is only
+        // logged as a redacted detail
+        warn!(code = error.code(), detail = %e, "Python execution failed");
+        error
+    });
+    let outcome = result.as_ref().map_or_else(ApiError::code, |_| "succeeded");
+    metrics().observe_execution("python", outcome, elapsed);
+    let result = result?;
+
+    // Step 5: Log the result in debug builds only, as host logs are not confidential
+    #[cfg(feature = "debug-endpoints")]
+    debug!(result = %result, "Python execution finished");
 
     Ok(result)
 }
 
-/// Decrypts and unseals the data.
-fn unseal_data() -> Result<Value> {
-    let sealed_data = std::fs::read("/data/data_pool")
-        .map_err(|e| anyhow!("Failed to read sealed data: {}", e))?;
-    if sealed_data.len() < 28 {
-        return Err(anyhow!(
-            "Invalid sealed data: insufficient length for salt, nonce, and ciphertext"
-        ));
-    }
-    let (salt, remaining) = sealed_data.split_at(16);
-    let (nonce, ciphertext) = remaining.split_at(12);
-
-    let derived_key = read_and_derive_key(salt)?;
-    let cipher = Aes128Gcm::new_from_slice(&derived_key)
-        .map_err(|e| anyhow!("Failed to initialize AES-GCM: {}", e))?;
-    let plaintext = cipher
-        .decrypt(Nonce::from_slice(nonce), ciphertext)
-        .map_err(|e| anyhow!("Decryption failed: {}", e))?;
-    serde_json::from_slice(&plaintext).map_err(|e| anyhow!("Failed to parse JSON: {}", e))
-}
-
-/// Handler for the `view_data` API
-/// Debug function - will be removed in production
-async fn view_data_handler() -> impl Responder {
-    // Unseal (decrypt) the data
-    match unseal_data() {
-        Ok(json_data) => HttpResponse::Ok().json(json_data), // Return the JSON data
-        Err(e) => {
-            eprintln!("[!] Error unsealing data: {}", e);
-            HttpResponse::InternalServerError().body("Failed to unseal data")
-        }
+/// Request structure for the `submit_job` API
+#[derive(Deserialize)]
+#[serde(tag = "execution_type", rename_all = "lowercase")]
+enum SubmitJobRequest {
+    Wasm(ExecuteWasmRequest),     // Same fields as the `execute_wasm` API
+    Python(ExecutePythonRequest), // Same fields as the `execute_python` API
+}
+
+/// Handler for the `submit_job` API
+#[allow(clippy::too_many_arguments)] // One extractor per shared service used by executions
+async fn submit_job_handler(
+    worker_pool: web::Data<WorkerPool>,
+    jobs: web::Data<JobStore>,
+    config: web::Data<Config>,
+    registry: web::Data<PoolRegistry>,
+    verifier: web::Data<RedemptionVerifier>,
+    ledger: web::Data<RedemptionLedger>,
+    audit: web::Data<AuditLog>,
+    request: SignedJson<SubmitJobRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let SignedJson { wallet, body } = request;
+    let (execution_type, pool_id) = match &body {
+        SubmitJobRequest::Wasm(request) => ("wasm", request.pool_id.clone()),
+        SubmitJobRequest::Python(request) => ("python", request.pool_id.clone()),
+    };
+    let job_id = jobs.into_inner().queue(
+        &worker_pool,
+        &wallet.clone(),
+        execution_type,
+        &pool_id,
+        move || match &body {
+            SubmitJobRequest::Wasm(request) => execute_wasm(
+                &config, &registry, &verifier, &ledger, &audit, &wallet, request,
+            ),
+            SubmitJobRequest::Python(request) => execute_python(
+                &config, &registry, &verifier, &ledger, &audit, &wallet, request,
+            ),
+        },
+    )?;
+
+    Ok(HttpResponse::Accepted().json(json!({
+        "job_id": job_id,
+        "status": "queued",
+    })))
+}
+
+/// Query parameters for the `job_status` and `job_result` APIs
+#[derive(Deserialize)]
+struct JobQuery {
+    job_id: String, // ID returned by `submit_job`
+}
+
+/// Handler for the `job_status` API
+async fn job_status_handler(
+    req: HttpRequest,
+    jobs: web::Data<JobStore>,
+    authenticator: web::Data<RequestAuthenticator>,
+    query: web::Query<JobQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let wallet = authenticator.authenticate(&req, &[])?;
+    let status = jobs.status(&query.job_id, &wallet)?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// Handler for the `job_result` API
+async fn job_result_handler(
+    req: HttpRequest,
+    jobs: web::Data<JobStore>,
+    authenticator: web::Data<RequestAuthenticator>,
+    query: web::Query<JobQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let wallet = authenticator.authenticate(&req, &[])?;
+    let result = jobs.result(&query.job_id, &wallet)?;
+    Ok(result_response(result))
+}
+
+/// Request structure for the `cancel_job` API
+#[derive(Deserialize)]
+struct CancelJobRequest {
+    job_id: String, // ID returned by `submit_job`
+}
+
+/// Handler for the `cancel_job` API
+async fn cancel_job_handler(
+    jobs: web::Data<JobStore>,
+    request: SignedJson<CancelJobRequest>,
+) -> Result<HttpResponse, ApiError> {
+    let status = jobs.cancel(&request.body.job_id, &request.wallet)?;
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// Query parameters for the `view_data` API
+#[cfg(feature = "debug-endpoints")]
+#[derive(Deserialize)]
+struct ViewDataQuery {
+    pool_id: String, // Pool PDA of the data pool to view
+}
+
+/// Handler for the `view_data` API.
+///
+/// Only compiled into debug builds. The request must be signed by the wallet
+/// that owns the pool on-chain.
+#[cfg(feature = "debug-endpoints")]
+async fn view_data_handler(
+    req: HttpRequest,
+    worker_pool: web::Data<WorkerPool>,
+    registry: web::Data<PoolRegistry>,
+    verifier: web::Data<RedemptionVerifier>,
+    authenticator: web::Data<RequestAuthenticator>,
+    query: web::Query<ViewDataQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let wallet = authenticator.authenticate(&req, &[])?;
+    let pool_id = query.into_inner().pool_id;
+    let json_data = worker_pool
+        .run(move || {
+            check_pool_exists(&registry, &pool_id)?;
+            check_pool_owner(&verifier, &pool_id, &wallet)?;
+
+            // Unseal (decrypt) the data
+            let (data, _) = load_pool(&registry, &pool_id).map_err(|e| {
+                error!(error = %e, "Failed to unseal data");
+                ApiError::Internal("Failed to unseal data")
+            })?;
+            lay_out_data(data, DataLayout::Columns)
+        })
+        .await?;
+
+    // Return the JSON data
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .body(json_data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::use_dev_keys;
+    use crate::testing::ScratchDir;
+    use sha2::{Digest, Sha256};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Barrier;
+    use std::thread;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const POOL_ID: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+    const WALLET: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+    const PROGRAM_ID: &str = "CME2Dg7UEW82Hf99rQetEi7Hc5Db9JQPx6Azmx1eWbEE";
+
+    /// Serves Solana JSON-RPC on a local port, answering every `getTransaction`
+    /// with a `redeem_drt` transaction in which `WALLET` redeemed an append DRT
+    /// for `POOL_ID`. Returns the URL to reach it at.
+    fn mock_rpc() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut content_length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    let mut body = vec![0u8; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    let request: Value = serde_json::from_slice(&body).unwrap();
+
+                    let result = match request["method"].as_str() {
+                        Some("getTransaction") => redeem_transaction(),
+                        _ => Value::Null,
+                    };
+                    let response = json!({"jsonrpc": "2.0", "id": 1, "result": result}).to_string();
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        response.len(),
+                        response
+                    )
+                    .unwrap();
+                });
+            }
+        });
+        url
+    }
+
+    /// Transaction whose logs hold the `DrtRedeemed` event of an append DRT
+    fn redeem_transaction() -> Value {
+        let string = |value: &str| {
+            let mut bytes = (value.len() as u32).to_le_bytes().to_vec();
+            bytes.extend_from_slice(value.as_bytes());
+            bytes
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let mut event = Sha256::digest(b"event:DrtRedeemed")[..8].to_vec();
+        event.extend(bs58::decode(POOL_ID).into_vec().unwrap());
+        event.extend(string("append"));
+        event.extend(string("append"));
+        event.extend(bs58::decode(WALLET).into_vec().unwrap());
+        event.extend([0, 0]); // No GitHub URL or code hash
+        event.extend(timestamp.to_le_bytes());
+
+        json!({
+            "meta": {
+                "err": null,
+                "logMessages": [
+                    format!("Program {} invoke [1]", PROGRAM_ID),
+                    format!("Program data: {}", BASE64.encode(event)),
+                    format!("Program {} success", PROGRAM_ID),
+                ],
+            },
+        })
+    }
+
+    #[actix_web::test]
+    async fn result_is_returned_with_its_salt() {
+        let salt = "ab".repeat(32);
+        let response = result_response(CommittedResult {
+            value: json!({"mean": 4.5}),
+            salt: Some(salt.clone()),
+        });
+        assert_eq!(response.headers().get(RESULT_SALT_HEADER).unwrap(), &salt);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let value: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(value, json!({"mean": 4.5}));
+
+        // The commitment covers the body exactly as it was sent
+        let mut hasher = Sha256::new();
+        hasher.update(hex::decode(&salt).unwrap());
+        hasher.update(&body);
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            audit::commit_value(&hex::decode(&salt).unwrap(), &value)
+        );
+    }
+
+    #[test]
+    fn concurrent_appends_to_one_pool_keep_every_row() {
+        use_dev_keys();
+        let dir = ScratchDir::new();
+        let config = Config::default();
+        let registry = PoolRegistry::new(Box::new(LocalStore::new(dir.0.join("pools")).unwrap()));
+        let verifier = RedemptionVerifier::new(&mock_rpc(), PROGRAM_ID).unwrap();
+        let chain_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let ledger = RedemptionLedger::open(dir.0.join("ledger"), chain_time, 3600).unwrap();
+        let audit = AuditLog::open(dir.0.join("audit"), audit_signing_key().unwrap()).unwrap();
+
+        let contents = PoolContents::legacy(json!({"n": [0]})).unwrap();
+        let sealed_data = seal_pool(&contents, POOL_ID).unwrap();
+        registry.save(POOL_ID, &sealed_data).unwrap();
+
+        const THREADS: usize = 8;
+        const APPENDS: usize = 5;
+        let barrier = Barrier::new(THREADS);
+        thread::scope(|scope| {
+            for thread in 0..THREADS {
+                let (config, registry, verifier, ledger, audit, barrier) =
+                    (&config, &registry, &verifier, &ledger, &audit, &barrier);
+                scope.spawn(move || {
+                    barrier.wait();
+                    for append in 0..APPENDS {
+                        let value = 1 + thread * APPENDS + append;
+                        let body = AppendDataRequest {
+                            pool_id: POOL_ID.to_string(),
+                            drt_type: "append".to_string(),
+                            redemption_signature: format!("signature-{}", value),
+                            data: json!({"n": [value]}),
+                            format: DataFormat::default(),
+                            csv: CsvOptions::default(),
+                        };
+                        append_data(config, registry, verifier, ledger, audit, WALLET, &body)
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        let contents = unseal_pool(&registry.load(POOL_ID).unwrap(), POOL_ID).unwrap();
+        let data = record_batch_to_json(&contents.data).unwrap();
+        let mut values: Vec<u64> = data["n"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|value| value.as_u64().unwrap())
+            .collect();
+        values.sort_unstable();
+        let expected: Vec<u64> = (0..=(THREADS * APPENDS) as u64).collect();
+        assert_eq!(values, expected);
     }
 }
@@ -0,0 +1,199 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/metrics.rs
+
+use anyhow::{anyhow, Result};
+use prometheus::{
+    exponential_buckets, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Metrics of the server, created on first use
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// Prometheus metrics served by `/metrics`.
+///
+/// Labels only ever hold routes, operation names, error codes and pool IDs,
+/// which are all public. Nothing derived from the contents of a data pool or a
+/// result is recorded, apart from the size of sealed blobs, which the host can
+/// see in the store anyway.
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,        // route, method, status
+    http_request_duration: HistogramVec, // route, method
+    sealing_duration: HistogramVec,      // operation (seal or unseal)
+    sealing_failures: IntCounterVec,     // operation
+    download_duration: HistogramVec,     // code_type (wasm or python)
+    download_failures: IntCounterVec,    // code_type
+    execution_duration: HistogramVec,    // runtime (wasm or python), outcome
+    pool_sealed_bytes: IntGaugeVec,      // pool_id
+    jobs: IntGaugeVec,                   // status (queued or running)
+}
+
+impl Metrics {
+    fn new() -> Result<Self> {
+        let registry = Registry::new_custom(Some("ntc".to_string()), None)
+            .map_err(|e| anyhow!("Failed to create metrics registry: {}", e))?;
+
+        // 1 ms to about 65 s, as executions and remote stores can be slow
+        let buckets = exponential_buckets(0.001, 2.0, 17)
+            .map_err(|e| anyhow!("Invalid histogram buckets: {}", e))?;
+        let histogram = |name: &str, help: &str, labels: &[&str]| -> Result<HistogramVec> {
+            let opts = HistogramOpts::new(name, help).buckets(buckets.clone());
+            let histogram = HistogramVec::new(opts, labels)
+                .map_err(|e| anyhow!("Failed to create metric {}: {}", name, e))?;
+            registry
+                .register(Box::new(histogram.clone()))
+                .map_err(|e| anyhow!("Failed to register metric {}: {}", name, e))?;
+            Ok(histogram)
+        };
+        let counter = |name: &str, help: &str, labels: &[&str]| -> Result<IntCounterVec> {
+            let counter = IntCounterVec::new(Opts::new(name, help), labels)
+                .map_err(|e| anyhow!("Failed to create metric {}: {}", name, e))?;
+            registry
+                .register(Box::new(counter.clone()))
+                .map_err(|e| anyhow!("Failed to register metric {}: {}", name, e))?;
+            Ok(counter)
+        };
+        let gauge = |name: &str, help: &str, labels: &[&str]| -> Result<IntGaugeVec> {
+            let gauge = IntGaugeVec::new(Opts::new(name, help), labels)
+                .map_err(|e| anyhow!("Failed to create metric {}: {}", name, e))?;
+            registry
+                .register(Box::new(gauge.clone()))
+                .map_err(|e| anyhow!("Failed to register metric {}: {}", name, e))?;
+            Ok(gauge)
+        };
+
+        Ok(Self {
+            http_requests: counter(
+                "http_requests_total",
+                "HTTP requests handled, by route, method and status",
+                &["route", "method", "status"],
+            )?,
+            http_request_duration: histogram(
+                "http_request_duration_seconds",
+                "Time taken to handle HTTP requests",
+                &["route", "method"],
+            )?,
+            sealing_duration: histogram(
+                "sealing_duration_seconds",
+                "Time taken to seal or unseal data",
+                &["operation"],
+            )?,
+            sealing_failures: counter(
+                "sealing_failures_total",
+                "Seal and unseal operations that failed",
+                &["operation"],
+            )?,
+            download_duration: histogram(
+                "download_duration_seconds",
+                "Time taken to download and verify code",
+                &["code_type"],
+            )?,
+            download_failures: counter(
+                "download_failures_total",
+                "Code downloads that failed or did not match their hash",
+                &["code_type"],
+            )?,
+            execution_duration: histogram(
+                "execution_duration_seconds",
+                "Time taken to run WASM binaries and Python scripts, by outcome",
+                &["runtime", "outcome"],
+            )?,
+            pool_sealed_bytes: gauge(
+                "pool_sealed_bytes",
+                "Size of the sealed blob of each data pool written since startup",
+                &["pool_id"],
+            )?,
+            jobs: gauge("jobs", "Jobs that are queued or running", &["status"])?,
+            registry,
+        })
+    }
+
+    /// Records a handled HTTP request. `route` is the matched route pattern, so
+    /// the number of label values stays bounded.
+    pub fn observe_request(&self, route: &str, method: &str, status: u16, elapsed: Duration) {
+        self.http_requests
+            .with_label_values(&[route, method, &status.to_string()])
+            .inc();
+        self.http_request_duration
+            .with_label_values(&[route, method])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Records a seal or unseal operation
+    pub fn observe_sealing(&self, operation: &str, succeeded: bool, elapsed: Duration) {
+        self.sealing_duration
+            .with_label_values(&[operation])
+            .observe(elapsed.as_secs_f64());
+        if !succeeded {
+            self.sealing_failures.with_label_values(&[operation]).inc();
+        }
+    }
+
+    /// Records a code download
+    pub fn observe_download(&self, code_type: &str, succeeded: bool, elapsed: Duration) {
+        self.download_duration
+            .with_label_values(&[code_type])
+            .observe(elapsed.as_secs_f64());
+        if !succeeded {
+            self.download_failures.with_label_values(&[code_type]).inc();
+        }
+    }
+
+    /// Records an execution, with `outcome` either `succeeded` or an error code
+    pub fn observe_execution(&self, runtime: &str, outcome: &str, elapsed: Duration) {
+        self.execution_duration
+            .with_label_values(&[runtime, outcome])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Records the size of the sealed blob just written for a pool
+    pub fn set_pool_size(&self, pool_id: &str, sealed_bytes: usize) {
+        self.pool_sealed_bytes
+            .with_label_values(&[pool_id])
+            .set(sealed_bytes as i64);
+    }
+
+    /// Forgets the size of a deleted pool
+    pub fn remove_pool(&self, pool_id: &str) {
+        // The pool may not have been written since startup
+        let _ = self.pool_sealed_bytes.remove_label_values(&[pool_id]);
+    }
+
+    /// Records the number of jobs that are queued and running
+    pub fn set_jobs(&self, queued: usize, running: usize) {
+        self.jobs.with_label_values(&["queued"]).set(queued as i64);
+        self.jobs
+            .with_label_values(&["running"])
+            .set(running as i64);
+    }
+
+    /// Renders all metrics in the Prometheus text format
+    pub fn render(&self) -> Result<String> {
+        TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .map_err(|e| anyhow!("Failed to encode metrics: {}", e))
+    }
+}
+
+/// Returns the metrics of the server
+pub fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(|| Metrics::new().expect("Metric definitions are valid"))
+}
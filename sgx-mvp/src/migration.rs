@@ -0,0 +1,693 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/migration.rs
+
+use crate::attestation::{QuoteBody, QuoteVerifier};
+use crate::encryption::{hpke_open, hpke_seal};
+use crate::keys::{KeyProvider, KeyProviderKind};
+use crate::ledger::{ConsumedRedemption, RedemptionLedger};
+use crate::pools::{PoolContents, PoolRegistry};
+use crate::sealing::{seal_pool, unseal_pool};
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+use tracing::warn;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// HPKE `info` every migration bundle is bound to
+const MIGRATION_INFO: &[u8] = b"ntc-migration-v1";
+
+/// Report data prefixes, so a quote over a migration key cannot pass for a
+/// quote over a bundle or the other way round
+const TARGET_KEY_DOMAIN: &[u8] = b"ntc-migration-key-v1";
+const BUNDLE_DOMAIN: &[u8] = b"ntc-migration-bundle-v1";
+
+/// Key an enclave takes migration bundles under, served by `/migration_key`
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TargetKey {
+    pub mrenclave: String,     // Hex MRENCLAVE of the enclave holding the key
+    pub public_key: String,    // Base64 X25519 public key bundles are encrypted to
+    pub quote: Option<String>, // Base64 quote binding the key, none without SGX
+}
+
+/// Data pools and consumed redemptions handed over from one enclave build to the next.
+///
+/// The contents are encrypted with HPKE to the migration key of the target,
+/// which only the attested target enclave holds, and the source attests the
+/// ciphertext with a quote of its own. The bundle can therefore be moved between
+/// enclaves by an untrusted host without exposing or forging any plaintext.
+#[derive(Serialize, Deserialize)]
+pub struct MigrationBundle {
+    pub source_mrenclave: String, // Hex MRENCLAVE of the enclave that exported the bundle
+    pub target_mrenclave: String, // Hex MRENCLAVE of the enclave allowed to import it
+    pub source_quote: Option<String>, // Base64 quote of the source over `enc` and `ciphertext`
+    pub enc: String,              // Base64 HPKE encapsulated key
+    pub ciphertext: String,       // Base64 HPKE ciphertext of the contents
+}
+
+/// Plaintext of a migration bundle
+#[derive(Serialize, Deserialize)]
+struct BundleContents {
+    pools: BTreeMap<String, String>, // Base64 encoded `PoolContents` per pool ID
+    redemptions: BTreeMap<String, ConsumedRedemption>,
+}
+
+/// Migration key of this enclave, the provider that attests it and the verifier
+/// that checks the quotes of the enclaves it exchanges bundles with.
+///
+/// A fresh key is generated every time the enclave starts and never leaves it,
+/// so a bundle can only be imported by the instance it was exported for.
+pub struct MigrationKey {
+    secret: StaticSecret,
+    key_provider: &'static dyn KeyProvider,
+    quote_verifier: Box<dyn QuoteVerifier>,
+    evidence: Mutex<Option<TargetKey>>,
+}
+
+impl MigrationKey {
+    /// Generates a migration key, attested by `key_provider` and checking peer
+    /// quotes with the given verifier
+    pub fn new(
+        key_provider: &'static dyn KeyProvider,
+        quote_verifier: Box<dyn QuoteVerifier>,
+    ) -> Self {
+        Self {
+            secret: StaticSecret::random_from_rng(rand::rngs::OsRng),
+            key_provider,
+            quote_verifier,
+            evidence: Mutex::new(None),
+        }
+    }
+
+    /// Returns the public key with the quote binding it to this enclave,
+    /// generating the quote if needed
+    pub fn evidence(&self) -> Result<TargetKey> {
+        let mut evidence = self.evidence.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(evidence) = evidence.as_ref() {
+            return Ok(evidence.clone());
+        }
+
+        let public_key = PublicKey::from(&self.secret);
+        let quote = self
+            .key_provider
+            .quote(&target_key_report_data(&public_key))?;
+        let generated = TargetKey {
+            mrenclave: hex::encode(self.key_provider.mrenclave()?),
+            public_key: BASE64.encode(public_key.as_bytes()),
+            quote: quote.map(|quote| BASE64.encode(quote)),
+        };
+        *evidence = Some(generated.clone());
+        Ok(generated)
+    }
+
+    /// Returns the report body of this enclave's own quote, or `None` without SGX
+    fn own_quote(&self) -> Result<Option<QuoteBody>> {
+        self.evidence()?
+            .quote
+            .map(|quote| QuoteBody::parse(&decode_base64("quote", &quote)?))
+            .transpose()
+    }
+
+    /// Checks that bundles may be exchanged without quotes, which is only the
+    /// case with the development key provider outside SGX
+    fn check_unattested(&self) -> Result<()> {
+        if self.key_provider.kind() != KeyProviderKind::Dev {
+            return Err(anyhow!(
+                "No quote available, and unattested migration needs the dev key provider"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Parses a hex-encoded 32-byte MRENCLAVE
+pub fn parse_mrenclave(value: &str) -> Result<[u8; 32]> {
+    let bytes =
+        hex::decode(value.trim()).map_err(|e| anyhow!("Invalid MRENCLAVE '{}': {}", value, e))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("Invalid MRENCLAVE '{}': expected 32 bytes", value))
+}
+
+/// Parses the hex MRENCLAVE values of approved predecessor enclaves
+pub fn parse_approved_predecessors(values: &[String]) -> Result<Vec<[u8; 32]>> {
+    values.iter().map(|value| parse_mrenclave(value)).collect()
+}
+
+/// Encrypts every data pool and the redemption ledger to the migration key of
+/// the target enclave.
+///
+/// The target is approved by the operator, who signs the export request
+/// naming its MRENCLAVE. Its quote must be genuine, bind the key, measure that
+/// MRENCLAVE and come from an enclave signed with the same key as this one, for
+/// the same product and at no lower security version.
+pub fn export_bundle(
+    registry: &PoolRegistry,
+    ledger: &RedemptionLedger,
+    migration_key: &MigrationKey,
+    target: &TargetKey,
+) -> Result<MigrationBundle> {
+    let public_key: [u8; 32] = decode_base64("target public key", &target.public_key)?
+        .try_into()
+        .map_err(|_| anyhow!("Target public key must be 32 bytes"))?;
+    let public_key = PublicKey::from(public_key);
+    let approved_mrenclave = parse_mrenclave(&target.mrenclave)?;
+
+    match migration_key.own_quote()? {
+        Some(own) => {
+            let quote = target
+                .quote
+                .as_deref()
+                .ok_or_else(|| anyhow!("Target migration key is not attested"))?;
+            let body = check_peer_quote(
+                migration_key.quote_verifier.as_ref(),
+                quote,
+                &target_key_report_data(&public_key),
+                &own.mrsigner,
+            )?;
+            if body.mrenclave != approved_mrenclave {
+                return Err(anyhow!(
+                    "Target enclave is {}, not the approved {}",
+                    hex::encode(body.mrenclave),
+                    target.mrenclave
+                ));
+            }
+            check_not_downgrade(&own, &body)?;
+        }
+        None => {
+            migration_key.check_unattested()?;
+            warn!("No quote available, exporting to an unverified migration key");
+        }
+    }
+
+    let mut pools = BTreeMap::new();
+    for pool_id in registry.list()? {
+        let pool_lock = registry.lock(&pool_id);
+        let _guard = pool_lock.read().unwrap_or_else(PoisonError::into_inner);
+        let contents = unseal_pool(&registry.load(&pool_id)?, &pool_id)?;
+        pools.insert(pool_id, BASE64.encode(contents.encode()?));
+    }
+    let contents = BundleContents {
+        pools,
+        redemptions: ledger.snapshot()?,
+    };
+    let plaintext = serde_json::to_vec(&contents)
+        .map_err(|e| anyhow!("Failed to serialize migration bundle: {}", e))?;
+
+    let (enc, ciphertext) = hpke_seal(&public_key, MIGRATION_INFO, &plaintext)?;
+    let source_quote = migration_key
+        .key_provider
+        .quote(&bundle_report_data(&enc, &ciphertext))?;
+
+    Ok(MigrationBundle {
+        source_mrenclave: hex::encode(migration_key.key_provider.mrenclave()?),
+        target_mrenclave: hex::encode(approved_mrenclave),
+        source_quote: source_quote.map(|quote| BASE64.encode(quote)),
+        enc: BASE64.encode(enc),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+/// Imports a bundle exported by an approved predecessor, re-sealing it under the current policy.
+///
+/// The source's quote must be genuine, cover the bundle and come from an
+/// enclave signed with the same key as this one, measured as an MRENCLAVE from
+/// `approved_predecessors`. Pools that already exist are never overwritten.
+/// Returns the IDs of the imported pools.
+pub fn import_bundle(
+    registry: &PoolRegistry,
+    ledger: &RedemptionLedger,
+    migration_key: &MigrationKey,
+    approved_predecessors: &[[u8; 32]],
+    bundle: &MigrationBundle,
+) -> Result<Vec<String>> {
+    let enc: [u8; 32] = decode_base64("encapsulated key", &bundle.enc)?
+        .try_into()
+        .map_err(|_| anyhow!("Encapsulated key must be 32 bytes"))?;
+    let ciphertext = decode_base64("ciphertext", &bundle.ciphertext)?;
+
+    let source_mrenclave = match migration_key.own_quote()? {
+        Some(own) => {
+            let quote = bundle
+                .source_quote
+                .as_deref()
+                .ok_or_else(|| anyhow!("Bundle is not attested by its source"))?;
+            let body = check_peer_quote(
+                migration_key.quote_verifier.as_ref(),
+                quote,
+                &bundle_report_data(&enc, &ciphertext),
+                &own.mrsigner,
+            )?;
+            body.mrenclave
+        }
+        None => {
+            migration_key.check_unattested()?;
+            warn!("No quote available, importing a bundle from an unverified source");
+            parse_mrenclave(&bundle.source_mrenclave)?
+        }
+    };
+    if !approved_predecessors.contains(&source_mrenclave) {
+        return Err(anyhow!(
+            "Enclave {} is not an approved predecessor",
+            hex::encode(source_mrenclave)
+        ));
+    }
+
+    // Only the holder of the migration key can decrypt, so this also checks that
+    // the bundle was exported for this enclave
+    let plaintext = hpke_open(&migration_key.secret, MIGRATION_INFO, &enc, &ciphertext)?;
+    let contents: BundleContents = serde_json::from_slice(&plaintext)
+        .map_err(|e| anyhow!("Failed to parse migration bundle: {}", e))?;
+
+    // Hold every imported pool exclusively, in key order, until it has been saved
+    let pool_locks: Vec<_> = contents.pools.keys().map(|id| registry.lock(id)).collect();
+    let _guards: Vec<_> = pool_locks
+        .iter()
+        .map(|lock| lock.write().unwrap_or_else(PoisonError::into_inner))
+        .collect();
+
+    // Decode everything up front so a bad bundle leaves no partial import behind
+    let mut pools = Vec::new();
+    for (pool_id, encoded) in &contents.pools {
+        PoolRegistry::validate_pool_id(pool_id)?;
+        if registry.exists(pool_id)? {
+            return Err(anyhow!("Data pool {} already exists", pool_id));
+        }
+        let pool = PoolContents::decode(&decode_base64("data pool", encoded)?)?;
+        pools.push((pool_id.clone(), pool));
+    }
+
+    // Merge redemptions first so none of them can be replayed against the imported pools
+    ledger.merge(contents.redemptions)?;
+
+    let mut imported = Vec::new();
+    for (pool_id, pool) in pools {
+        let sealed_data = seal_pool(&pool, &pool_id)?;
+        registry.save(&pool_id, &sealed_data)?;
+        imported.push(pool_id);
+    }
+    Ok(imported)
+}
+
+/// Checks that the quote of a peer enclave is genuine, carries the expected
+/// report data and comes from an enclave signed with the given key
+fn check_peer_quote(
+    verifier: &dyn QuoteVerifier,
+    quote: &str,
+    report_data: &[u8; 64],
+    mrsigner: &[u8; 32],
+) -> Result<QuoteBody> {
+    let body = verifier.verify_quote(&decode_base64("quote", quote)?)?;
+    if &body.report_data != report_data {
+        return Err(anyhow!("Quote does not cover the migration data"));
+    }
+    if &body.mrsigner != mrsigner {
+        return Err(anyhow!(
+            "Enclave is signed by {}, not by the signer of this enclave",
+            hex::encode(body.mrsigner)
+        ));
+    }
+    Ok(body)
+}
+
+/// Checks that a target enclave runs the same product as this one at no lower
+/// security version, so pools cannot be handed back to a build with known flaws
+fn check_not_downgrade(own: &QuoteBody, target: &QuoteBody) -> Result<()> {
+    if target.isv_prod_id != own.isv_prod_id {
+        return Err(anyhow!(
+            "Target enclave has product ID {}, not {}",
+            target.isv_prod_id,
+            own.isv_prod_id
+        ));
+    }
+    if target.isv_svn < own.isv_svn {
+        return Err(anyhow!(
+            "Target enclave has security version {}, below {}",
+            target.isv_svn,
+            own.isv_svn
+        ));
+    }
+    Ok(())
+}
+
+/// Report data binding a migration key to the enclave holding it
+fn target_key_report_data(public_key: &PublicKey) -> [u8; 64] {
+    report_data(&[TARGET_KEY_DOMAIN, public_key.as_bytes()])
+}
+
+/// Report data binding a bundle to the enclave that exported it
+fn bundle_report_data(enc: &[u8; 32], ciphertext: &[u8]) -> [u8; 64] {
+    report_data(&[BUNDLE_DOMAIN, enc, ciphertext])
+}
+
+/// Report data holding the SHA-256 hash of the given parts, zero-padded
+fn report_data(parts: &[&[u8]]) -> [u8; 64] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut report_data = [0u8; 64];
+    report_data[..32].copy_from_slice(&hasher.finalize());
+    report_data
+}
+
+/// Decodes a base64 field of a migration request
+fn decode_base64(field: &str, value: &str) -> Result<Vec<u8>> {
+    BASE64
+        .decode(value)
+        .map_err(|e| anyhow!("Invalid {}: {}", field, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::use_dev_keys;
+    use crate::sealing::SealingPolicy;
+    use crate::testing::ScratchDir;
+    use drt_verify::DrtRedeemed;
+    use sealed_store::LocalStore;
+    use serde_json::json;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const POOL_ID: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+    const SIGNATURE: &str = "5VERYLongTransactionSignature";
+    const SOURCE: [u8; 32] = [1; 32];
+    const TARGET: [u8; 32] = [3; 32];
+    const SIGNER: [u8; 32] = [2; 32];
+
+    /// Enclave that quotes its own measurements over any report data
+    struct MockKeyProvider {
+        mrenclave: [u8; 32],
+        mrsigner: [u8; 32],
+        isv_prod_id: u16,
+        isv_svn: u16,
+        kind: KeyProviderKind,
+        attested: bool, // Whether it can produce quotes at all
+    }
+
+    impl MockKeyProvider {
+        /// Attested enclave with the given MRENCLAVE, signed by `SIGNER`
+        fn sgx(mrenclave: [u8; 32], isv_svn: u16) -> &'static Self {
+            Box::leak(Box::new(Self {
+                mrenclave,
+                mrsigner: SIGNER,
+                isv_prod_id: 1,
+                isv_svn,
+                kind: KeyProviderKind::Gramine,
+                attested: true,
+            }))
+        }
+
+        /// Provider of the given kind that cannot produce quotes
+        fn unattested(mrenclave: [u8; 32], kind: KeyProviderKind) -> &'static Self {
+            Box::leak(Box::new(Self {
+                mrenclave,
+                mrsigner: SIGNER,
+                isv_prod_id: 1,
+                isv_svn: 1,
+                kind,
+                attested: false,
+            }))
+        }
+    }
+
+    impl KeyProvider for MockKeyProvider {
+        fn base_key(&self, _policy: SealingPolicy) -> Result<[u8; 16]> {
+            Ok([0; 16])
+        }
+
+        fn mrenclave(&self) -> Result<[u8; 32]> {
+            Ok(self.mrenclave)
+        }
+
+        fn quote(&self, report_data: &[u8; 64]) -> Result<Option<Vec<u8>>> {
+            if !self.attested {
+                return Ok(None);
+            }
+            let mut quote = BASE64
+                .decode(quote(self.mrenclave, self.mrsigner, report_data))
+                .unwrap();
+            quote[48 + 256..48 + 258].copy_from_slice(&self.isv_prod_id.to_le_bytes());
+            quote[48 + 258..48 + 260].copy_from_slice(&self.isv_svn.to_le_bytes());
+            Ok(Some(quote))
+        }
+
+        fn kind(&self) -> KeyProviderKind {
+            self.kind
+        }
+    }
+
+    /// Registry and ledger of one enclave instance, with its migration key
+    struct Instance {
+        registry: PoolRegistry,
+        ledger: RedemptionLedger,
+        migration_key: MigrationKey,
+        _dir: ScratchDir,
+    }
+
+    impl Instance {
+        fn new(key_provider: &'static MockKeyProvider) -> Self {
+            use_dev_keys();
+            let dir = ScratchDir::new();
+            let store = LocalStore::new(dir.0.join("pools")).unwrap();
+            Self {
+                registry: PoolRegistry::new(Box::new(store)),
+                ledger: RedemptionLedger::open(dir.0.join("ledger"), now(), 3600).unwrap(),
+                migration_key: MigrationKey::new(key_provider, Box::new(MockQuoteVerifier)),
+                _dir: dir,
+            }
+        }
+
+        /// Instance holding one pool and one consumed redemption
+        fn with_pool(key_provider: &'static MockKeyProvider) -> Self {
+            let instance = Self::new(key_provider);
+            let sealed_data = seal_pool(&pool(), POOL_ID).unwrap();
+            instance.registry.save(POOL_ID, &sealed_data).unwrap();
+            let redemption = DrtRedeemed {
+                pool: POOL_ID.to_string(),
+                drt_type: "append".to_string(),
+                execution_type: "append".to_string(),
+                redeemer: "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM".to_string(),
+                github_url: None,
+                code_hash: None,
+                timestamp: now(),
+            };
+            instance.ledger.consume(SIGNATURE, &redemption).unwrap();
+            instance
+        }
+
+        fn export_to(&self, target: &Instance) -> Result<MigrationBundle> {
+            let evidence = target.migration_key.evidence().unwrap();
+            export_bundle(&self.registry, &self.ledger, &self.migration_key, &evidence)
+        }
+
+        fn import(&self, bundle: &MigrationBundle) -> Result<Vec<String>> {
+            import_bundle(
+                &self.registry,
+                &self.ledger,
+                &self.migration_key,
+                &[SOURCE],
+                bundle,
+            )
+        }
+    }
+
+    fn pool() -> PoolContents {
+        PoolContents::legacy(json!({"id": [1, 2, 3], "name": ["a", "b", "c"]})).unwrap()
+    }
+
+    fn now() -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+    }
+
+    /// Accepts every quote, so the checks on its contents can be exercised
+    struct MockQuoteVerifier;
+
+    impl QuoteVerifier for MockQuoteVerifier {
+        fn verify_quote(&self, quote: &[u8]) -> Result<QuoteBody> {
+            QuoteBody::parse(quote)
+        }
+    }
+
+    /// Rejects every quote, like a verifier given a forged one
+    struct RejectingQuoteVerifier;
+
+    impl QuoteVerifier for RejectingQuoteVerifier {
+        fn verify_quote(&self, _quote: &[u8]) -> Result<QuoteBody> {
+            Err(anyhow!("Quote signature is invalid"))
+        }
+    }
+
+    /// Builds a base64 quote with the given measurements and report data
+    fn quote(mrenclave: [u8; 32], mrsigner: [u8; 32], report_data: &[u8; 64]) -> String {
+        let mut quote = vec![0u8; 48 + 384];
+        quote[48 + 64..48 + 96].copy_from_slice(&mrenclave);
+        quote[48 + 128..48 + 160].copy_from_slice(&mrsigner);
+        quote[48 + 320..48 + 384].copy_from_slice(report_data);
+        BASE64.encode(quote)
+    }
+
+    #[test]
+    fn accepts_quote_covering_the_data_from_the_same_signer() {
+        let public_key = PublicKey::from(&StaticSecret::random_from_rng(rand::rngs::OsRng));
+        let report_data = target_key_report_data(&public_key);
+        let quote = quote([1; 32], [2; 32], &report_data);
+
+        let body = check_peer_quote(&MockQuoteVerifier, &quote, &report_data, &[2; 32]).unwrap();
+        assert_eq!(body.mrenclave, [1; 32]);
+    }
+
+    #[test]
+    fn rejects_quote_from_another_signer() {
+        let report_data = bundle_report_data(&[3; 32], b"ciphertext");
+        let quote = quote([1; 32], [9; 32], &report_data);
+
+        assert!(check_peer_quote(&MockQuoteVerifier, &quote, &report_data, &[2; 32]).is_err());
+    }
+
+    #[test]
+    fn rejects_quote_over_other_data() {
+        let report_data = bundle_report_data(&[3; 32], b"ciphertext");
+        let quote = quote([1; 32], [2; 32], &report_data);
+
+        let swapped = bundle_report_data(&[3; 32], b"other ciphertext");
+        assert!(check_peer_quote(&MockQuoteVerifier, &quote, &swapped, &[2; 32]).is_err());
+    }
+
+    #[test]
+    fn key_and_bundle_quotes_are_not_interchangeable() {
+        let public_key = [4u8; 32];
+        let key_data = target_key_report_data(&PublicKey::from(public_key));
+        assert_ne!(key_data, bundle_report_data(&public_key, b""));
+    }
+
+    #[test]
+    fn rejects_quote_the_verifier_does_not_trust() {
+        let report_data = bundle_report_data(&[3; 32], b"ciphertext");
+        let quote = quote([1; 32], [2; 32], &report_data);
+
+        assert!(check_peer_quote(&RejectingQuoteVerifier, &quote, &report_data, &[2; 32]).is_err());
+    }
+
+    #[test]
+    fn moves_pools_and_redemptions_to_an_approved_successor() {
+        let source = Instance::with_pool(MockKeyProvider::sgx(SOURCE, 1));
+        let target = Instance::new(MockKeyProvider::sgx(TARGET, 2));
+
+        let bundle = source.export_to(&target).unwrap();
+        assert_eq!(target.import(&bundle).unwrap(), vec![POOL_ID.to_string()]);
+
+        let imported = unseal_pool(&target.registry.load(POOL_ID).unwrap(), POOL_ID).unwrap();
+        assert_eq!(imported.data, pool().data);
+        assert!(target.ledger.status(SIGNATURE).unwrap().is_some());
+    }
+
+    #[test]
+    fn export_refuses_a_target_other_than_the_approved_one() {
+        let source = Instance::with_pool(MockKeyProvider::sgx(SOURCE, 1));
+        let target = Instance::new(MockKeyProvider::sgx(TARGET, 2));
+
+        let mut evidence = target.migration_key.evidence().unwrap();
+        evidence.mrenclave = hex::encode([4u8; 32]);
+        let result = export_bundle(
+            &source.registry,
+            &source.ledger,
+            &source.migration_key,
+            &evidence,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn export_refuses_a_downgrade() {
+        let source = Instance::with_pool(MockKeyProvider::sgx(SOURCE, 2));
+        let older = Instance::new(MockKeyProvider::sgx(TARGET, 1));
+        assert!(source.export_to(&older).is_err());
+
+        let same = Instance::new(MockKeyProvider::sgx(TARGET, 2));
+        assert!(source.export_to(&same).is_ok());
+    }
+
+    #[test]
+    fn export_refuses_another_product() {
+        let source = Instance::with_pool(MockKeyProvider::sgx(SOURCE, 1));
+        let other = Instance::new(Box::leak(Box::new(MockKeyProvider {
+            isv_prod_id: 7,
+            ..*MockKeyProvider::sgx(TARGET, 2)
+        })));
+        assert!(source.export_to(&other).is_err());
+    }
+
+    #[test]
+    fn import_refuses_a_source_that_is_not_an_approved_predecessor() {
+        let source = Instance::with_pool(MockKeyProvider::sgx([5; 32], 1));
+        let target = Instance::new(MockKeyProvider::sgx(TARGET, 2));
+
+        let bundle = source.export_to(&target).unwrap();
+        assert!(target.import(&bundle).is_err());
+        assert!(!target.registry.exists(POOL_ID).unwrap());
+    }
+
+    #[test]
+    fn import_refuses_a_tampered_bundle() {
+        let source = Instance::with_pool(MockKeyProvider::sgx(SOURCE, 1));
+        let target = Instance::new(MockKeyProvider::sgx(TARGET, 2));
+
+        let mut bundle = source.export_to(&target).unwrap();
+        let mut ciphertext = BASE64.decode(&bundle.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        bundle.ciphertext = BASE64.encode(ciphertext);
+        assert!(target.import(&bundle).is_err());
+        assert!(!target.registry.exists(POOL_ID).unwrap());
+    }
+
+    #[test]
+    fn import_refuses_a_bundle_for_another_instance() {
+        let source = Instance::with_pool(MockKeyProvider::sgx(SOURCE, 1));
+        let target = Instance::new(MockKeyProvider::sgx(TARGET, 2));
+        let restarted = Instance::new(MockKeyProvider::sgx(TARGET, 2));
+
+        let bundle = source.export_to(&target).unwrap();
+        assert!(restarted.import(&bundle).is_err());
+        assert!(!restarted.registry.exists(POOL_ID).unwrap());
+    }
+
+    #[test]
+    fn unattested_migration_needs_the_dev_key_provider() {
+        let gramine = |mrenclave| MockKeyProvider::unattested(mrenclave, KeyProviderKind::Gramine);
+        let source = Instance::with_pool(gramine(SOURCE));
+        let target = Instance::new(gramine(TARGET));
+        assert!(source.export_to(&target).is_err());
+
+        let dev = |mrenclave| MockKeyProvider::unattested(mrenclave, KeyProviderKind::Dev);
+        let source = Instance::with_pool(dev(SOURCE));
+        let target = Instance::new(dev(TARGET));
+        let bundle = source.export_to(&target).unwrap();
+        assert_eq!(target.import(&bundle).unwrap(), vec![POOL_ID.to_string()]);
+
+        let refusing = Instance::new(gramine(TARGET));
+        let bundle = source.export_to(&refusing).unwrap();
+        assert!(refusing.import(&bundle).is_err());
+    }
+}
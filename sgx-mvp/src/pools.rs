@@ -0,0 +1,420 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/pools.rs
+
+use crate::metrics::metrics;
+use anyhow::{anyhow, Result};
+use arrow_array::RecordBatch;
+use arrow_ipc::reader::StreamReader;
+use arrow_ipc::writer::StreamWriter;
+use json_append::{columns_json_len, to_record_batch, ColumnPolicy};
+use sealed_store::SealedStore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError, RwLock, Weak};
+
+/// Contents of a data pool as sealed: its data, the JSON Schema that all data
+/// written to it must satisfy and how appends treat new or missing columns
+#[derive(Debug, Clone)]
+pub struct PoolContents {
+    pub schema: Option<Value>, // JSON Schema of the data, none for pools created without one
+    pub data: RecordBatch,     // Data of the pool as a typed Arrow table
+    pub data_len: usize, // Length of the data as columnar JSON, which the size limit applies to
+    pub column_policy: ColumnPolicy, // What appends do with columns only one side has
+}
+
+/// Everything about a data pool but its data, sealed as JSON
+#[derive(Serialize, Deserialize)]
+struct PoolHeader {
+    schema: Option<Value>,
+    #[serde(default)]
+    column_policy: ColumnPolicy,
+    #[serde(default)]
+    data_len: Option<usize>, // Absent in pools sealed before it was recorded
+}
+
+/// Contents of a data pool as sealed before pools were held as Arrow tables
+#[derive(Deserialize)]
+struct JsonPoolContents {
+    #[serde(flatten)]
+    header: PoolHeader,
+    data: Value,
+}
+
+impl PoolContents {
+    /// Creates the contents of a pool from columnar JSON data, typing its
+    /// columns by the schema
+    pub fn new(schema: Option<Value>, data: Value, column_policy: ColumnPolicy) -> Result<Self> {
+        let data = to_record_batch(&data, schema.as_ref())?;
+        let data_len = columns_json_len(&data)?;
+        Ok(Self {
+            schema,
+            data,
+            data_len,
+            column_policy,
+        })
+    }
+
+    /// Contents of a pool sealed before schemas and column policies, which
+    /// holds nothing but its data
+    pub fn legacy(data: Value) -> Result<Self> {
+        Self::new(None, data, ColumnPolicy::default())
+    }
+
+    /// Parses the contents of a pool sealed as JSON, with its schema and
+    /// column policy alongside its columnar data
+    pub fn from_json(plaintext: &[u8]) -> Result<Self> {
+        let contents: JsonPoolContents = serde_json::from_slice(plaintext)
+            .map_err(|e| anyhow!("Failed to parse JSON: {}", e))?;
+        let PoolHeader {
+            schema,
+            column_policy,
+            ..
+        } = contents.header;
+        Self::new(schema, contents.data, column_policy)
+    }
+
+    /// Encodes the contents for sealing as `header_len(4, little endian) |
+    /// header | data`, where the header is JSON holding the schema and column
+    /// policy and the data is an Arrow IPC stream of a single record batch
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let header = serde_json::to_vec(&PoolHeader {
+            schema: self.schema.clone(),
+            column_policy: self.column_policy.clone(),
+            data_len: Some(self.data_len),
+        })
+        .map_err(|e| anyhow!("Failed to serialize JSON: {}", e))?;
+        let header_len =
+            u32::try_from(header.len()).map_err(|_| anyhow!("Pool header is too long"))?;
+
+        let mut plaintext = Vec::new();
+        plaintext.extend_from_slice(&header_len.to_le_bytes());
+        plaintext.extend_from_slice(&header);
+        StreamWriter::try_new(&mut plaintext, &self.data.schema())
+            .and_then(|mut writer| {
+                writer.write(&self.data)?;
+                writer.finish()
+            })
+            .map_err(|e| anyhow!("Failed to write Arrow data: {}", e))?;
+        Ok(plaintext)
+    }
+
+    /// Decodes contents encoded by `encode`
+    pub fn decode(plaintext: &[u8]) -> Result<Self> {
+        let (header_len, rest) = plaintext
+            .split_first_chunk::<4>()
+            .ok_or_else(|| anyhow!("Invalid pool contents: truncated header"))?;
+        let header_len = u32::from_le_bytes(*header_len) as usize;
+        if header_len > rest.len() {
+            return Err(anyhow!("Invalid pool contents: truncated header"));
+        }
+        let (header, ipc) = rest.split_at(header_len);
+        let header: PoolHeader =
+            serde_json::from_slice(header).map_err(|e| anyhow!("Failed to parse JSON: {}", e))?;
+
+        let reader = StreamReader::try_new(ipc, None)
+            .map_err(|e| anyhow!("Failed to read Arrow data: {}", e))?;
+        let mut batches = reader
+            .collect::<Result<Vec<RecordBatch>, _>>()
+            .map_err(|e| anyhow!("Failed to read Arrow data: {}", e))?;
+        if batches.len() != 1 {
+            return Err(anyhow!(
+                "Invalid pool contents: expected one record batch, found {}",
+                batches.len()
+            ));
+        }
+        let data = batches.remove(0);
+        let data_len = match header.data_len {
+            Some(data_len) => data_len,
+            None => columns_json_len(&data)?,
+        };
+        Ok(Self {
+            schema: header.schema,
+            data,
+            data_len,
+            column_policy: header.column_policy,
+        })
+    }
+}
+
+/// Registry of sealed data pools, keyed by the on-chain `Pool` PDA of `drt-manager`
+pub struct PoolRegistry {
+    store: Box<dyn SealedStore>,
+    locks: Mutex<HashMap<String, Weak<RwLock<()>>>>,
+}
+
+impl PoolRegistry {
+    /// Creates a registry keeping its sealed blobs in `store`
+    pub fn new(store: Box<dyn SealedStore>) -> Self {
+        Self {
+            store,
+            locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the lock guarding the unseal, modify and seal cycle of the given pool.
+    ///
+    /// Anything that only reads a pool takes the lock shared, and anything that
+    /// rewrites or removes its sealed blob takes it exclusively. Locks are only
+    /// kept while someone holds them.
+    pub fn lock(&self, pool_id: &str) -> Arc<RwLock<()>> {
+        let mut locks = self.locks.lock().unwrap_or_else(PoisonError::into_inner);
+        locks.retain(|_, lock| lock.strong_count() > 0);
+
+        if let Some(lock) = locks.get(pool_id).and_then(Weak::upgrade) {
+            return lock;
+        }
+        let lock = Arc::new(RwLock::new(()));
+        locks.insert(pool_id.to_string(), Arc::downgrade(&lock));
+        lock
+    }
+
+    /// Checks that a pool ID is a base58-encoded 32-byte Solana public key.
+    ///
+    /// Since pool IDs are used as file names and object keys, this also guarantees
+    /// they cannot contain path separators or other characters that escape the
+    /// pool directory.
+    pub fn validate_pool_id(pool_id: &str) -> Result<()> {
+        let bytes = bs58::decode(pool_id)
+            .into_vec()
+            .map_err(|e| anyhow!("Invalid pool ID '{}': {}", pool_id, e))?;
+        if bytes.len() != 32 {
+            return Err(anyhow!(
+                "Invalid pool ID '{}': expected 32 bytes, got {}",
+                pool_id,
+                bytes.len()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns whether a sealed blob exists for the given pool
+    pub fn exists(&self, pool_id: &str) -> Result<bool> {
+        Self::validate_pool_id(pool_id)?;
+        self.store.exists(pool_id)
+    }
+
+    /// Writes the sealed blob for the given pool, replacing any previous one.
+    ///
+    /// Returns the content ID the store assigned to the blob.
+    pub fn save(&self, pool_id: &str, sealed_data: &[u8]) -> Result<String> {
+        Self::validate_pool_id(pool_id)?;
+        let content_id = self.store.put(pool_id, sealed_data)?;
+        metrics().set_pool_size(pool_id, sealed_data.len());
+        Ok(content_id)
+    }
+
+    /// Reads the sealed blob for the given pool
+    pub fn load(&self, pool_id: &str) -> Result<Vec<u8>> {
+        Self::validate_pool_id(pool_id)?;
+        self.store.get(pool_id)
+    }
+
+    /// Lists the IDs of all pools with a sealed blob
+    pub fn list(&self) -> Result<Vec<String>> {
+        // Skip anything that was not written by the registry
+        let mut pool_ids: Vec<String> = self
+            .store
+            .list()?
+            .into_iter()
+            .filter(|key| Self::validate_pool_id(key).is_ok())
+            .collect();
+        pool_ids.sort();
+        Ok(pool_ids)
+    }
+
+    /// Removes the sealed blob for the given pool
+    pub fn delete(&self, pool_id: &str) -> Result<()> {
+        Self::validate_pool_id(pool_id)?;
+        self.store.delete(pool_id)?;
+        metrics().remove_pool(pool_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use json_append::record_batch_to_json;
+    use serde_json::json;
+    use std::sync::Barrier;
+    use std::thread;
+
+    const POOL_ID: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+    const OTHER_POOL_ID: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+
+    /// Store holding blobs in memory
+    #[derive(Default)]
+    struct MemoryStore {
+        blobs: Mutex<HashMap<String, Vec<u8>>>,
+    }
+
+    impl SealedStore for MemoryStore {
+        fn put(&self, key: &str, sealed_data: &[u8]) -> Result<String> {
+            let mut blobs = self.blobs.lock().unwrap();
+            blobs.insert(key.to_string(), sealed_data.to_vec());
+            Ok(key.to_string())
+        }
+
+        fn get(&self, key: &str) -> Result<Vec<u8>> {
+            let blobs = self.blobs.lock().unwrap();
+            blobs.get(key).cloned().ok_or_else(|| anyhow!("No blob"))
+        }
+
+        fn exists(&self, key: &str) -> Result<bool> {
+            Ok(self.blobs.lock().unwrap().contains_key(key))
+        }
+
+        fn list(&self) -> Result<Vec<String>> {
+            Ok(self.blobs.lock().unwrap().keys().cloned().collect())
+        }
+
+        fn delete(&self, key: &str) -> Result<()> {
+            self.blobs.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+
+    fn registry() -> PoolRegistry {
+        PoolRegistry::new(Box::new(MemoryStore::default()))
+    }
+
+    #[test]
+    fn concurrent_writers_do_not_lose_updates() {
+        let registry = Arc::new(registry());
+        registry.save(POOL_ID, b"0").unwrap();
+
+        const THREADS: usize = 8;
+        const INCREMENTS: usize = 50;
+        let barrier = Arc::new(Barrier::new(THREADS));
+        let writers: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let registry = Arc::clone(&registry);
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    for _ in 0..INCREMENTS {
+                        // The same unseal, modify and seal cycle as an append
+                        let pool_lock = registry.lock(POOL_ID);
+                        let _guard = pool_lock.write().unwrap();
+                        let count: usize = String::from_utf8(registry.load(POOL_ID).unwrap())
+                            .unwrap()
+                            .parse()
+                            .unwrap();
+                        thread::yield_now();
+                        registry
+                            .save(POOL_ID, (count + 1).to_string().as_bytes())
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let count = String::from_utf8(registry.load(POOL_ID).unwrap()).unwrap();
+        assert_eq!(count, (THREADS * INCREMENTS).to_string());
+    }
+
+    #[test]
+    fn writer_excludes_readers_of_the_same_pool() {
+        let registry = registry();
+        let pool_lock = registry.lock(POOL_ID);
+        let _guard = pool_lock.write().unwrap();
+
+        assert!(registry.lock(POOL_ID).try_read().is_err());
+        assert!(registry.lock(OTHER_POOL_ID).try_write().is_ok());
+    }
+
+    #[test]
+    fn readers_share_a_pool() {
+        let registry = registry();
+        let pool_lock = registry.lock(POOL_ID);
+        let _guard = pool_lock.read().unwrap();
+
+        assert!(registry.lock(POOL_ID).try_read().is_ok());
+        assert!(registry.lock(POOL_ID).try_write().is_err());
+    }
+
+    #[test]
+    fn locks_are_dropped_once_released() {
+        let registry = registry();
+        let pool_lock = registry.lock(POOL_ID);
+        assert!(Arc::ptr_eq(&pool_lock, &registry.lock(POOL_ID)));
+        drop(pool_lock);
+
+        // Taking any lock clears out the ones nobody holds
+        let _other_lock = registry.lock(OTHER_POOL_ID);
+        let locks = registry.locks.lock().unwrap();
+        assert!(!locks.contains_key(POOL_ID));
+        assert!(locks.contains_key(OTHER_POOL_ID));
+    }
+
+    #[test]
+    fn contents_round_trip_through_encoding() {
+        let data = json!({
+            "a": [1, 2, 9007199254740993_i64],
+            "b": [0.5, null, 1],
+            "c": ["x", "y", "z"],
+        });
+        let schema = json!({
+            "type": "object",
+            "properties": { "a": { "type": "array", "items": { "type": "number" } } }
+        });
+        let contents = PoolContents::new(
+            Some(schema.clone()),
+            data.clone(),
+            ColumnPolicy::BackfillDefault(json!("-")),
+        )
+        .unwrap();
+
+        let decoded = PoolContents::decode(&contents.encode().unwrap()).unwrap();
+        assert_eq!(decoded.schema, Some(schema));
+        assert_eq!(
+            decoded.column_policy,
+            ColumnPolicy::BackfillDefault(json!("-"))
+        );
+        assert_eq!(decoded.data, contents.data);
+        assert_eq!(decoded.data_len, serde_json::to_vec(&data).unwrap().len());
+        assert_eq!(record_batch_to_json(&decoded.data).unwrap(), data);
+    }
+
+    #[test]
+    fn decode_rejects_damaged_contents() {
+        let contents = PoolContents::legacy(json!({ "a": [1] })).unwrap();
+        let encoded = contents.encode().unwrap();
+
+        assert!(PoolContents::decode(&encoded[..3]).is_err());
+        assert!(PoolContents::decode(&encoded[..encoded.len() - 1]).is_err());
+        let mut long_header = encoded.clone();
+        long_header[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(PoolContents::decode(&long_header).is_err());
+    }
+
+    #[test]
+    fn reads_contents_sealed_as_json() {
+        let plaintext = br#"{"schema":null,"column_policy":"backfill_null","data":{"a":[1,2]}}"#;
+        let contents = PoolContents::from_json(plaintext).unwrap();
+        assert_eq!(contents.column_policy, ColumnPolicy::BackfillNull);
+        assert_eq!(
+            record_batch_to_json(&contents.data).unwrap(),
+            json!({ "a": [1, 2] })
+        );
+    }
+}
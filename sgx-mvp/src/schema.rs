@@ -0,0 +1,65 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/schema.rs
+
+use anyhow::{anyhow, Result};
+use jsonschema::Validator;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Most violations reported for a single request
+pub const MAX_REPORTED_VIOLATIONS: usize = 100;
+
+/// A value that does not satisfy the schema of a data pool
+#[derive(Serialize, Debug, Clone)]
+pub struct SchemaViolation {
+    pub path: String,        // JSON pointer to the offending value, e.g. `/age/3`
+    pub schema_path: String, // JSON pointer to the schema keyword it fails
+    pub message: String,     // What is wrong with the value
+}
+
+/// JSON Schema that data written to a pool must satisfy
+pub struct PoolSchema {
+    validator: Validator,
+}
+
+impl PoolSchema {
+    /// Compiles a schema, rejecting anything that is not a valid JSON Schema.
+    ///
+    /// References are only resolved within the schema itself; the enclave never
+    /// fetches remote schemas.
+    pub fn compile(schema: &Value) -> Result<Self> {
+        jsonschema::meta::validate(schema).map_err(|e| anyhow!("Invalid JSON Schema: {}", e))?;
+        let validator =
+            jsonschema::validator_for(schema).map_err(|e| anyhow!("Invalid JSON Schema: {}", e))?;
+        Ok(Self { validator })
+    }
+
+    /// Returns the violations of the schema by `data`, at most
+    /// `MAX_REPORTED_VIOLATIONS` of them, or an empty list if it is valid
+    pub fn violations(&self, data: &Value) -> Vec<SchemaViolation> {
+        self.validator
+            .iter_errors(data)
+            .take(MAX_REPORTED_VIOLATIONS)
+            .map(|error| SchemaViolation {
+                path: error.instance_path.to_string(),
+                schema_path: error.schema_path.to_string(),
+                message: error.to_string(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,640 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/sealing.rs
+
+use crate::keys::key_provider;
+use crate::metrics::metrics;
+use crate::pools::PoolContents;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes128Gcm, KeyInit, Nonce};
+use anyhow::{anyhow, Result};
+use hkdf::Hkdf;
+use rand::RngCore;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// Magic value at the start of every versioned sealed blob
+pub const SEALED_MAGIC: &[u8; 4] = b"NTCS";
+
+/// Current version of the sealed blob format
+pub const SEALED_FORMAT_VERSION: u8 = 2;
+
+/// HKDF info label used to derive sealing keys
+pub const KDF_LABEL: &str = "sealing";
+
+/// HKDF info label used to derive the sealing keys of data pools stored
+/// together with their schema. Blobs of pools sealed before schemas were
+/// introduced use `KDF_LABEL` and hold the data alone.
+pub const POOL_KDF_LABEL: &str = "pool";
+
+/// HKDF info label used to derive the sealing keys of data pools stored as
+/// Arrow tables (see `PoolContents::encode`). Blobs of pools sealed as JSON
+/// use `POOL_KDF_LABEL`.
+pub const TABLE_KDF_LABEL: &str = "pool-table";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Policy in effect for newly sealed blobs, set once at startup
+static SEALING_POLICY: OnceLock<SealingPolicy> = OnceLock::new();
+
+/// AEAD algorithms that can protect a sealed blob
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AeadAlgorithm {
+    Aes128Gcm = 1,
+}
+
+impl AeadAlgorithm {
+    /// Looks up an algorithm by the ID stored in a sealed blob header
+    pub fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(AeadAlgorithm::Aes128Gcm),
+            other => Err(anyhow!("Unsupported AEAD algorithm ID: {}", other)),
+        }
+    }
+}
+
+/// Enclave identity a sealing key is bound to
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SealingPolicy {
+    /// Only this exact enclave build can unseal (Gramine `_sgx_mrenclave` key)
+    MrEnclave = 1,
+    /// Any enclave signed with the same key can unseal (Gramine `_sgx_mrsigner` key)
+    MrSigner = 2,
+}
+
+impl SealingPolicy {
+    /// Looks up a policy by the ID stored in a sealed blob header
+    pub fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(SealingPolicy::MrEnclave),
+            2 => Ok(SealingPolicy::MrSigner),
+            other => Err(anyhow!("Unsupported sealing policy ID: {}", other)),
+        }
+    }
+
+    /// Parses a policy name as used in configuration (`mrenclave` or `mrsigner`)
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "mrenclave" => Ok(SealingPolicy::MrEnclave),
+            "mrsigner" => Ok(SealingPolicy::MrSigner),
+            other => Err(anyhow!("Unknown sealing policy: {}", other)),
+        }
+    }
+}
+
+/// Sets the policy used for newly sealed blobs. Can only be called once.
+pub fn set_sealing_policy(policy: SealingPolicy) -> Result<()> {
+    SEALING_POLICY
+        .set(policy)
+        .map_err(|_| anyhow!("Sealing policy has already been set"))
+}
+
+/// Returns the policy used for newly sealed blobs, defaulting to MRENCLAVE
+pub fn sealing_policy() -> SealingPolicy {
+    *SEALING_POLICY.get().unwrap_or(&SealingPolicy::MrEnclave)
+}
+
+/// Layout of a sealed blob
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SealedFormat {
+    /// Original headerless layout: `salt(16) | nonce(12) | ciphertext`
+    Legacy,
+    /// Self-describing layout with a header bound as associated data
+    Versioned(u8),
+}
+
+/// Header of a versioned sealed blob.
+///
+/// Version 2 is encoded as `magic(4) | version(1) | aead_id(1) | policy_id(1) |
+/// kdf_label_len(1) | kdf_label | pool_id_len(1) | pool_id | salt(16) | nonce(12)`,
+/// followed by the ciphertext. Version 1 has no policy byte and always used the
+/// MRENCLAVE key. The encoded header is passed to AES-GCM as associated data, so
+/// none of its fields can be altered without decryption failing.
+#[derive(Debug, Clone)]
+pub struct SealedHeader {
+    pub version: u8,
+    pub aead: AeadAlgorithm,
+    pub policy: SealingPolicy,
+    pub kdf_label: String,
+    pub pool_id: String,
+    pub salt: [u8; SALT_LEN],
+    pub nonce: [u8; NONCE_LEN],
+}
+
+impl SealedHeader {
+    /// Serializes the header into its on-disk representation
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let kdf_label = self.kdf_label.as_bytes();
+        let pool_id = self.pool_id.as_bytes();
+        let kdf_label_len =
+            u8::try_from(kdf_label.len()).map_err(|_| anyhow!("KDF label is too long"))?;
+        let pool_id_len =
+            u8::try_from(pool_id.len()).map_err(|_| anyhow!("Pool ID is too long"))?;
+
+        let mut header = Vec::with_capacity(
+            SEALED_MAGIC.len() + 5 + kdf_label.len() + pool_id.len() + SALT_LEN + NONCE_LEN,
+        );
+        header.extend_from_slice(SEALED_MAGIC);
+        header.push(self.version);
+        header.push(self.aead as u8);
+        if self.version >= 2 {
+            header.push(self.policy as u8);
+        }
+        header.push(kdf_label_len);
+        header.extend_from_slice(kdf_label);
+        header.push(pool_id_len);
+        header.extend_from_slice(pool_id);
+        header.extend_from_slice(&self.salt);
+        header.extend_from_slice(&self.nonce);
+        Ok(header)
+    }
+
+    /// Parses a header from the start of a sealed blob, returning it with its encoded length
+    pub fn decode(sealed_data: &[u8]) -> Result<(Self, usize)> {
+        let mut reader = HeaderReader {
+            data: sealed_data,
+            offset: 0,
+        };
+
+        if reader.take(SEALED_MAGIC.len())? != SEALED_MAGIC {
+            return Err(anyhow!("Invalid sealed data: missing magic value"));
+        }
+        let version = reader.take(1)?[0];
+        if version == 0 || version > SEALED_FORMAT_VERSION {
+            return Err(anyhow!("Unsupported sealed data version: {}", version));
+        }
+        let aead = AeadAlgorithm::from_id(reader.take(1)?[0])?;
+        let policy = if version >= 2 {
+            SealingPolicy::from_id(reader.take(1)?[0])?
+        } else {
+            SealingPolicy::MrEnclave
+        };
+        let kdf_label = reader.take_string()?;
+        let pool_id = reader.take_string()?;
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(reader.take(SALT_LEN)?);
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(reader.take(NONCE_LEN)?);
+
+        let header = Self {
+            version,
+            aead,
+            policy,
+            kdf_label,
+            pool_id,
+            salt,
+            nonce,
+        };
+        Ok((header, reader.offset))
+    }
+}
+
+/// Cursor over the bytes of a sealed blob header
+struct HeaderReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl HeaderReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        let end = self.offset + len;
+        if end > self.data.len() {
+            return Err(anyhow!("Invalid sealed data: truncated header"));
+        }
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn take_string(&mut self) -> Result<String> {
+        let len = self.take(1)?[0] as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| anyhow!("Invalid sealed data: bad header"))
+    }
+}
+
+/// Derives a new key using HKDF with a given base key, salt, and purpose.
+fn derive_key(base_key: &[u8], salt: &[u8], label: &str) -> Result<[u8; 16]> {
+    let hkdf = Hkdf::<Sha256>::new(Some(salt), base_key);
+    let mut derived_key = [0u8; 16];
+    hkdf.expand(label.as_bytes(), &mut derived_key)
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(derived_key)
+}
+
+/// Generates a 16-byte random salt.
+fn generate_salt() -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// Fetches the base key for a policy and derives the encryption key using a salt.
+fn read_and_derive_key(policy: SealingPolicy, salt: &[u8], label: &str) -> Result<[u8; 16]> {
+    let base_key = key_provider().base_key(policy)?;
+    derive_key(&base_key, salt, label)
+}
+
+/// Detects the layout of a sealed blob.
+///
+/// Legacy blobs start with a random salt, so one that happens to begin with the
+/// magic value is still read correctly by `unseal_legacy_pool`, which falls back
+/// to the legacy layout if the versioned one does not decrypt.
+pub fn sealed_format(sealed_data: &[u8]) -> SealedFormat {
+    match SealedHeader::decode(sealed_data) {
+        Ok((header, _)) => SealedFormat::Versioned(header.version),
+        Err(_) => SealedFormat::Legacy,
+    }
+}
+
+/// Encrypts and seals the data under the current sealing policy, binding it to the given pool ID.
+///
+/// Blobs that do not belong to a data pool use an empty pool ID.
+pub fn seal_data(data: &Value, pool_id: &str) -> Result<Vec<u8>> {
+    let started = Instant::now();
+    let sealed_data = seal_with(data, pool_id, sealing_policy(), KDF_LABEL);
+    metrics().observe_sealing("seal", sealed_data.is_ok(), started.elapsed());
+    sealed_data
+}
+
+/// Encrypts and seals a data pool under the current sealing policy, binding it to its pool ID.
+pub fn seal_pool(contents: &PoolContents, pool_id: &str) -> Result<Vec<u8>> {
+    let started = Instant::now();
+    let sealed_data = contents.encode().and_then(|plaintext| {
+        seal_plaintext(&plaintext, pool_id, sealing_policy(), TABLE_KDF_LABEL)
+    });
+    metrics().observe_sealing("seal", sealed_data.is_ok(), started.elapsed());
+    sealed_data
+}
+
+/// Serializes the data to JSON, then seals it with an explicit policy and key derivation label.
+fn seal_with<T: Serialize>(
+    data: &T,
+    pool_id: &str,
+    policy: SealingPolicy,
+    kdf_label: &str,
+) -> Result<Vec<u8>> {
+    let serialized_data =
+        serde_json::to_vec(data).map_err(|e| anyhow!("Failed to serialize JSON: {}", e))?;
+    seal_plaintext(&serialized_data, pool_id, policy, kdf_label)
+}
+
+/// Encrypts and seals a plaintext with an explicit policy and key derivation label.
+fn seal_plaintext(
+    plaintext: &[u8],
+    pool_id: &str,
+    policy: SealingPolicy,
+    kdf_label: &str,
+) -> Result<Vec<u8>> {
+    // Generate a secure nonce (12 bytes as required by AES-GCM)
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+    let header = SealedHeader {
+        version: SEALED_FORMAT_VERSION,
+        aead: AeadAlgorithm::Aes128Gcm,
+        policy,
+        kdf_label: kdf_label.to_string(),
+        pool_id: pool_id.to_string(),
+        salt: generate_salt(),
+        nonce,
+    };
+    let encoded_header = header.encode()?;
+
+    // Derive the encryption key
+    let derived_key = read_and_derive_key(header.policy, &header.salt, &header.kdf_label)?;
+    let cipher = Aes128Gcm::new_from_slice(&derived_key)
+        .map_err(|e| anyhow!("Failed to initialize AES-GCM: {}", e))?;
+
+    // Encrypt the plaintext, authenticating the header alongside it
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&header.nonce),
+            Payload {
+                msg: plaintext,
+                aad: &encoded_header,
+            },
+        )
+        .map_err(|e| anyhow!("Encryption failed: {}", e))?;
+
+    // Combine header and ciphertext into a single Vec<u8>
+    let mut sealed_data = encoded_header;
+    sealed_data.extend_from_slice(&ciphertext);
+
+    Ok(sealed_data)
+}
+
+/// Decrypts and unseals the data, checking it was sealed for the given pool ID.
+///
+/// Versioned blobs are unsealed with whichever policy their header records.
+/// Blobs in the legacy headerless layout carry no pool binding, so they are
+/// refused here; the one legacy data pool is read by `unseal_legacy_pool`.
+pub fn unseal_data(sealed_data: &[u8], pool_id: &str) -> Result<Value> {
+    let started = Instant::now();
+    let data = unseal_versioned(sealed_data, pool_id, KDF_LABEL);
+    metrics().observe_sealing("unseal", data.is_ok(), started.elapsed());
+    data
+}
+
+/// Decrypts and unseals a data pool, checking it was sealed for the given pool ID.
+///
+/// Pools sealed as JSON are converted to Arrow tables, and pools sealed before
+/// schemas were introduced are returned without a schema.
+pub fn unseal_pool(sealed_data: &[u8], pool_id: &str) -> Result<PoolContents> {
+    let (header, _) = SealedHeader::decode(sealed_data)?;
+    let started = Instant::now();
+    let plaintext = decrypt_versioned(sealed_data, pool_id, &header.kdf_label);
+    metrics().observe_sealing("unseal", plaintext.is_ok(), started.elapsed());
+    match header.kdf_label.as_str() {
+        TABLE_KDF_LABEL => PoolContents::decode(&plaintext?),
+        POOL_KDF_LABEL => PoolContents::from_json(&plaintext?),
+        KDF_LABEL => PoolContents::legacy(
+            serde_json::from_slice(&plaintext?)
+                .map_err(|e| anyhow!("Failed to parse JSON: {}", e))?,
+        ),
+        other => Err(anyhow!(
+            "Sealed data uses key label '{}', not a pool label",
+            other
+        )),
+    }
+}
+
+/// Decrypts the single data pool written by enclave versions that predate pool
+/// IDs, in the legacy headerless layout.
+///
+/// A legacy blob is bound to no pool, so this must only be used to import the
+/// legacy pool file under the pool ID its owner claims for it.
+pub fn unseal_legacy_pool(sealed_data: &[u8]) -> Result<PoolContents> {
+    let started = Instant::now();
+    // A legacy blob may start with the magic value by chance, so the legacy
+    // layout is tried whatever the blob looks like
+    let data = unseal_legacy(sealed_data).map_err(|e| match sealed_format(sealed_data) {
+        SealedFormat::Legacy => e,
+        SealedFormat::Versioned(_) => anyhow!("Sealed data is not in the legacy format"),
+    });
+    metrics().observe_sealing("unseal", data.is_ok(), started.elapsed());
+    PoolContents::legacy(data?)
+}
+
+/// Decrypts a blob in the versioned layout and parses it as JSON.
+fn unseal_versioned<T: DeserializeOwned>(
+    sealed_data: &[u8],
+    pool_id: &str,
+    kdf_label: &str,
+) -> Result<T> {
+    let plaintext = decrypt_versioned(sealed_data, pool_id, kdf_label)?;
+    serde_json::from_slice(&plaintext).map_err(|e| anyhow!("Failed to parse JSON: {}", e))
+}
+
+/// Decrypts a blob in the versioned layout, returning its plaintext.
+fn decrypt_versioned(sealed_data: &[u8], pool_id: &str, kdf_label: &str) -> Result<Vec<u8>> {
+    let (header, header_len) = SealedHeader::decode(sealed_data)?;
+    if header.pool_id != pool_id {
+        return Err(anyhow!(
+            "Sealed data belongs to pool '{}', not '{}'",
+            header.pool_id,
+            pool_id
+        ));
+    }
+    if header.kdf_label != kdf_label {
+        return Err(anyhow!(
+            "Sealed data uses key label '{}', not '{}'",
+            header.kdf_label,
+            kdf_label
+        ));
+    }
+    let (encoded_header, ciphertext) = sealed_data.split_at(header_len);
+
+    let derived_key = read_and_derive_key(header.policy, &header.salt, &header.kdf_label)?;
+    match header.aead {
+        AeadAlgorithm::Aes128Gcm => {
+            let cipher = Aes128Gcm::new_from_slice(&derived_key)
+                .map_err(|e| anyhow!("Failed to initialize AES-GCM: {}", e))?;
+            cipher
+                .decrypt(
+                    Nonce::from_slice(&header.nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: encoded_header,
+                    },
+                )
+                .map_err(|e| anyhow!("Decryption failed: {}", e))
+        }
+    }
+}
+
+/// Decrypts a blob in the legacy `salt(16) | nonce(12) | ciphertext` layout.
+fn unseal_legacy(sealed_data: &[u8]) -> Result<Value> {
+    if sealed_data.len() < SALT_LEN + NONCE_LEN {
+        return Err(anyhow!(
+            "Invalid sealed data: insufficient length for salt, nonce, and ciphertext"
+        ));
+    }
+    let (salt, remaining) = sealed_data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = remaining.split_at(NONCE_LEN);
+
+    let derived_key = read_and_derive_key(SealingPolicy::MrEnclave, salt, KDF_LABEL)?;
+    let cipher = Aes128Gcm::new_from_slice(&derived_key)
+        .map_err(|e| anyhow!("Failed to initialize AES-GCM: {}", e))?;
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|e| anyhow!("Decryption failed: {}", e))?;
+    serde_json::from_slice(&plaintext).map_err(|e| anyhow!("Failed to parse JSON: {}", e))
+}
+
+/// Re-seals a blob in the current format and under the current sealing policy,
+/// binding it to the given pool ID.
+///
+/// Returns `None` if the blob is already up to date.
+pub fn migrate_sealed_data(sealed_data: &[u8], pool_id: &str) -> Result<Option<Vec<u8>>> {
+    if let Ok((header, _)) = SealedHeader::decode(sealed_data) {
+        if header.version == SEALED_FORMAT_VERSION
+            && header.policy == sealing_policy()
+            && unseal_versioned::<Value>(sealed_data, pool_id, KDF_LABEL).is_ok()
+        {
+            return Ok(None);
+        }
+    }
+    let data = unseal_data(sealed_data, pool_id)?;
+    seal_data(&data, pool_id).map(Some)
+}
+
+/// Re-seals the blob of a data pool as an Arrow table, in the current format
+/// and under the current sealing policy, together with its schema if it has one.
+///
+/// Returns `None` if the blob is already up to date.
+pub fn migrate_sealed_pool(sealed_data: &[u8], pool_id: &str) -> Result<Option<Vec<u8>>> {
+    if let Ok((header, _)) = SealedHeader::decode(sealed_data) {
+        if header.version == SEALED_FORMAT_VERSION
+            && header.policy == sealing_policy()
+            && decrypt_versioned(sealed_data, pool_id, TABLE_KDF_LABEL).is_ok()
+        {
+            return Ok(None);
+        }
+    }
+    let contents = unseal_pool(sealed_data, pool_id)?;
+    seal_pool(&contents, pool_id).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::use_dev_keys;
+    use serde_json::json;
+
+    const POOL_ID: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+    const OTHER_POOL_ID: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+
+    fn contents() -> PoolContents {
+        PoolContents::new(
+            Some(json!({ "type": "object" })),
+            json!({ "age": [31, 42], "name": ["Ada", "Bob"] }),
+            Default::default(),
+        )
+        .unwrap()
+    }
+
+    /// Seals data in the headerless layout of enclave versions that predate pool IDs
+    fn seal_legacy(data: &Value) -> Vec<u8> {
+        let salt = generate_salt();
+        let nonce = [7u8; NONCE_LEN];
+        let key = read_and_derive_key(SealingPolicy::MrEnclave, &salt, KDF_LABEL).unwrap();
+        let ciphertext = Aes128Gcm::new_from_slice(&key)
+            .unwrap()
+            .encrypt(Nonce::from_slice(&nonce), data.to_string().as_bytes())
+            .unwrap();
+        [&salt[..], &nonce, &ciphertext].concat()
+    }
+
+    #[test]
+    fn sealed_data_round_trips() {
+        use_dev_keys();
+        let data = json!({ "records": [1, 2, 3] });
+        let sealed = seal_data(&data, POOL_ID).unwrap();
+        assert_eq!(sealed_format(&sealed), SealedFormat::Versioned(2));
+        assert_eq!(unseal_data(&sealed, POOL_ID).unwrap(), data);
+
+        let contents = contents();
+        let sealed = seal_pool(&contents, POOL_ID).unwrap();
+        let unsealed = unseal_pool(&sealed, POOL_ID).unwrap();
+        assert_eq!(unsealed.data, contents.data);
+        assert_eq!(unsealed.schema, contents.schema);
+        assert_eq!(migrate_sealed_pool(&sealed, POOL_ID).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_tampered_blobs() {
+        use_dev_keys();
+        let sealed = seal_pool(&contents(), POOL_ID).unwrap();
+        let (header, header_len) = SealedHeader::decode(&sealed).unwrap();
+
+        // Every byte after the magic and version is authenticated, whether it
+        // belongs to the header or the ciphertext
+        for offset in [6, header_len - 1, header_len, sealed.len() - 1] {
+            let mut tampered = sealed.clone();
+            tampered[offset] ^= 1;
+            assert!(
+                unseal_pool(&tampered, POOL_ID).is_err(),
+                "offset {}",
+                offset
+            );
+        }
+
+        // A header re-encoded with another policy no longer decrypts
+        let relabelled = SealedHeader {
+            policy: SealingPolicy::MrSigner,
+            ..header
+        };
+        let mut tampered = relabelled.encode().unwrap();
+        tampered.extend_from_slice(&sealed[header_len..]);
+        assert!(unseal_pool(&tampered, POOL_ID).is_err());
+    }
+
+    #[test]
+    fn rejects_blob_of_another_pool() {
+        use_dev_keys();
+        let sealed = seal_pool(&contents(), POOL_ID).unwrap();
+        let error = unseal_pool(&sealed, OTHER_POOL_ID).unwrap_err();
+        assert!(error.to_string().contains("belongs to pool"), "{}", error);
+
+        let sealed = seal_data(&json!([1]), POOL_ID).unwrap();
+        assert!(unseal_data(&sealed, OTHER_POOL_ID).is_err());
+        assert!(unseal_data(&sealed, "").is_err());
+    }
+
+    #[test]
+    fn rejects_blob_sealed_under_another_label() {
+        use_dev_keys();
+        // A data pool cannot be read as another kind of sealed data
+        let sealed = seal_pool(&contents(), POOL_ID).unwrap();
+        let error = unseal_data(&sealed, POOL_ID).unwrap_err();
+        assert!(error.to_string().contains("key label"), "{}", error);
+
+        // Nor can data sealed for something else be read as a data pool
+        let sealed =
+            seal_plaintext(br#"{"a":[1]}"#, POOL_ID, SealingPolicy::MrEnclave, "audit").unwrap();
+        assert!(unseal_pool(&sealed, POOL_ID).is_err());
+    }
+
+    #[test]
+    fn reads_legacy_blobs_only_as_the_legacy_pool() {
+        use_dev_keys();
+        let data = json!({ "age": [31, 42] });
+        let sealed = seal_legacy(&data);
+        assert_eq!(sealed_format(&sealed), SealedFormat::Legacy);
+
+        let contents = unseal_legacy_pool(&sealed).unwrap();
+        assert_eq!(contents.data, PoolContents::legacy(data).unwrap().data);
+        assert!(contents.schema.is_none());
+
+        // Legacy blobs are bound to no pool, so no pool ID reads them
+        assert!(unseal_pool(&sealed, POOL_ID).is_err());
+        assert!(unseal_data(&sealed, "").is_err());
+        assert!(migrate_sealed_pool(&sealed, POOL_ID).is_err());
+
+        // And a versioned blob is not taken for the legacy pool
+        let sealed = seal_pool(&contents, POOL_ID).unwrap();
+        assert!(unseal_legacy_pool(&sealed).is_err());
+    }
+
+    #[test]
+    fn migrates_blobs_sealed_under_another_policy() {
+        use_dev_keys();
+        let contents = contents();
+        let plaintext = contents.encode().unwrap();
+        let sealed = seal_plaintext(
+            &plaintext,
+            POOL_ID,
+            SealingPolicy::MrSigner,
+            TABLE_KDF_LABEL,
+        )
+        .unwrap();
+
+        let migrated = migrate_sealed_pool(&sealed, POOL_ID).unwrap().unwrap();
+        let (header, _) = SealedHeader::decode(&migrated).unwrap();
+        assert_eq!(header.policy, sealing_policy());
+        assert_eq!(unseal_pool(&migrated, POOL_ID).unwrap().data, contents.data);
+    }
+}
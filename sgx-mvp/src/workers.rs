@@ -0,0 +1,230 @@
+// Nautilus Trusted Compute
+// Copyright (C) 2025 Nautilus
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// sgx-mvp/src/workers.rs
+
+use crate::errors::ApiError;
+use anyhow::{anyhow, Result};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use tokio::sync::oneshot;
+use tracing::{error, warn, Span};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Fixed set of threads for blocking work: downloads, executions and anything
+/// that unseals, seals or stores data.
+///
+/// Handlers hand their work to the pool instead of running it on the HTTP
+/// workers, so a long computation never stalls requests such as `/health`. The
+/// queue in front of the threads is bounded, and requests arriving while it is
+/// full are turned away rather than piling up.
+pub struct WorkerPool {
+    queue: SyncSender<Job>,
+}
+
+impl WorkerPool {
+    /// Starts `threads` worker threads behind a queue of at most `max_queued` jobs
+    pub fn new(threads: usize, max_queued: usize) -> Result<Self> {
+        let (queue, jobs) = sync_channel::<Job>(max_queued);
+        let jobs = Arc::new(Mutex::new(jobs));
+
+        for index in 0..threads {
+            let jobs = jobs.clone();
+            thread::Builder::new()
+                .name(format!("ntc-worker-{}", index))
+                .spawn(move || Self::work(&jobs))
+                .map_err(|e| anyhow!("Failed to start worker thread: {}", e))?;
+        }
+
+        Ok(Self { queue })
+    }
+
+    /// Runs jobs until the pool is dropped
+    fn work(jobs: &Mutex<Receiver<Job>>) {
+        loop {
+            let job = jobs.lock().unwrap_or_else(PoisonError::into_inner).recv();
+            match job {
+                // Keep the worker thread alive if the job panics
+                Ok(job) => {
+                    if catch_unwind(AssertUnwindSafe(job)).is_err() {
+                        error!("Worker thread panicked while running a job");
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Queues `job` to run on a worker thread without waiting for it.
+    ///
+    /// Fails with `503 Service Unavailable` if the queue is full.
+    pub fn spawn<F>(&self, job: F) -> Result<(), ApiError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        // Log lines of the job belong to the request that queued it
+        let span = Span::current();
+        let job = move || {
+            let _entered = span.enter();
+            job()
+        };
+        match self.queue.try_send(Box::new(job)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                warn!("Worker pool queue is full, rejecting request");
+                Err(ApiError::Busy)
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                error!("Worker pool has shut down");
+                Err(ApiError::Internal("Server is shutting down"))
+            }
+        }
+    }
+
+    /// Runs `job` on a worker thread and waits for its result.
+    ///
+    /// Fails with `503 Service Unavailable` if the queue is full. A job that has
+    /// started always runs to completion, even if the client goes away, so that
+    /// consumed redemptions and sealed writes are never left half done.
+    pub async fn run<F, T>(&self, job: F) -> Result<T, ApiError>
+    where
+        F: FnOnce() -> Result<T, ApiError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.spawn(move || {
+            let _ = sender.send(job());
+        })?;
+
+        // The sender is dropped without a result if the job panicked
+        receiver
+            .await
+            .unwrap_or(Err(ApiError::Internal("Internal server error")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::{channel, Sender};
+    use std::time::{Duration, Instant};
+
+    /// Occupies a worker thread until the returned sender is dropped
+    fn block_worker(pool: &WorkerPool) -> Sender<()> {
+        let (release, blocked) = channel::<()>();
+        let (started, wait_started) = channel();
+        pool.spawn(move || {
+            started.send(()).unwrap();
+            let _ = blocked.recv();
+        })
+        .unwrap();
+        wait_started.recv().unwrap();
+        release
+    }
+
+    #[tokio::test]
+    async fn returns_the_result_of_a_job() {
+        let pool = WorkerPool::new(1, 4).unwrap();
+        assert_eq!(pool.run(|| Ok(6 * 7)).await.unwrap(), 42);
+        assert!(matches!(
+            pool.run(|| Err::<(), _>(ApiError::PoolNotFound)).await,
+            Err(ApiError::PoolNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn requests_are_answered_while_a_job_runs() {
+        let pool = Arc::new(WorkerPool::new(1, 4).unwrap());
+        let (release, blocked) = channel::<()>();
+
+        // A long execution awaited on the same single-threaded runtime
+        let execution = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                pool.run(move || {
+                    let _ = blocked.recv();
+                    Ok("executed")
+                })
+                .await
+            }
+        });
+
+        // A request that needs no worker, like /health, is still answered at once
+        let health = tokio::spawn(async { "ok" });
+        let answered = tokio::time::timeout(Duration::from_secs(1), health).await;
+        assert_eq!(answered.unwrap().unwrap(), "ok");
+        assert!(!execution.is_finished());
+
+        drop(release);
+        assert_eq!(execution.await.unwrap().unwrap(), "executed");
+    }
+
+    #[tokio::test]
+    async fn queued_job_runs_once_a_worker_is_free() {
+        let pool = WorkerPool::new(1, 4).unwrap();
+        let release = block_worker(&pool);
+
+        let queued = pool.run(|| Ok("done"));
+        drop(release);
+        let result = tokio::time::timeout(Duration::from_secs(5), queued).await;
+        assert_eq!(result.unwrap().unwrap(), "done");
+    }
+
+    #[tokio::test]
+    async fn rejects_jobs_when_the_queue_is_full() {
+        let pool = WorkerPool::new(1, 1).unwrap();
+        let _release = block_worker(&pool);
+        pool.spawn(|| {}).unwrap();
+
+        let started = Instant::now();
+        assert!(matches!(pool.run(|| Ok(())).await, Err(ApiError::Busy)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn worker_survives_a_panicking_job() {
+        let pool = WorkerPool::new(1, 4).unwrap();
+        let panicked = pool.run(|| -> Result<(), ApiError> { panic!("job failed") });
+        assert!(matches!(panicked.await, Err(ApiError::Internal(_))));
+        assert_eq!(pool.run(|| Ok(1)).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn started_job_finishes_after_the_caller_goes_away() {
+        let pool = WorkerPool::new(1, 4).unwrap();
+        let finished = Arc::new(AtomicBool::new(false));
+        let (started, wait_started) = channel();
+
+        let job_finished = finished.clone();
+        let run = pool.run(move || {
+            started.send(()).unwrap();
+            thread::sleep(Duration::from_millis(50));
+            job_finished.store(true, Ordering::SeqCst);
+            Ok(())
+        });
+        // Polling once queues the job; dropping the future is a client disconnecting
+        let _ = tokio::time::timeout(Duration::from_millis(1), run).await;
+        wait_started.recv().unwrap();
+
+        // The next job only runs once the abandoned one has finished
+        pool.run(|| Ok(())).await.unwrap();
+        assert!(finished.load(Ordering::SeqCst));
+    }
+}
@@ -19,11 +19,12 @@
 use anyhow::{anyhow, Result};
 use serde_json::Value as JsonValue;
 use std::fs;
-use wasmi::{Engine, Linker, Memory, MemoryType, Module, Store, Val};
+use tracing::warn;
+use wasmi::{Config, Engine, Linker, Memory, MemoryType, Module, Store, Val};
 
 /// Define the error codes returned by the WASM modules
 #[repr(i32)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WasmErrorCode {
     Success = 0,
     ParseInputData = 1,
@@ -85,26 +86,46 @@ impl std::fmt::Display for WasmErrorCode {
 
 impl std::error::Error for WasmErrorCode {}
 
+/// Resource limits applied to a single WASM execution
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WasmLimits {
+    pub fuel: Option<u64>, // Fuel the module may consume, unlimited if `None`
+    pub max_memory_pages: Option<u32>, // Maximum linear memory in 64 KiB pages, unlimited if `None`
+}
+
 /// Executes a WASM binary with the provided JSON data and schema.
 ///
 /// # Arguments
 ///
 /// * `binary` - Path to the WASM binary.
-/// * `data` - JSON data as `serde_json::Value`.
+/// * `data` - JSON data, already serialized.
 /// * `schema` - JSON schema as `serde_json::Value`.
+/// * `limits` - Fuel and memory limits for the execution.
 ///
 /// # Returns
 ///
 /// * `Ok(JsonValue)` containing the result if execution is successful.
 /// * `Err(anyhow::Error)` containing the error if an error occurs.
-pub fn wasm_execution(binary: &str, data: JsonValue, schema: JsonValue) -> Result<JsonValue> {
+pub fn wasm_execution(
+    binary: &str,
+    data: &[u8],
+    schema: JsonValue,
+    limits: &WasmLimits,
+) -> Result<JsonValue> {
     // Load the WASM binary
     let wasm_binary =
         fs::read(binary).map_err(|e| anyhow!("Failed to read WASM binary '{}': {}", binary, e))?;
 
-    // Create an engine and store
-    let engine = Engine::default();
+    // Create an engine and store, metering fuel if the execution is limited
+    let mut config = Config::default();
+    config.consume_fuel(limits.fuel.is_some());
+    let engine = Engine::new(&config);
     let mut store = Store::new(&engine, ());
+    if let Some(fuel) = limits.fuel {
+        store
+            .set_fuel(fuel)
+            .map_err(|e| anyhow!("Failed to set fuel: {}", e))?;
+    }
 
     // Compile the module
     let module = Module::new(&engine, &wasm_binary)
@@ -114,8 +135,8 @@ pub fn wasm_execution(binary: &str, data: JsonValue, schema: JsonValue) -> Resul
     let mut linker = Linker::new(&engine);
 
     // Create a memory type (minimum 17 pages = 17 * 64KB = 1,088 KB)
-    let memory_type =
-        MemoryType::new(17, None).map_err(|e| anyhow!("Failed to create memory type: {}", e))?;
+    let memory_type = MemoryType::new(17, limits.max_memory_pages)
+        .map_err(|e| anyhow!("Failed to create memory type: {}", e))?;
 
     // Create a memory instance and add it to the linker
     let memory = Memory::new(&mut store, memory_type)
@@ -134,18 +155,15 @@ pub fn wasm_execution(binary: &str, data: JsonValue, schema: JsonValue) -> Resul
         .ensure_no_start(&mut store)
         .map_err(|e| anyhow!("Failed to ensure no start: {}", e))?;
 
-    // Serialize input data and schema
-    let data_bytes =
-        serde_json::to_vec(&data).map_err(|e| anyhow!("Failed to serialize input data: {}", e))?;
-
+    // Serialize the input schema
     let schema_bytes = serde_json::to_vec(&schema)
         .map_err(|e| anyhow!("Failed to serialize input schema: {}", e))?;
 
     // Write data into WASM memory
     let data_ptr: u32 = 0;
-    let data_len = data_bytes.len() as u32;
+    let data_len = data.len() as u32;
     memory
-        .write(&mut store, data_ptr as usize, &data_bytes)
+        .write(&mut store, data_ptr as usize, data)
         .map_err(|e| anyhow!("Failed to write input data to memory: {}", e))?;
 
     let schema_ptr = data_ptr + data_len;
@@ -205,9 +223,9 @@ pub fn wasm_execution(binary: &str, data: JsonValue, schema: JsonValue) -> Resul
         // Map the error code to a WasmErrorCode variant
         let wasm_error = WasmErrorCode::from_code(result_code);
         // Log the error for debugging
-        eprintln!("[!] WASM Execution failed with error: {}", wasm_error);
-        // Return the error as anyhow::Error
-        return Err(anyhow!(wasm_error.to_string()));
+        warn!(error = %wasm_error, "WASM execution failed");
+        // Return the error code itself, so callers can tell it apart from other failures
+        return Err(wasm_error.into());
     }
 
     // Read the actual output length from WASM memory